@@ -0,0 +1,97 @@
+//! Models which are queried by `pg-worm-service`.
+
+use pg_worm::prelude::*;
+
+/// A shelf holding books.
+#[derive(Model, Debug)]
+pub struct Shelf {
+    #[column(primary_key, auto)]
+    pub id: i64,
+    #[column(unique, max_length = 20)]
+    pub label: String,
+}
+
+/// A book on a shelf.
+#[derive(Model, Debug)]
+#[table(order_by = "title")]
+pub struct Volume {
+    #[column(primary_key, auto)]
+    pub id: i64,
+    pub shelf_id: i64,
+    pub title: String,
+    #[column(encode_with = "encode_note", decode_with = "decode_note")]
+    pub note: Option<String>,
+    #[column(sensitive)]
+    pub(crate) secret: Option<String>,
+}
+
+fn encode_note(note: &Option<String>) -> Option<String> {
+    note.as_ref().map(|note| note.to_uppercase())
+}
+
+fn decode_note(note: Option<String>) -> Result<Option<String>, pg_worm::Error> {
+    Ok(note.map(|note| note.to_lowercase()))
+}
+
+/// Models derived without the standard prelude, so the generated
+/// code can't rely on any names being in scope.
+pub mod hygiene {
+    #![no_implicit_prelude]
+
+    /// A label for a shelf.
+    #[derive(::pg_worm::Model, Debug)]
+    #[table(
+        archive_to = "tag_archive",
+        unique = "shelf_id, name",
+        order_by = "name DESC",
+        before_insert = "trim_name",
+        after_load = "count_aliases"
+    )]
+    pub struct Tag {
+        #[column(primary_key, auto)]
+        pub id: i64,
+        pub shelf_id: ::std::option::Option<i64>,
+        #[column(index, max_length = 30)]
+        pub name: ::std::string::String,
+        #[column(encode_with = "super::encode_note", decode_with = "super::decode_note")]
+        pub note: ::std::option::Option<::std::string::String>,
+        #[column(sensitive)]
+        pub aliases: ::std::vec::Vec<::std::string::String>,
+        #[column(updated_at)]
+        pub updated_at: ::std::time::SystemTime,
+        #[column(skip)]
+        pub alias_count: usize,
+    }
+
+    /// The archive of [`Tag`].
+    #[derive(::pg_worm::Model, Debug)]
+    pub struct TagArchive {
+        #[column(primary_key)]
+        pub id: i64,
+        pub name: ::std::string::String,
+    }
+
+    /// The id of a [`Tag`].
+    #[derive(::pg_worm::PgNewtype, Debug, Clone, Copy, PartialEq)]
+    pub struct TagId(pub i64);
+
+    /// Links a tag to a shelf.
+    #[derive(::pg_worm::Model, Debug)]
+    pub struct ShelfTag {
+        #[column(primary_key, auto)]
+        pub id: i64,
+        pub shelf_id: i64,
+        #[column(custom)]
+        pub tag_id: TagId,
+    }
+
+    fn trim_name(tag: &mut TagInsert) -> ::core::result::Result<(), ::pg_worm::Error> {
+        tag.name = ::std::string::ToString::to_string(tag.name.trim());
+        ::core::result::Result::Ok(())
+    }
+
+    fn count_aliases(tag: &mut Tag) -> ::core::result::Result<(), ::pg_worm::Error> {
+        tag.alias_count = tag.aliases.len();
+        ::core::result::Result::Ok(())
+    }
+}
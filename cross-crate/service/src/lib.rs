@@ -0,0 +1,19 @@
+//! Queries the models of `pg-worm-domain`.
+
+use pg_worm::prelude::*;
+use pg_worm::Error;
+use pg_worm_domain::{Shelf, Volume};
+
+/// Get the titles of the books on a shelf, in the models' default order.
+pub async fn titles(label: &str) -> Result<Vec<String>, Error> {
+    let label = label.to_string();
+    let Some(shelf) = Shelf::select_one().where_(Shelf::label.eq(&label)).await? else {
+        return Ok(Vec::new());
+    };
+
+    let volumes = Volume::select()
+        .where_(Volume::shelf_id.eq(&shelf.id))
+        .await?;
+
+    Ok(volumes.into_iter().map(|v| v.title).collect())
+}
@@ -0,0 +1,84 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields};
+
+/// Generate the `ToSql`, `FromSql` and `PgType` implementations
+/// of a tuple struct wrapping a single value, like `struct BookId(i64)`.
+///
+/// Each of them is passed through to the wrapped type.
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let inner = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Some(&fields.unnamed[0].ty),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let Some(inner) = inner else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "pg-worm: `PgNewtype` needs a tuple struct with exactly one field",
+        ));
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "pg-worm: `PgNewtype` doesn't support generics",
+        ));
+    }
+
+    let ident = &input.ident;
+
+    Ok(quote!(
+        impl ::pg_worm::pg::types::ToSql for #ident {
+            fn to_sql(
+                &self,
+                ty: &::pg_worm::pg::types::Type,
+                out: &mut ::pg_worm::bytes::BytesMut,
+            ) -> ::core::result::Result<
+                ::pg_worm::pg::types::IsNull,
+                ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Sync + ::core::marker::Send>,
+            > {
+                <#inner as ::pg_worm::pg::types::ToSql>::to_sql(&self.0, ty, out)
+            }
+
+            fn accepts(ty: &::pg_worm::pg::types::Type) -> bool {
+                <#inner as ::pg_worm::pg::types::ToSql>::accepts(ty)
+            }
+
+            // Spelled out, since `to_sql_checked!` relies on the prelude
+            fn to_sql_checked(
+                &self,
+                ty: &::pg_worm::pg::types::Type,
+                out: &mut ::pg_worm::bytes::BytesMut,
+            ) -> ::core::result::Result<
+                ::pg_worm::pg::types::IsNull,
+                ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Sync + ::core::marker::Send>,
+            > {
+                ::pg_worm::pg::types::__to_sql_checked(self, ty, out)
+            }
+        }
+
+        impl<'__pg_worm> ::pg_worm::pg::types::FromSql<'__pg_worm> for #ident {
+            fn from_sql(
+                ty: &::pg_worm::pg::types::Type,
+                raw: &'__pg_worm [u8],
+            ) -> ::core::result::Result<
+                #ident,
+                ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Sync + ::core::marker::Send>,
+            > {
+                <#inner as ::pg_worm::pg::types::FromSql<'__pg_worm>>::from_sql(ty, raw).map(#ident)
+            }
+
+            fn accepts(ty: &::pg_worm::pg::types::Type) -> bool {
+                <#inner as ::pg_worm::pg::types::FromSql<'__pg_worm>>::accepts(ty)
+            }
+        }
+
+        impl ::pg_worm::PgType for #ident {
+            const PG_TYPE: &'static str = <#inner as ::pg_worm::PgType>::PG_TYPE;
+        }
+    ))
+}
@@ -1,17 +1,229 @@
 use convert_case::Casing;
-use darling::{ast::Data, Error, FromDeriveInput, FromField};
+use darling::{ast::Data, util::Override, Error, FromDeriveInput, FromField, FromMeta};
+use pg_worm_ident::quote_if_needed;
 use postgres_types::Type;
-use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{Ident, PathArguments};
+use proc_macro2::{Group, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{ext::IdentExt, spanned::Spanned, Ident, PathArguments, PathSegment};
+
+/// Generated SQL in which the types of `#[column(custom)]`
+/// fields are only filled in at runtime.
+#[derive(Clone, Default)]
+pub(crate) struct Sql(Vec<SqlPart>);
+
+#[derive(Clone)]
+enum SqlPart {
+    Text(String),
+    /// The `PgType::PG_TYPE` of a custom field's type.
+    PgType(syn::Type),
+}
 
-#[derive(FromDeriveInput)]
-#[darling(attributes(table), supports(struct_named))]
+impl Sql {
+    /// The Postgres type of a custom field's type.
+    fn pg_type(ty: syn::Type) -> Sql {
+        Sql(vec![SqlPart::PgType(ty)])
+    }
+
+    /// Append more SQL.
+    fn push(&mut self, sql: impl Into<Sql>) {
+        for part in sql.into().0 {
+            match (self.0.last_mut(), part) {
+                (Some(SqlPart::Text(last)), SqlPart::Text(text)) => last.push_str(&text),
+                (_, part) => self.0.push(part),
+            }
+        }
+    }
+
+    /// Join several pieces of SQL, seperated by `sep`.
+    fn join(sqls: impl IntoIterator<Item = Sql>, sep: &str) -> Sql {
+        let mut res = Sql::default();
+        for (i, sql) in sqls.into_iter().enumerate() {
+            if i > 0 {
+                res.push(sep);
+            }
+            res.push(sql);
+        }
+
+        res
+    }
+
+    /// Whether the SQL is fully known at compile time.
+    fn is_static(&self) -> bool {
+        self.0.iter().all(|part| matches!(part, SqlPart::Text(_)))
+    }
+
+    /// Generate an expression for a `&'static str` containing the SQL.
+    ///
+    /// Columns with `#[column(custom)]` take their type from the `PgType`
+    /// implementation, so their SQL has to be built once at runtime.
+    fn to_static(&self) -> TokenStream {
+        if self.is_static() {
+            let sql = self.to_string();
+            return quote!(#sql);
+        }
+
+        let parts = self.0.iter().map(|part| match part {
+            SqlPart::Text(text) => quote!(#text),
+            SqlPart::PgType(ty) => quote!(<#ty as ::pg_worm::PgType>::PG_TYPE),
+        });
+
+        quote!({
+            static SQL: ::std::sync::OnceLock<::std::string::String> = ::std::sync::OnceLock::new();
+            SQL.get_or_init(|| [#(#parts),*].concat()).as_str()
+        })
+    }
+}
+
+impl From<String> for Sql {
+    fn from(text: String) -> Sql {
+        Sql(vec![SqlPart::Text(text)])
+    }
+}
+
+impl From<&str> for Sql {
+    fn from(text: &str) -> Sql {
+        text.to_string().into()
+    }
+}
+
+/// Custom types are shown as e.g. `{TenantId}`.
+impl std::fmt::Display for Sql {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for part in &self.0 {
+            match part {
+                SqlPart::Text(text) => f.write_str(text)?,
+                SqlPart::PgType(ty) => write!(f, "{{{}}}", ty.to_token_stream())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Sql {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl PartialEq<&str> for Sql {
+    fn eq(&self, other: &&str) -> bool {
+        self.to_string().as_str() == *other
+    }
+}
+
+#[derive(Clone)]
 pub struct ModelInput {
     ident: syn::Ident,
-    #[darling(map = ModelField::init)]
+    vis: syn::Visibility,
     data: Data<(), ModelField>,
     table_name: Option<String>,
+    quoted: bool,
+    archive_to: Option<String>,
+    rename_all: RenameAll,
+    naming: Option<TableNaming>,
+    unique: Vec<String>,
+    order_by: Option<String>,
+    before_insert: Option<syn::Path>,
+    after_load: Option<syn::Path>,
+    serde: bool,
+}
+
+/// The options passed using the `#[table]` attribute.
+///
+/// They are parsed separately from the fields, so that
+/// errors in both are reported at once.
+#[derive(FromDeriveInput)]
+#[darling(attributes(table), supports(struct_named))]
+struct TableAttrs {
+    ident: syn::Ident,
+    vis: syn::Visibility,
+    table_name: Option<String>,
+    #[darling(default)]
+    quoted: bool,
+    archive_to: Option<String>,
+    #[darling(default)]
+    rename_all: RenameAll,
+    naming: Option<TableNaming>,
+    #[darling(multiple)]
+    unique: Vec<String>,
+    order_by: Option<String>,
+    before_insert: Option<syn::Path>,
+    after_load: Option<syn::Path>,
+    #[darling(default)]
+    serde: bool,
+}
+
+impl FromDeriveInput for ModelInput {
+    fn from_derive_input(input: &syn::DeriveInput) -> darling::Result<ModelInput> {
+        let mut errors = Error::accumulator();
+        let attrs = errors.handle(TableAttrs::from_derive_input(input));
+        let data = errors.handle(Data::try_from(&input.data));
+
+        let (Some(attrs), Some(data)) = (attrs, data) else {
+            return Err(errors.finish().unwrap_err());
+        };
+        errors.finish()?;
+
+        ModelInput::init(ModelInput {
+            ident: attrs.ident,
+            vis: attrs.vis,
+            data,
+            table_name: attrs.table_name,
+            quoted: attrs.quoted,
+            archive_to: attrs.archive_to,
+            rename_all: attrs.rename_all,
+            naming: attrs.naming,
+            unique: attrs.unique,
+            order_by: attrs.order_by,
+            before_insert: attrs.before_insert,
+            after_load: attrs.after_load,
+            serde: attrs.serde,
+        })
+    }
+}
+
+/// How the struct's name is converted to the table's name,
+/// unless it is set explicitly.
+///
+/// Models which don't set it follow the default of their
+/// crate, see `pg_worm::table_naming!`.
+#[derive(Clone, Copy, Default, FromMeta)]
+pub enum TableNaming {
+    #[default]
+    #[darling(rename = "singular")]
+    Singular,
+    #[darling(rename = "plural")]
+    Plural,
+}
+
+/// How field names are converted to column names.
+#[derive(Clone, Copy, Default, FromMeta)]
+pub enum RenameAll {
+    #[default]
+    #[darling(rename = "snake_case")]
+    Snake,
+    #[darling(rename = "camelCase")]
+    Camel,
+    #[darling(rename = "none")]
+    None,
+}
+
+/// The options of a column's index, passed using `#[column(index(...))]`.
+#[derive(Clone, Default, FromMeta)]
+pub struct IndexOptions {
+    /// The index method, e.g. `gin`.
+    using: Option<String>,
+    /// The operator class, e.g. `gin_trgm_ops`.
+    ops: Option<String>,
+}
+
+/// The options of a generated column, passed using `#[column(auto(...))]`.
+#[derive(Clone, Default, FromMeta)]
+pub struct AutoOptions {
+    /// Generate time-ordered UUIDv7 instead of random UUIDv4.
+    #[darling(default)]
+    uuid_v7: bool,
 }
 
 #[derive(Clone, FromField)]
@@ -21,6 +233,10 @@ pub struct ModelField {
     ty: syn::Type,
     column_name: Option<String>,
     #[darling(default)]
+    skip: bool,
+    #[darling(rename = "auto")]
+    auto_options: Option<Override<AutoOptions>>,
+    #[darling(skip)]
     auto: bool,
     #[darling(skip)]
     auto_gen_stmt: String,
@@ -28,29 +244,184 @@ pub struct ModelField {
     primary_key: bool,
     #[darling(default)]
     unique: bool,
+    #[darling(default)]
+    updated_at: bool,
+    #[darling(default)]
+    deprecated: bool,
+    #[darling(default)]
+    sensitive: bool,
+    sql_type: Option<String>,
+    #[darling(default)]
+    custom: bool,
+    max_length: Option<usize>,
+    min_length: Option<usize>,
+    max_bytes: Option<usize>,
+    min_bytes: Option<usize>,
+    collate: Option<String>,
+    index: Option<Override<IndexOptions>>,
+    encode_with: Option<syn::Path>,
+    decode_with: Option<syn::Path>,
+    insert_expr: Option<String>,
     #[darling(skip)]
     nullable: bool,
     #[darling(skip)]
     array: bool,
+    #[darling(skip)]
+    bytes: bool,
+    #[darling(skip)]
+    rename_all: RenameAll,
 }
 
 impl ModelInput {
+    /// Initialization function called after
+    /// the input has been parsed.
+    fn init(mut input: ModelInput) -> darling::Result<ModelInput> {
+        // Pass the naming convention on to the fields
+        if let Data::Struct(fields) = &mut input.data {
+            for field in &mut fields.fields {
+                field.rename_all = input.rename_all;
+            }
+        }
+
+        // Make sure the unique constraints only use existing columns
+        let mut errors = Error::accumulator();
+        for unique in &input.unique {
+            let columns = split_column_list(unique);
+            if columns.is_empty() {
+                errors.push(Error::custom("pg-worm: `unique` needs at least one column"));
+            }
+
+            for column in columns {
+                if !input.live_fields().any(|f| f.column_name() == column) {
+                    errors.push(Error::custom(format!(
+                        "pg-worm: unknown column `{column}` in `unique`"
+                    )));
+                }
+            }
+        }
+
+        // Make sure the default ordering only uses existing columns
+        if input.order_by.is_some() {
+            match input.default_order() {
+                Ok(order) if order.is_empty() => errors.push(Error::custom(
+                    "pg-worm: `order_by` needs at least one column",
+                )),
+                Ok(_) => (),
+                Err(err) => errors.push(Error::custom(err)),
+            }
+        }
+
+        if input.serde && !cfg!(feature = "serde") {
+            errors.push(Error::custom(
+                "pg-worm: `serde` requires the `serde` feature",
+            ));
+        }
+
+        errors.finish_with(input)
+    }
+
+    /// Get the fields and directions of the `order_by` attribute,
+    /// e.g. `created_at desc, id`.
+    fn default_order(&self) -> Result<Vec<(&ModelField, &'static str)>, String> {
+        let Some(order_by) = &self.order_by else {
+            return Ok(Vec::new());
+        };
+
+        split_column_list(order_by)
+            .into_iter()
+            .map(|item| {
+                let mut words = item.split_whitespace();
+                let column = words.next().unwrap_or_default();
+                let direction = match words.next().map(str::to_ascii_lowercase).as_deref() {
+                    None | Some("asc") => "ASC",
+                    Some("desc") => "DESC",
+                    Some(other) => {
+                        return Err(format!(
+                        "pg-worm: unknown direction `{other}` in `order_by`, use `asc` or `desc`"
+                    ))
+                    }
+                };
+
+                if words.next().is_some() {
+                    return Err(format!(
+                        "pg-worm: invalid ordering `{item}` in `order_by`, use `column desc`"
+                    ));
+                }
+
+                let field = self
+                    .live_fields()
+                    .find(|f| f.column_name() == column)
+                    .ok_or_else(|| format!("pg-worm: unknown column `{column}` in `order_by`"))?;
+
+                Ok((field, direction))
+            })
+            .collect()
+    }
+
     /// Get the input's ident.
     const fn ident(&self) -> &Ident {
         &self.ident
     }
 
-    /// Generate the table's name.
+    /// Generate the table's name as it has to be written in SQL.
+    ///
+    /// Explicit names are used as written, so Postgres folds them to
+    /// lowercase unless they are quoted (e.g. `"\"Book\""`) or the
+    /// table is marked as `quoted`.
     fn table_name(&self) -> String {
-        if let Some(table_name) = &self.table_name {
+        let Some(table_name) = &self.table_name else {
+            return quote_if_needed(&self.unquoted_table_name());
+        };
+        if !self.quoted || table_name.starts_with('"') {
             return table_name.clone();
         }
 
-        self.ident.to_string().to_case(convert_case::Case::Snake)
+        // Keep names qualified by a schema, like `app.Book`, working
+        table_name
+            .split('.')
+            .map(quote_if_needed)
+            .collect::<Vec<_>>()
+            .join(".")
     }
 
-    /// Get an iterator over the input struct's fields.
-    fn all_fields(&self) -> impl Iterator<Item = &ModelField> {
+    /// Generate the table's name, without quotes.
+    ///
+    /// Explicit names may already be quoted, e.g. `"\"Book\""`.
+    fn unquoted_table_name(&self) -> String {
+        if let Some(table_name) = &self.table_name {
+            return match table_name
+                .strip_prefix('"')
+                .and_then(|i| i.strip_suffix('"'))
+            {
+                Some(unquoted) => unquoted.replace("\"\"", "\""),
+                None => table_name.clone(),
+            };
+        }
+
+        let table_name = self.ident.to_string().to_case(convert_case::Case::Snake);
+
+        match self.naming.unwrap_or_default() {
+            TableNaming::Singular => table_name,
+            TableNaming::Plural => pluralize(&table_name),
+        }
+    }
+
+    /// Generate the name of something belonging to the table,
+    /// like an index, by appending `suffix` to the table's name.
+    ///
+    /// It is quoted as a whole if the table's name is.
+    fn derived_name(&self, suffix: &str) -> String {
+        let (table_name, unquoted) = (self.table_name(), self.unquoted_table_name());
+        if table_name == unquoted {
+            return format!("{table_name}_{suffix}");
+        }
+
+        quote_if_needed(&format!("{unquoted}_{suffix}"))
+    }
+
+    /// Get an iterator over the input struct's fields,
+    /// including the ones which aren't columns.
+    fn struct_fields(&self) -> impl Iterator<Item = &ModelField> {
         let Data::Struct(fields) = &self.data else {
             panic!("only named structs allowed");
         };
@@ -58,43 +429,280 @@ impl ModelInput {
         fields.iter()
     }
 
+    /// Get an iterator over the input struct's fields
+    /// which are columns, i.e. aren't skipped.
+    fn all_fields(&self) -> impl Iterator<Item = &ModelField> {
+        self.struct_fields().filter(|f| !f.skip)
+    }
+
+    /// Get an iterator over the fields marked as `skip`,
+    /// which are defaulted when parsing a row.
+    fn skipped_fields(&self) -> impl Iterator<Item = &ModelField> {
+        self.struct_fields().filter(|f| f.skip)
+    }
+
+    /// Generate the call of the `after_load` hook
+    /// on the parsed entity `value`, if there is one.
+    fn after_load_hook(&self, value: &Ident) -> TokenStream {
+        match &self.after_load {
+            Some(hook) => quote!(
+                let mut #value = #value;
+                #hook(&mut #value)?;
+            ),
+            None => quote!(),
+        }
+    }
+
+    /// Get an iterator over the input struct's fields
+    /// but skip the deprecated ones.
+    ///
+    /// Deprecated columns may not exist (anymore), so
+    /// they are never written or selected explicitly.
+    fn live_fields(&self) -> impl Iterator<Item = &ModelField> {
+        self.all_fields().filter(|f| !f.deprecated)
+    }
+
+    /// Get an iterator over the input struct's primary key fields.
+    fn primary_key_fields(&self) -> impl Iterator<Item = &ModelField> {
+        self.all_fields().filter(|f| f.primary_key)
+    }
+
+    /// Get the primary key field if there is exactly one.
+    fn single_primary_key(&self) -> Option<&ModelField> {
+        let mut primary_keys = self.primary_key_fields();
+        match (primary_keys.next(), primary_keys.next()) {
+            (Some(primary_key), None) => Some(primary_key),
+            _ => None,
+        }
+    }
+
+    /// Get the field marked as `updated_at`, if there is one.
+    fn updated_at_field(&self) -> Option<&ModelField> {
+        self.all_fields().find(|f| f.updated_at)
+    }
+
     /// Get an iterator over the input struct's fields
     /// but skip the auto generated ones.
     fn non_generated_fields(&self) -> impl Iterator<Item = &ModelField> {
-        self.all_fields().filter(|f| !f.auto)
-    }
-
-    /// Generate the SQL statement needed to create
-    /// the table corresponding to the input.
-    fn table_creation_sql(&self) -> Result<String, Error> {
-        Ok(format!(
-            "CREATE TABLE {} ({})",
-            self.table_name(),
-            self.all_fields()
-                .map(|f| f.column_creation_sql())
-                .collect::<Result<Vec<String>, Error>>()?
-                .join(", ")
-        ))
+        self.live_fields().filter(|f| !f.auto)
+    }
+
+    /// The placeholders of the inserted values, in the order
+    /// of [`ModelInput::non_generated_fields`], e.g. `$1, $2`.
+    fn insert_placeholders(&self) -> String {
+        self.non_generated_fields()
+            .zip(1..)
+            .map(|(f, i)| f.insert_placeholder(i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Get the name and columns of each table-level
+    /// unique constraint.
+    ///
+    /// The name follows Postgres' own naming scheme:
+    /// `{table}_{column}_..._key`.
+    fn unique_constraints(&self) -> Vec<(String, Vec<String>)> {
+        self.unique
+            .iter()
+            .map(|unique| {
+                let columns = split_column_list(unique)
+                    .into_iter()
+                    .filter_map(|column| {
+                        self.live_fields()
+                            .find(|f| f.column_name() == column)
+                            .map(|f| f.sql_column_name())
+                    })
+                    .collect::<Vec<_>>();
+                let name = self.derived_name(&format!(
+                    "{}_key",
+                    columns
+                        .iter()
+                        .map(|i| i.trim_matches('"'))
+                        .collect::<Vec<_>>()
+                        .join("_")
+                ));
+
+                (name, columns)
+            })
+            .collect()
+    }
+
+    /// Generate the SQL statements needed to create
+    /// the table corresponding to the input, joined by `; `.
+    pub(crate) fn table_creation_sql(&self) -> Result<Sql, Error> {
+        Ok(Sql::join(self.setup_sql()?, "; "))
+    }
+
+    /// Generate the SQL statements needed to set up the table
+    /// corresponding to the input, in the order they have to run:
+    /// the table itself, the trigger keeping `updated_at` up to
+    /// date and the indexes.
+    pub(crate) fn setup_sql(&self) -> Result<Vec<Sql>, Error> {
+        let table_name = self.table_name();
+        let mut definitions = self
+            .live_fields()
+            .map(|f| f.column_creation_sql())
+            .collect::<Result<Vec<Sql>, Error>>()?;
+
+        for (name, columns) in self.unique_constraints() {
+            definitions.push(format!("CONSTRAINT {name} UNIQUE ({})", columns.join(", ")).into());
+        }
+
+        let mut create = Sql::from(format!("CREATE TABLE {table_name} ("));
+        create.push(Sql::join(definitions, ", "));
+        create.push(")");
+        let mut statements = vec![create];
+
+        // Keep the `updated_at` column up to date using a trigger
+        if let Some(field) = self.updated_at_field() {
+            let col = field.sql_column_name();
+            let trigger = self.derived_name("set_updated_at");
+            statements.push(
+                format!(
+                    "CREATE OR REPLACE FUNCTION {trigger}() RETURNS trigger AS $$ \
+                    BEGIN NEW.{col} := now(); RETURN NEW; END $$ LANGUAGE plpgsql"
+                )
+                .into(),
+            );
+            // `CREATE OR REPLACE TRIGGER` needs Postgres 14
+            statements.push(format!("DROP TRIGGER IF EXISTS {trigger} ON {table_name}").into());
+            statements.push(
+                format!(
+                    "CREATE TRIGGER {trigger} BEFORE UPDATE ON {table_name} \
+                    FOR EACH ROW EXECUTE FUNCTION {trigger}()"
+                )
+                .into(),
+            );
+        }
+
+        statements.extend(
+            self.live_fields()
+                .filter_map(|field| field.index_creation_sql(self))
+                .map(Sql::from),
+        );
+
+        Ok(statements)
+    }
+
+    /// Generate the SQL statement needed to create the
+    /// archive table, if there is one.
+    ///
+    /// The archive table has the same columns but without
+    /// any constraints, plus an `archived_at` timestamp.
+    pub(crate) fn archive_table_creation_sql(&self) -> Option<Result<Sql, Error>> {
+        let archive = self.archive_to.as_ref()?;
+        let columns = self
+            .live_fields()
+            .map(|f| f.archive_column_creation_sql())
+            .collect::<Result<Vec<Sql>, Error>>();
+
+        Some(columns.map(|columns| {
+            let mut sql = Sql::from(format!("CREATE TABLE IF NOT EXISTS {archive} ("));
+            sql.push(Sql::join(columns, ", "));
+            sql.push(", archived_at TIMESTAMPTZ NOT NULL DEFAULT now())");
+            sql
+        }))
+    }
+
+    /// Generate an expression for a `&'static [&'static str]`
+    /// containing the statements, see [`Sql::to_static`].
+    fn static_statements(&self, statements: &[Sql]) -> TokenStream {
+        let sqls = statements.iter().map(Sql::to_static);
+        if statements.iter().all(Sql::is_static) {
+            // Not promoted if the names depend on the crate's naming
+            return quote!({
+                const STATEMENTS: &[&str] = &[#(#sqls),*];
+                STATEMENTS
+            });
+        }
+
+        quote!({
+            static STATEMENTS: ::std::sync::OnceLock<::std::vec::Vec<&'static str>> =
+                ::std::sync::OnceLock::new();
+            STATEMENTS
+                .get_or_init(|| ::std::vec![#(#sqls),*])
+                .as_slice()
+        })
     }
 
     /// Generate all code needed.
+    ///
+    /// Unless the model sets its table's name or naming, it follows the
+    /// default of its crate, which is only known to the compiler. So the
+    /// code is generated for both namings, see [`merge_namings`].
     pub fn impl_everything(&self) -> TokenStream {
+        if self.table_name.is_some() || self.naming.is_some() {
+            return self.impl_named();
+        }
+
+        let with_naming = |naming| {
+            ModelInput {
+                naming: Some(naming),
+                ..self.clone()
+            }
+            .impl_named()
+        };
+
+        merge_namings(
+            with_naming(TableNaming::Singular),
+            with_naming(TableNaming::Plural),
+        )
+    }
+
+    /// Generate all code needed, using the model's naming.
+    fn impl_named(&self) -> TokenStream {
         let ident = self.ident();
 
         let try_from_row = self.impl_try_from_row();
         let column_consts = self.impl_column_consts();
         let columns = self.impl_columns();
         let insert = self.impl_insert();
+        let insert_idempotent = self.impl_insert_idempotent();
+        let create = self.impl_create();
+        let insert_many_lenient = self.impl_insert_many_lenient();
+        let seed = self.impl_seed();
+        let insert_struct = self.impl_insert_struct();
+        let refresh = self.impl_refresh();
+        let save = self.impl_save();
+        let persisted_eq = self.impl_persisted_eq();
+        let hydrate = self.impl_hydrate();
+        let archive = self.impl_archive();
+        let changes_since = self.impl_changes_since();
+        let change_feed = self.impl_change_feed();
+        let cache = self.impl_cache();
+        let cached = self.impl_cached();
         let model = self.impl_model();
+        let codecs = self.all_fields().map(|f| f.impl_codec(self));
+        let identity_checks = self.live_fields().map(|f| f.impl_identity_check());
 
         quote!(
             #[automatically_derived]
             impl #ident {
                 #column_consts
                 #insert
+                #insert_idempotent
+                #create
+                #insert_many_lenient
+                #seed
+                #refresh
+                #save
+                #persisted_eq
+                #hydrate
+                #archive
+                #changes_since
+                #cache
                 #columns
             }
 
+            #change_feed
+            #cached
+
+            #insert_struct
+
+            #(#codecs)*
+            #(#identity_checks)*
+
             #[automatically_derived]
             #try_from_row
             #[automatically_derived]
@@ -110,20 +718,39 @@ impl ModelInput {
                 if !i.primary_key {
                     None
                 } else {
-                    Some(i.column_name())
+                    Some(i.sql_column_name())
                 }
             })
             .collect::<Vec<String>>();
-        let columns = self.all_fields().map(|i| i.create_column());
+        let columns = self.live_fields().map(|i| i.create_column(self));
+        let deprecated_columns = self
+            .all_fields()
+            .filter(|i| i.deprecated)
+            .map(|i| i.sql_column_name());
+        let unique = self.unique_constraints().into_iter().map(
+            |(name, columns)| quote!(.unique_named(#name, ::std::vec![#(::std::string::ToString::to_string(#columns)), *])),
+        );
+
+        // Only add a primary key constraint if there actually is one.
+        let primary_key = if primary_keys.is_empty() {
+            quote!()
+        } else {
+            quote!(.primary_key(::std::vec![#(::std::string::ToString::to_string(#primary_keys)), *]))
+        };
 
         quote!(
             fn table() -> ::pg_worm::migration::Table {
                 use ::pg_worm::migration::{Table, Column};
 
-                Table::new(#table_name).primary_key(vec![#(#primary_keys.to_string()), *])
+                Table::new(#table_name)
+                #primary_key
                 #(
                     .column(#columns)
                 )*
+                #(
+                    .deprecated_column(#deprecated_columns)
+                )*
+                #(#unique)*
             }
         )
     }
@@ -134,24 +761,53 @@ impl ModelInput {
         let ident = self.ident();
         let table_name = self.table_name();
         let creation_sql = match self.table_creation_sql() {
-            Ok(res) => quote!(#res),
+            Ok(res) => res.to_static(),
+            Err(err) => err.write_errors(),
+        };
+        let setup_sql = match self.setup_sql() {
+            Ok(statements) => self.static_statements(&statements),
             Err(err) => err.write_errors(),
         };
 
         let select = self.impl_select();
         let delete = self.impl_delete();
+        let insert_builder = self.impl_insert_builder();
         let update = self.impl_update();
         let query = self.impl_query();
         let table = self.impl_table();
+        let archive_table = match (&self.archive_to, self.archive_table_creation_sql()) {
+            (Some(archive), Some(Ok(sql))) => {
+                let sql = sql.to_static();
+                quote!(
+                    fn _archive_table() -> ::core::option::Option<(&'static str, &'static str)> {
+                        ::core::option::Option::Some((#archive, #sql))
+                    }
+                )
+            }
+            (_, Some(Err(err))) => err.write_errors(),
+            _ => quote!(),
+        };
+        let uses_uuid_v7 = if self.live_fields().any(|f| f.uuid_v7()) {
+            quote!(
+                fn _uses_uuid_v7() -> bool {
+                    true
+                }
+            )
+        } else {
+            quote!()
+        };
 
         quote!(
-            #[pg_worm::async_trait]
-            impl pg_worm::Model<#ident> for #ident {
+            #[::pg_worm::async_trait]
+            impl ::pg_worm::Model<#ident> for #ident {
                 #select
                 #update
                 #delete
+                #insert_builder
                 #query
                 #table
+                #archive_table
+                #uses_uuid_v7
 
                 fn table_name() -> &'static str {
                     #table_name
@@ -161,7 +817,11 @@ impl ModelInput {
                     #creation_sql
                 }
 
-                fn columns() -> &'static [&'static dyn std::ops::Deref<Target = pg_worm::query::Column>] {
+                fn _setup_sql() -> &'static [&'static str] {
+                    #setup_sql
+                }
+
+                fn columns() -> &'static [::pg_worm::query::Column] {
                     &#ident::COLUMNS
                 }
             }
@@ -172,9 +832,9 @@ impl ModelInput {
         let ident = self.ident();
 
         quote!(
-            fn query<'a>(statement: impl Into<String>, params: Vec<&'a (dyn pg_worm::pg::types::ToSql + Sync)>) -> pg_worm::query::Query<'a, Vec<#ident>> {
-                pg_worm::query::Query::new(
-                    statement.into(),
+            fn query<'a>(statement: impl ::core::convert::Into<::std::string::String>, params: ::std::vec::Vec<&'a (dyn ::pg_worm::pg::types::ToSql + ::core::marker::Sync)>) -> ::pg_worm::query::Query<'a, ::std::vec::Vec<#ident>> {
+                ::pg_worm::query::Query::new(
+                    ::core::convert::Into::into(statement),
                     params
                 )
             }
@@ -183,20 +843,46 @@ impl ModelInput {
 
     fn impl_update(&self) -> TokenStream {
         let ident = self.ident();
+        let invalidating = self.invalidating();
 
         quote!(
-            fn update<'a>() -> pg_worm::query::Update<'a, pg_worm::query::NoneSet> {
-                pg_worm::query::Update::<pg_worm::query::NoneSet>::new(#ident::table_name())
+            fn update<'a>() -> ::pg_worm::query::Update<'a, ::pg_worm::query::NoneSet, u64, #ident> {
+                ::pg_worm::query::Update::<::pg_worm::query::NoneSet>::new(#ident::table_name())
+                    .of_model::<#ident>()
+                    #invalidating
             }
         )
     }
 
     fn impl_delete(&self) -> TokenStream {
         let ident = self.ident();
+        let invalidating = self.invalidating();
+
+        quote!(
+            fn delete<'a>() -> ::pg_worm::query::Delete<'a> {
+                ::pg_worm::query::Delete::new(#ident::table_name())
+                    #invalidating
+            }
+        )
+    }
+
+    /// Generate the call which lets `update()` and `delete()`
+    /// invalidate the model's cache, if it can have one.
+    fn invalidating(&self) -> TokenStream {
+        if self.single_primary_key().is_none() {
+            return quote!();
+        }
+
+        let ident = self.ident();
+        quote!(.invalidating(::pg_worm::cache::invalidator::<#ident>()))
+    }
+
+    fn impl_insert_builder(&self) -> TokenStream {
+        let ident = self.ident();
 
         quote!(
-            fn delete<'a>() -> pg_worm::query::Delete<'a> {
-                pg_worm::query::Delete::new(#ident::table_name())
+            fn insert_builder<'a>() -> ::pg_worm::query::Insert<'a, ::pg_worm::query::NoneSet, u64, #ident> {
+                ::pg_worm::query::Insert::new(#ident::table_name()).of_model::<#ident>()
             }
         )
     }
@@ -205,14 +891,36 @@ impl ModelInput {
     /// select method.
     fn impl_select(&self) -> TokenStream {
         let ident = self.ident();
+        // The start of every query selecting all columns,
+        // so it doesn't have to be built each time.
+        let prefix = format!("SELECT {} FROM {}", self.column_list(), self.table_name());
+
+        let order = self
+            .default_order()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(field, direction)| {
+                let field = field.ident();
+                let direction = match direction {
+                    "DESC" => quote!(::pg_worm::query::Order::Desc),
+                    _ => quote!(::pg_worm::query::Order::Asc),
+                };
+                quote!((#ident::#field.column(), #direction))
+            })
+            .collect::<Vec<_>>();
+        let default_order = (!order.is_empty()).then(|| quote!(.default_order_by(&[#(#order),*])));
 
         quote!(
-            fn select<'a>() -> pg_worm::query::Select<'a, Vec<#ident>> {
-                pg_worm::query::Select::new(#ident::columns(), #ident::table_name())
+            fn select<'a>() -> ::pg_worm::query::Select<'a, ::std::vec::Vec<#ident>> {
+                ::pg_worm::query::Select::new(#ident::columns(), #ident::table_name())
+                    .with_prefix(#prefix)
+                    #default_order
             }
 
-            fn select_one<'a>() -> pg_worm::query::Select<'a, Option<#ident>> {
-                pg_worm::query::Select::new(#ident::columns(), #ident::table_name())
+            fn select_one<'a>() -> ::pg_worm::query::Select<'a, ::core::option::Option<#ident>> {
+                ::pg_worm::query::Select::new(#ident::columns(), #ident::table_name())
+                    .with_prefix(#prefix)
+                    #default_order
                     .limit(1)
             }
         )
@@ -222,49 +930,541 @@ impl ModelInput {
     /// `TryFrom<&Row>`
     fn impl_try_from_row(&self) -> TokenStream {
         let ident = self.ident();
-        let field_idents = self.all_fields().map(|i| i.ident());
-        let column_names = self.all_fields().map(|i| i.column_name());
+        let field_idents = self.live_fields().map(|i| i.ident()).collect::<Vec<_>>();
+        let column_names = self
+            .live_fields()
+            .map(|i| i.column_name())
+            .collect::<Vec<_>>();
+        // The names used by `Select::qualify_aliases`
+        let table_name = self.table_name();
+        let qualified_names = column_names
+            .iter()
+            .map(|i| format!("{}__{i}", table_name.trim_matches('"')))
+            .collect::<Vec<_>>();
+        let deprecated_idents = self
+            .all_fields()
+            .filter(|i| i.deprecated)
+            .map(|i| i.ident())
+            .collect::<Vec<_>>();
+        let deprecated_names = self
+            .all_fields()
+            .filter(|i| i.deprecated)
+            .map(|i| i.column_name())
+            .collect::<Vec<_>>();
+        let deprecated_index_idents = (0..deprecated_idents.len())
+            .map(|i| quote::format_ident!("__deprecated_index_{i}"))
+            .collect::<Vec<_>>();
+        let index_idents = (0..field_idents.len())
+            .map(|i| quote::format_ident!("__index_{i}"))
+            .collect::<Vec<_>>();
+        let skipped_idents = self.skipped_fields().map(|i| i.ident()).collect::<Vec<_>>();
+        // Read a column, naming the model and column if that fails
+        let model_name = ident.to_string();
+        let try_get = |index: TokenStream, column_name: &str| {
+            quote!(
+                row.try_get(#index).map_err(|source| ::pg_worm::Error::ColumnParse {
+                    model: #model_name,
+                    column: #column_name,
+                    source,
+                })?
+            )
+        };
+        // The values of the fields, decoded if needed
+        let indexed_values = self
+            .live_fields()
+            .zip(index_idents.iter().zip(&column_names))
+            .map(|(f, (i, column_name))| f.decode(try_get(quote!(#i), column_name)))
+            .collect::<Vec<_>>();
+        let named_values = self
+            .live_fields()
+            .zip(qualified_names.iter().zip(&column_names))
+            .map(|(f, (qualified_name, column_name))| {
+                let by_index = try_get(quote!(i), column_name);
+                let by_name = try_get(quote!(#column_name), column_name);
+                // Prefer qualified aliases, e.g. `book__id`
+                f.decode(quote!(
+                    match ::core::iter::Iterator::position(&mut row.columns().iter(), |i| i.name() == #qualified_name) {
+                        ::core::option::Option::Some(i) => #by_index,
+                        ::core::option::Option::None => #by_name,
+                    }
+                ))
+            })
+            .collect::<Vec<_>>();
+        let deprecated_values = deprecated_names
+            .iter()
+            .map(|column_name| try_get(quote!(i), column_name))
+            .collect::<Vec<_>>();
+        let value = quote::format_ident!("value");
+        let after_load = self.after_load_hook(&value);
+
+        // Parse whole result sets looking up the column indices only once.
+        // Models without fields simply keep the default.
+        let from_rows = if field_idents.is_empty() && deprecated_idents.is_empty() {
+            quote!()
+        } else {
+            quote!(
+                fn from_rows(rows: ::std::vec::Vec<::pg_worm::Row>) -> ::core::result::Result<::std::vec::Vec<#ident>, ::pg_worm::Error> {
+                    let ::core::option::Option::Some(first) = rows.first() else {
+                        return ::core::result::Result::Ok(::std::vec::Vec::new());
+                    };
+
+                    // Look up each field's column index once for the whole
+                    // result set. If a column is missing, parse the rows one
+                    // by one to get the usual error.
+                    let columns = first.columns();
+                    let indices = [
+                        #(
+                            ::core::iter::Iterator::position(&mut columns.iter(), |i| i.name() == #qualified_names)
+                                .or_else(|| ::core::iter::Iterator::position(&mut columns.iter(), |i| i.name() == #column_names))
+                        ),*
+                    ];
+                    let [#(::core::option::Option::Some(#index_idents)),*] = indices else {
+                        return ::core::iter::Iterator::collect(::core::iter::Iterator::map(rows.iter(), <#ident as ::pg_worm::FromRow>::from_row));
+                    };
+
+                    // Deprecated columns are only parsed if they are present
+                    #(
+                        let #deprecated_index_idents = ::core::iter::Iterator::position(&mut columns.iter(), |i| i.name() == #deprecated_names);
+                    )*
+
+                    let mut res = ::std::vec::Vec::with_capacity(rows.len());
+                    for row in rows {
+                        let #value = #ident {
+                            #(
+                                #field_idents: #indexed_values,
+                            )*
+                            #(
+                                #deprecated_idents: match #deprecated_index_idents {
+                                    ::core::option::Option::Some(i) => #deprecated_values,
+                                    ::core::option::Option::None => ::core::default::Default::default(),
+                                },
+                            )*
+                            #(
+                                #skipped_idents: ::core::default::Default::default(),
+                            )*
+                        };
+                        #after_load
+                        res.push(#value);
+                    }
+
+                    ::core::result::Result::Ok(res)
+                }
+            )
+        };
 
         quote!(
-            impl TryFrom<pg_worm::pg::Row> for #ident {
-                type Error = pg_worm::Error;
+            impl ::core::convert::TryFrom<::pg_worm::Row> for #ident {
+                type Error = ::pg_worm::Error;
 
-                fn try_from(row: pg_worm::pg::Row) -> Result<#ident, Self::Error> {
-                    let res = #ident {
+                fn try_from(row: ::pg_worm::Row) -> ::core::result::Result<#ident, Self::Error> {
+                    <#ident as ::core::convert::TryFrom<&::pg_worm::pg::Row>>::try_from(&*row)
+                }
+            }
+
+            impl ::core::convert::TryFrom<&::pg_worm::Row> for #ident {
+                type Error = ::pg_worm::Error;
+
+                fn try_from(row: &::pg_worm::Row) -> ::core::result::Result<#ident, Self::Error> {
+                    <#ident as ::core::convert::TryFrom<&::pg_worm::pg::Row>>::try_from(&**row)
+                }
+            }
+
+            // Converting from the driver's row is kept for compatibility
+            impl ::core::convert::TryFrom<::pg_worm::pg::Row> for #ident {
+                type Error = ::pg_worm::Error;
+
+                fn try_from(row: ::pg_worm::pg::Row) -> ::core::result::Result<#ident, Self::Error> {
+                    <#ident as ::core::convert::TryFrom<&::pg_worm::pg::Row>>::try_from(&row)
+                }
+            }
+
+            impl ::core::convert::TryFrom<&::pg_worm::pg::Row> for #ident {
+                type Error = ::pg_worm::Error;
+
+                fn try_from(row: &::pg_worm::pg::Row) -> ::core::result::Result<#ident, Self::Error> {
+                    let #value = #ident {
                         #(
-                            #field_idents: row.try_get(#column_names)?
-                        ),*
+                            #field_idents: #named_values,
+                        )*
+                        // Deprecated columns are only parsed if they are present
+                        #(
+                            #deprecated_idents: match ::core::iter::Iterator::position(&mut row.columns().iter(), |i| i.name() == #deprecated_names) {
+                                ::core::option::Option::Some(i) => #deprecated_values,
+                                ::core::option::Option::None => ::core::default::Default::default(),
+                            },
+                        )*
+                        // Skipped fields aren't columns
+                        #(
+                            #skipped_idents: ::core::default::Default::default(),
+                        )*
                     };
+                    #after_load
 
-                    Ok(res)
+                    ::core::result::Result::Ok(#value)
                 }
             }
 
-            impl pg_worm::FromRow for #ident { }
+            impl ::pg_worm::FromRow for #ident {
+                #from_rows
+            }
         )
     }
 
-    /// Generate the code needed for
-    /// creating the `COLUMNS` constant.
-    fn impl_columns(&self) -> TokenStream {
+    /// Generate the code for the `refresh` method.
+    ///
+    /// Only models with exactly one primary key column get one.
+    fn impl_refresh(&self) -> TokenStream {
+        let mut primary_keys = self.primary_key_fields();
+        let (Some(primary_key), None) = (primary_keys.next(), primary_keys.next()) else {
+            return quote!();
+        };
+
         let ident = self.ident();
-        let field_idents = self.all_fields().map(|i| i.ident());
-        let n_fields = self.all_fields().count();
+        let pk_ident = primary_key.ident();
+        let field_idents = self.live_fields().map(|f| f.ident()).collect::<Vec<_>>();
 
         quote!(
-            pub const COLUMNS: [&'static dyn std::ops::Deref<Target = pg_worm::query::Column>; #n_fields] = [
+            /// Reload this entity from the database by its primary key.
+            ///
+            /// Each field is overwritten with the freshly selected value,
+            /// one by one. The entity itself is not replaced, it keeps
+            /// living at the same place in memory.
+            ///
+            /// Returns [`pg_worm::Error::NotFound`] if the row doesn't
+            /// exist anymore. In that case, as with any other error,
+            /// the entity is left unchanged.
+            pub async fn refresh(&mut self) -> ::core::result::Result<(), ::pg_worm::Error> {
+                let fresh = <#ident as ::pg_worm::Model<#ident>>::select_one()
+                    .where_(#ident::#pk_ident.eq(&self.#pk_ident))
+                    .await?
+                    .ok_or(::pg_worm::Error::NotFound)?;
+
                 #(
-                    &#ident::#field_idents
-                ),*
-            ];
+                    self.#field_idents = fresh.#field_idents;
+                )*
+
+                ::core::result::Result::Ok(())
+            }
         )
     }
 
-    /// Generate the code for implementing
-    /// the column constants.
-    /// Needs to be wrapped in an `impl` block.
-    fn impl_column_consts(&self) -> TokenStream {
-        let column_consts = self.all_fields().map(|f| f.impl_column_const(self));
+    /// Generate the code for the `save` and `remove` methods.
+    ///
+    /// Only models with exactly one primary key column get them.
+    /// `save` is left out if there are no columns to update.
+    fn impl_save(&self) -> TokenStream {
+        let Some(primary_key) = self.single_primary_key() else {
+            return quote!();
+        };
+
+        let ident = self.ident();
+        let pk_ident = primary_key.ident();
+        // Generated columns are kept up to date by the database
+        let field_idents = self
+            .live_fields()
+            .filter(|f| !(f.primary_key || f.auto || f.updated_at))
+            .map(|f| f.ident())
+            .collect::<Vec<_>>();
+
+        let save = (!field_idents.is_empty()).then(|| {
+            quote!(
+                /// Write the columns of this entity to its row,
+                /// which is found by its primary key.
+                ///
+                /// Returns [`pg_worm::SaveOutcome::NotFound`] if
+                /// the row doesn't exist (anymore).
+                pub async fn save(&self) -> ::core::result::Result<::pg_worm::SaveOutcome, ::pg_worm::Error> {
+                    let affected = <#ident as ::pg_worm::Model<#ident>>::update()
+                        #(.set(#ident::#field_idents, &self.#field_idents))*
+                        .where_(#ident::#pk_ident.eq(&self.#pk_ident))
+                        .await?;
+
+                    <::pg_worm::SaveOutcome as ::core::convert::TryFrom<u64>>::try_from(affected)
+                }
+            )
+        });
+
+        quote!(
+            #save
+
+            /// Delete the row of this entity, which is found by its primary key.
+            ///
+            /// Returns [`pg_worm::DeleteOutcome::NotFound`] if
+            /// the row doesn't exist (anymore).
+            pub async fn remove(&self) -> ::core::result::Result<::pg_worm::DeleteOutcome, ::pg_worm::Error> {
+                let affected = <#ident as ::pg_worm::Model<#ident>>::delete()
+                    .where_(#ident::#pk_ident.eq(&self.#pk_ident))
+                    .await?;
+
+                <::pg_worm::DeleteOutcome as ::core::convert::TryFrom<u64>>::try_from(affected)
+            }
+        )
+    }
+
+    /// Generate the code for the `persisted_eq` method.
+    ///
+    /// It can only be called if all columns' types implement `PartialEq`.
+    /// The higher-ranked bounds keep the ones which don't from failing to
+    /// compile before the method is used.
+    fn impl_persisted_eq(&self) -> TokenStream {
+        let ident = self.ident();
+        let field_idents = self.all_fields().map(|f| f.ident()).collect::<Vec<_>>();
+        let field_types = self.all_fields().map(|f| &f.ty);
+
+        let bounds = if field_idents.is_empty() {
+            quote!()
+        } else {
+            quote!(where for<'__pg_worm> #(#field_types: ::core::cmp::PartialEq),*)
+        };
+
+        quote!(
+            /// Check whether both entities are equal in all of their columns.
+            ///
+            /// Fields marked as `skip` aren't compared.
+            pub fn persisted_eq(&self, other: &#ident) -> bool #bounds {
+                true #(&& self.#field_idents == other.#field_idents)*
+            }
+        )
+    }
+
+    /// Generate the code for the `hydrate` method.
+    ///
+    /// Only models with exactly one primary key column get one.
+    fn impl_hydrate(&self) -> TokenStream {
+        let mut primary_keys = self.primary_key_fields();
+        let (Some(primary_key), None) = (primary_keys.next(), primary_keys.next()) else {
+            return quote!();
+        };
+
+        let ident = self.ident();
+        let pk_ident = primary_key.ident();
+        let pk_type = &primary_key.ty;
+        let field_types = self.all_fields().map(|f| &f.ty);
+
+        quote!(
+            /// Update a map of entities by their primary key in place with
+            /// the ones matching `filter`.
+            ///
+            /// New entities are added and changed ones are replaced, compared using
+            /// `persisted_eq`. Call `.remove_missing()` to also remove the entries
+            /// which don't match the filter.
+            ///
+            /// # Example
+            ///
+            /// ```ignore
+            #[doc = ::core::concat!("let mut cache: HashMap<_, ", ::core::stringify!(#ident), "> = HashMap::new();")]
+            #[doc = ::core::concat!("let changes = ", ::core::stringify!(#ident), "::hydrate(&mut cache, Where::Empty).remove_missing().await?;")]
+            /// ```
+            pub fn hydrate<'a>(
+                map: &'a mut ::std::collections::HashMap<#pk_type, #ident>,
+                filter: ::pg_worm::query::Where<'a>,
+            ) -> ::pg_worm::query::Hydrate<'a, #pk_type, #ident>
+            where
+                for<'__pg_worm> #pk_type: ::core::clone::Clone + ::core::cmp::Eq + ::std::hash::Hash,
+                #(for<'__pg_worm> #field_types: ::core::cmp::PartialEq),*
+            {
+                ::pg_worm::query::Hydrate::new(
+                    map,
+                    <#ident as ::pg_worm::Model<#ident>>::select().where_(filter),
+                    |entity| ::core::clone::Clone::clone(&entity.#pk_ident),
+                    #ident::persisted_eq,
+                )
+            }
+        )
+    }
+
+    /// Generate the code for the `set_cache`, `remove_cache` and `get` methods.
+    ///
+    /// Only models with exactly one primary key column get them.
+    fn impl_cache(&self) -> TokenStream {
+        let Some(primary_key) = self.single_primary_key() else {
+            return quote!();
+        };
+
+        let ident = self.ident();
+        let pk_ty = &primary_key.ty;
+
+        quote!(
+            /// Register a cache which `get` consults before querying the database.
+            ///
+            /// Updates and deletes using `update()` and `delete()` invalidate the
+            /// entities they changed. Other queries, e.g. upserts, don't.
+            pub fn set_cache(cache: ::std::sync::Arc<dyn ::pg_worm::ModelCache<#ident>>) {
+                ::pg_worm::cache::set_cache::<#ident>(cache)
+            }
+
+            /// Remove the cache registered using `set_cache`.
+            pub fn remove_cache() {
+                ::pg_worm::cache::remove_cache::<#ident>()
+            }
+
+            /// Get an entity by its primary key.
+            ///
+            /// If a cache is registered, it is consulted first
+            /// and the entity is put into it on a miss.
+            pub async fn get(key: &#pk_ty) -> ::core::result::Result<::core::option::Option<#ident>, ::pg_worm::Error> {
+                ::pg_worm::cache::get::<#ident>(key).await
+            }
+        )
+    }
+
+    /// Generate the code implementing `pg_worm::Cached`.
+    ///
+    /// Only models with exactly one primary key column implement it.
+    fn impl_cached(&self) -> TokenStream {
+        let Some(primary_key) = self.single_primary_key() else {
+            return quote!();
+        };
+
+        let ident = self.ident();
+        let pk_ident = primary_key.ident();
+        let pk_ty = &primary_key.ty;
+
+        quote!(
+            #[automatically_derived]
+            impl ::pg_worm::Cached for #ident {
+                type Key = #pk_ty;
+
+                fn key_column() -> ::pg_worm::query::Column {
+                    *#ident::#pk_ident
+                }
+
+                fn cache_slot() -> &'static ::pg_worm::cache::CacheSlot<#ident> {
+                    static SLOT: ::pg_worm::cache::CacheSlot<#ident> = ::pg_worm::cache::CacheSlot::new();
+                    &SLOT
+                }
+            }
+        )
+    }
+
+    /// Generate the code for the `archive` method.
+    ///
+    /// Only models with an archive table get one.
+    fn impl_archive(&self) -> TokenStream {
+        let Some(archive) = &self.archive_to else {
+            return quote!();
+        };
+        let ident = self.ident();
+        let table_name = self.table_name();
+
+        quote!(
+            /// Start building a query which moves rows into
+            #[doc = ::core::concat!("the `", #archive, "` table instead of only deleting them.")]
+            ///
+            /// Returns the number of rows archived.
+            pub fn archive<'a>() -> ::pg_worm::query::Archive<'a> {
+                ::pg_worm::query::Archive::new(#table_name, #archive, &#ident::COLUMNS)
+            }
+        )
+    }
+
+    /// Generate the code for the `changes_since` method.
+    ///
+    /// Only models with an `updated_at` column get one.
+    fn impl_changes_since(&self) -> TokenStream {
+        let Some(updated_at) = self.updated_at_field() else {
+            return quote!();
+        };
+        let ident = self.ident();
+        let updated_at_ident = updated_at.ident();
+        let ty = &updated_at.ty;
+        let primary_keys = self.primary_key_fields().map(|f| f.ident());
+
+        quote!(
+            /// Select all entities which have changed at or after `since`,
+            /// ordered by when they were last updated.
+            pub fn changes_since<'a>(since: &'a #ty) -> ::pg_worm::query::Select<'a, ::std::vec::Vec<#ident>> {
+                <#ident as ::pg_worm::Model<#ident>>::select()
+                    .where_(#ident::#updated_at_ident.gte(since))
+                    .order_by(#ident::#updated_at_ident, ::pg_worm::query::Order::Asc)
+                    #(
+                        .order_by(#ident::#primary_keys, ::pg_worm::query::Order::Asc)
+                    )*
+            }
+        )
+    }
+
+    /// Generate the code for implementing `ChangeFeed`.
+    ///
+    /// Only models with an `updated_at` column and
+    /// exactly one primary key column get one.
+    fn impl_change_feed(&self) -> TokenStream {
+        let mut primary_keys = self.primary_key_fields();
+        let (Some(updated_at), Some(primary_key), None) = (
+            self.updated_at_field(),
+            primary_keys.next(),
+            primary_keys.next(),
+        ) else {
+            return quote!();
+        };
+
+        let ident = self.ident();
+        let updated_at_ident = updated_at.ident();
+        let updated_at_ty = &updated_at.ty;
+        let pk_ident = primary_key.ident();
+        let pk_ty = &primary_key.ty;
+
+        quote!(
+            #[automatically_derived]
+            impl ::pg_worm::ChangeFeed for #ident {
+                type Timestamp = #updated_at_ty;
+                type Id = #pk_ty;
+
+                fn cursor_columns() -> (::pg_worm::query::Column, ::pg_worm::query::Column) {
+                    (*#ident::#updated_at_ident, *#ident::#pk_ident)
+                }
+
+                fn cursor(&self) -> (Self::Timestamp, Self::Id) {
+                    (
+                        ::core::clone::Clone::clone(&self.#updated_at_ident),
+                        ::core::clone::Clone::clone(&self.#pk_ident),
+                    )
+                }
+            }
+        )
+    }
+
+    /// The table-qualified columns, e.g. `book.id, book.title`.
+    fn column_list(&self) -> String {
+        let table_name = self.table_name();
+
+        self.live_fields()
+            .map(|f| format!("{table_name}.{}", f.sql_column_name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Generate the code needed for creating the `COLUMNS`,
+    /// `COLUMN_LIST` and `QUALIFIED_TABLE` constants.
+    fn impl_columns(&self) -> TokenStream {
+        let ident = self.ident();
+        let field_idents = self.live_fields().map(|i| i.ident());
+        let n_fields = self.live_fields().count();
+
+        let table_name = self.table_name();
+        let column_list = self.column_list();
+
+        quote!(
+            pub const COLUMNS: [::pg_worm::query::Column; #n_fields] = [
+                #(
+                    #ident::#field_idents.column()
+                ),*
+            ];
+
+            /// The table-qualified columns in the order of `COLUMNS`,
+            /// e.g. for the `SELECT` list of a raw query.
+            pub const COLUMN_LIST: &'static str = #column_list;
+
+            /// The name of the table to use in raw queries.
+            pub const QUALIFIED_TABLE: &'static str = #table_name;
+        )
+    }
+
+    /// Generate the code for implementing
+    /// the column constants.
+    /// Needs to be wrapped in an `impl` block.
+    fn impl_column_consts(&self) -> TokenStream {
+        let column_consts = self.all_fields().map(|f| f.impl_column_const(self));
         quote!(
             #(#column_consts) *
         )
@@ -277,74 +1477,476 @@ impl ModelInput {
 
         let column_names = self
             .non_generated_fields()
-            .map(|f| f.column_name())
+            .map(|f| f.sql_column_name())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let placeholders = self.insert_placeholders();
+
+        let params = self
+            .non_generated_fields()
+            .map(|f| f.bind(f.ident().to_token_stream(), self));
+        let (args, prepare_args) = self.insert_args();
+
+        quote!(
+            /// Insert a new entity into the database.
+            ///
+            /// For columns which are autogenerated (like in the example below, `id`),
+            /// no value has to be specified.
+            ///
+            /// # Example
+            ///
+            /// ```ignore
+            /// use pg_worm::Model;
+            ///
+            /// #[derive(Model)]
+            /// struct Book {
+            ///     #[column(primary_key, auto)]
+            ///     id: i64,
+            ///     title: String
+            /// }
+            ///
+            /// async fn some_func() -> Result<(), pg_worm::Error> {
+            ///     Book::insert("Foo".to_string()).await?;
+            /// }
+            /// ```
+            #[allow(non_snake_case, clippy::too_many_arguments)]
+            pub async fn insert(#args) -> ::core::result::Result<(), ::pg_worm::Error> {
+                // Format sql statement
+                let stmt = ::std::format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    #table_name,
+                    #column_names,
+                    #placeholders
+                );
+
+                #prepare_args
+
+                // Retrieve the client, or the transaction of the enclosing scope
+                let client = ::pg_worm::query::fetch_scoped_client().await?;
+
+                // Execute the query
+                client.execute(
+                    stmt.as_str(),
+                    &::pg_worm::query::Param::values(&[
+                        #(#params),*
+                    ])
+                ).await?;
+
+                // Everything's fine
+                ::core::result::Result::Ok(())
+            }
+        )
+    }
+
+    /// Generate the arguments taken by the `insert` functions,
+    /// as well as the code converting and validating them.
+    fn insert_args(&self) -> (TokenStream, TokenStream) {
+        let ident = self.ident();
+        let field_idents = self
+            .non_generated_fields()
+            .map(|f| f.ident())
+            .collect::<Vec<_>>();
+
+        let field_concrete_types = self.non_generated_fields().map(|f| f.ty.to_token_stream());
+        let field_conversions = self.non_generated_fields().map(|f| f.insert_conversion());
+        let field_generic_types = self.non_generated_fields().map(|f| f.insert_arg_type());
+
+        let validated_fields = self
+            .non_generated_fields()
+            .filter(|f| f.has_length_limits())
+            .map(|f| f.ident());
+
+        // Pass the values to the hook bundled in the insert struct
+        let before_insert = match &self.before_insert {
+            Some(hook) => {
+                let insert_ident = self.insert_struct_ident();
+                quote!(
+                    let mut value = #insert_ident { #(#field_idents),* };
+                    #hook(&mut value)?;
+                    let #insert_ident { #(#field_idents),* } = value;
+                )
+            }
+            None => quote!(),
+        };
+
+        let args = quote!(#(#field_idents: #field_generic_types),*);
+        let prepare = quote!(
+            // Convert to concrete types
+            #(
+                let #field_idents: #field_concrete_types = #field_conversions;
+            ) *
+
+            #before_insert
+
+            // Check the values before sending them
+            #(
+                #ident::#validated_fields.validate(&#validated_fields)?;
+            )*
+        );
+
+        (args, prepare)
+    }
+
+    /// Generate the code for implementing
+    /// the `insert_idempotent` function.
+    fn impl_insert_idempotent(&self) -> TokenStream {
+        let ident = self.ident();
+        let table_name = self.table_name();
+        let column_names = self.non_generated_fields().map(|f| f.sql_column_name());
+        let placeholders = self.insert_placeholders();
+        let params = self
+            .non_generated_fields()
+            .map(|f| f.bind(f.ident().to_token_stream(), self));
+        let (args, prepare_args) = self.insert_args();
+
+        // Updating a row to itself would fire the `updated_at` trigger
+        let touches_rows = self.updated_at_field().is_some();
+
+        quote!(
+            /// Insert a new entity unless one with the same idempotency key
+            /// already exists, e.g. when a message is delivered twice.
+            ///
+            /// Returns the new or the existing entity, along with
+            /// whether it has been inserted. The `key` column has to be
+            /// `unique` (or the primary key) and must not be `auto`.
+            ///
+            /// Unless the model has an `updated_at` column, this is a single
+            /// `INSERT ... ON CONFLICT DO UPDATE` statement which updates an
+            /// existing row's key to itself. Otherwise the insert and the
+            /// following `SELECT` run in a transaction.
+            ///
+            /// # Example
+            ///
+            /// ```ignore
+            /// let (event, was_new) = Event::insert_idempotent(Event::key, "abc", "payload").await?;
+            /// ```
+            #[allow(non_snake_case, clippy::too_many_arguments)]
+            pub async fn insert_idempotent(
+                idempotency_key: impl ::std::ops::Deref<Target = ::pg_worm::query::Column>,
+                #args
+            ) -> ::core::result::Result<(#ident, bool), ::pg_worm::Error> {
+                #prepare_args
+
+                let params = [#(#params),*];
+                ::pg_worm::query::insert_idempotent(
+                    #table_name,
+                    &[#(#column_names),*],
+                    #placeholders,
+                    &idempotency_key,
+                    &params,
+                    #touches_rows,
+                ).await
+            }
+        )
+    }
+
+    /// Get the name of the companion struct used for inserting.
+    fn insert_struct_ident(&self) -> Ident {
+        quote::format_ident!("{}Insert", self.ident)
+    }
+
+    /// Generate the companion struct containing all
+    /// fields which need to be set when inserting.
+    ///
+    /// It only derives `Default` if all of its fields
+    /// are known to implement it.
+    fn impl_insert_struct(&self) -> TokenStream {
+        let ident = self.ident();
+        let insert_ident = self.insert_struct_ident();
+        let vis = &self.vis;
+        let field_idents = self
+            .non_generated_fields()
+            .map(|f| f.ident())
+            .collect::<Vec<_>>();
+        let field_types = self.non_generated_fields().map(|f| &f.ty);
+
+        let default = if self.non_generated_fields().all(|f| f.has_default()) {
+            quote!(::core::default::Default,)
+        } else {
+            quote!()
+        };
+
+        let serde = if self.serde {
+            quote!(
+                #[derive(::pg_worm::serde::Serialize, ::pg_worm::serde::Deserialize)]
+                #[serde(crate = "::pg_worm::serde")]
+            )
+        } else {
+            quote!()
+        };
+
+        let doc = format!(
+            "The values needed to insert a [`{ident}`], i.e. all \
+            of its fields which aren't generated by the database."
+        );
+
+        quote!(
+            #[doc = #doc]
+            ///
+            #[doc = ::core::concat!("Pass it to [`", ::core::stringify!(#ident), "::create`].")]
+            #[derive(#default ::core::fmt::Debug, ::core::clone::Clone)]
+            #serde
+            #[allow(non_snake_case)]
+            #vis struct #insert_ident {
+                #(
+                    #[allow(missing_docs)]
+                    pub #field_idents: #field_types
+                ),*
+            }
+
+            #[automatically_derived]
+            impl ::core::convert::From<#ident> for #insert_ident {
+                fn from(value: #ident) -> #insert_ident {
+                    #insert_ident {
+                        #(
+                            #field_idents: value.#field_idents
+                        ),*
+                    }
+                }
+            }
+        )
+    }
+
+    /// Generate the code for the `create` method.
+    fn impl_create(&self) -> TokenStream {
+        let ident = self.ident();
+        let insert_ident = self.insert_struct_ident();
+        let table_name = self.table_name();
+        let field_idents = self
+            .non_generated_fields()
+            .map(|f| f.ident())
+            .collect::<Vec<_>>();
+        let params = self.non_generated_fields().map(|f| {
+            let ident = f.ident();
+            f.bind(quote!(value.#ident), self)
+        });
+        let column_names = self
+            .non_generated_fields()
+            .map(|f| f.sql_column_name())
             .collect::<Vec<_>>()
             .join(", ");
+        let placeholders = self.insert_placeholders();
+        let validated_fields = self
+            .non_generated_fields()
+            .filter(|f| f.has_length_limits())
+            .map(|f| f.ident());
+
+        let before_insert = match &self.before_insert {
+            Some(hook) => quote!(
+                let mut value = value;
+                #hook(&mut value)?;
+            ),
+            None => quote!(),
+        };
+
+        let stmt = if field_idents.is_empty() {
+            format!("INSERT INTO {table_name} DEFAULT VALUES RETURNING *")
+        } else {
+            format!("INSERT INTO {table_name} ({column_names}) VALUES ({placeholders}) RETURNING *")
+        };
+
+        quote!(
+            /// Insert a new entity into the database and return it,
+            /// including all values generated by the database.
+            ///
+            /// Unlike `insert`, the values are passed by name.
+            ///
+            /// # Example
+            ///
+            /// ```ignore
+            #[doc = ::core::concat!("let created = ", ::core::stringify!(#ident), "::create(", ::core::stringify!(#insert_ident), " {")]
+            ///     title: "Foo".into(),
+            ///     ..Default::default()
+            /// }).await?;
+            /// ```
+            pub async fn create(value: #insert_ident) -> ::core::result::Result<#ident, ::pg_worm::Error> {
+                #before_insert
 
-        let placeholders = (1..=self.non_generated_fields().count())
-            .map(|i| format!("${i}"))
+                // Check the values before sending them
+                #(
+                    #ident::#validated_fields.validate(&value.#validated_fields)?;
+                )*
+
+                let client = ::pg_worm::query::fetch_scoped_client().await?;
+                let row = client.query_one(
+                    #stmt,
+                    &::pg_worm::query::Param::values(&[
+                        #(#params),*
+                    ])
+                ).await?;
+
+                <#ident as ::core::convert::TryFrom<::pg_worm::pg::Row>>::try_from(row)
+            }
+        )
+    }
+
+    /// Generate the code for the `insert_many_lenient` method.
+    fn impl_insert_many_lenient(&self) -> TokenStream {
+        let ident = self.ident();
+        let insert_ident = self.insert_struct_ident();
+        let table_name = self.table_name();
+        let field_idents = self
+            .non_generated_fields()
+            .map(|f| f.ident())
+            .collect::<Vec<_>>();
+        let params = self.non_generated_fields().map(|f| {
+            let ident = f.ident();
+            f.bind(quote!(value.#ident), self)
+        });
+        let column_names = self
+            .non_generated_fields()
+            .map(|f| f.sql_column_name())
             .collect::<Vec<_>>()
             .join(", ");
+        let placeholders = self.insert_placeholders();
+        let validated_fields = self
+            .non_generated_fields()
+            .filter(|f| f.has_length_limits())
+            .map(|f| f.ident());
+
+        // Failing hooks are reported like any other failing row
+        let (before_insert, check_hook) = match &self.before_insert {
+            Some(hook) => (
+                quote!(
+                    let mut rows = rows;
+                    let hooked = ::core::iter::Iterator::map(rows.iter_mut(), #hook);
+                    let mut hooked = ::core::iter::IntoIterator::into_iter(
+                        ::core::iter::Iterator::collect::<::std::vec::Vec<::core::result::Result<(), ::pg_worm::Error>>>(hooked),
+                    );
+                ),
+                quote!(::core::iter::Iterator::next(&mut hooked).unwrap_or(::core::result::Result::Ok(()))?;),
+            ),
+            None => (quote!(), quote!()),
+        };
+
+        let stmt = if field_idents.is_empty() {
+            format!("INSERT INTO {table_name} DEFAULT VALUES")
+        } else {
+            format!("INSERT INTO {table_name} ({column_names}) VALUES ({placeholders})")
+        };
+
+        quote!(
+            /// Insert many entities, skipping the ones which can't be inserted
+            /// (e.g. because they violate a constraint) instead of failing
+            /// as a whole. Returns how many rows were inserted, as well as
+            /// which ones failed and why.
+            ///
+            /// Each row is inserted in a savepoint of one transaction, which takes
+            /// about three round trips per row. That is fine for imports of
+            /// some thousand rows, but much slower than a single bulk insert.
+            ///
+            /// Inside a `Transaction::scope`, concurrent calls take turns. Don't run
+            /// other statements of the scope concurrently, though: they would be
+            /// rolled back along with a failing row.
+            ///
+            /// # Example
+            ///
+            /// ```ignore
+            #[doc = ::core::concat!("let report = ", ::core::stringify!(#ident), "::insert_many_lenient(rows).await?;")]
+            /// for (index, err) in report.failed {
+            ///     println!("row {index} failed: {:?}", err.kind);
+            /// }
+            /// ```
+            pub async fn insert_many_lenient(
+                rows: impl ::core::iter::IntoIterator<Item = #insert_ident>,
+            ) -> ::core::result::Result<::pg_worm::query::InsertReport, ::pg_worm::Error> {
+                let rows = ::core::iter::Iterator::collect::<::std::vec::Vec<#insert_ident>>(
+                    ::core::iter::IntoIterator::into_iter(rows),
+                );
+                #before_insert
+                let params = ::core::iter::Iterator::map(rows.iter(), |value| -> ::core::result::Result<::std::vec::Vec<::pg_worm::query::Param<'_>>, ::pg_worm::Error> {
+                        #check_hook
+
+                        // Check the values before sending them
+                        #(
+                            #ident::#validated_fields.validate(&value.#validated_fields)?;
+                        )*
+
+                        ::core::result::Result::Ok(::std::vec![
+                            #(#params),*
+                        ])
+                    });
+                let params = ::core::iter::Iterator::collect(params);
+
+                ::pg_worm::query::insert_lenient(#stmt, params).await
+            }
+        )
+    }
 
+    /// Generate the code for the `seed` method.
+    fn impl_seed(&self) -> TokenStream {
+        let ident = self.ident();
+        let insert_ident = self.insert_struct_ident();
+        let table_name = self.table_name();
         let field_idents = self
             .non_generated_fields()
             .map(|f| f.ident())
             .collect::<Vec<_>>();
+        let params = self.non_generated_fields().map(|f| {
+            let ident = f.ident();
+            f.bind(quote!(value.#ident), self)
+        });
+        let column_names = self
+            .non_generated_fields()
+            .map(|f| f.sql_column_name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let placeholders = self.insert_placeholders();
+        let validated_fields = self
+            .non_generated_fields()
+            .filter(|f| f.has_length_limits())
+            .map(|f| f.ident());
+
+        let before_insert = match &self.before_insert {
+            Some(hook) => quote!(
+                let mut rows = rows;
+                for value in rows.iter_mut() {
+                    #hook(value)?;
+                }
+            ),
+            None => quote!(),
+        };
 
-        let field_concrete_types = self.non_generated_fields().map(|f| f.ty.to_token_stream());
-        let field_generic_types = self.non_generated_fields().map(|f| f.insert_arg_type());
+        let stmt = if field_idents.is_empty() {
+            format!("INSERT INTO {table_name} DEFAULT VALUES")
+        } else {
+            format!("INSERT INTO {table_name} ({column_names}) VALUES ({placeholders})")
+        };
 
         quote!(
-            /// Insert a new entity into the database.
-            ///
-            /// For columns which are autogenerated (like in the example below, `id`),
-            /// no value has to be specified.
-            ///
-            /// # Example
-            ///
-            /// ```ignore
-            /// use pg_worm::Model;
+            /// Insert a fixed set of entities, but only if the table is empty,
+            /// e.g. to fill a development database right after creating it.
+            /// Returns how many rows were inserted, which is zero if the
+            /// table had rows already.
             ///
-            /// #[derive(Model)]
-            /// struct Book {
-            ///     #[column(primary_key, auto)]
-            ///     id: i64,
-            ///     title: String
-            /// }
+            /// All rows are inserted in one transaction, so if one fails,
+            /// none are inserted.
             ///
-            /// async fn some_func() -> Result<(), pg_worm::Error> {
-            ///     Book::insert("Foo".to_string()).await?;
-            /// }
-            /// ```
-            pub async fn insert(
-                #(#field_idents: #field_generic_types),*
-            ) -> Result<(), pg_worm::Error> {
-                // Format sql statement
-                let stmt = format!(
-                    "INSERT INTO {} ({}) VALUES ({})",
-                    #table_name,
-                    #column_names,
-                    #placeholders
+            #[doc = ::core::concat!("The rows are given as [`", ::core::stringify!(#insert_ident), "`]s.")]
+            pub async fn seed(
+                rows: impl ::core::iter::IntoIterator<Item = #insert_ident>,
+            ) -> ::core::result::Result<u64, ::pg_worm::Error> {
+                let rows = ::core::iter::Iterator::collect::<::std::vec::Vec<#insert_ident>>(
+                    ::core::iter::IntoIterator::into_iter(rows),
                 );
+                #before_insert
 
-                // Convert to concrete types
-                #(
-                    let #field_idents: #field_concrete_types = #field_idents.into();
-                ) *
-
-                // Retrieve the client
-                let client = pg_worm::pool::fetch_client().await?;
+                // Check the values before sending them
+                for value in rows.iter() {
+                    #(
+                        #ident::#validated_fields.validate(&value.#validated_fields)?;
+                    )*
+                }
 
-                // Execute the query
-                client.execute(
-                    stmt.as_str(),
-                    &[
-                        #(&#field_idents),*
+                let params = ::core::iter::Iterator::map(rows.iter(), |value| -> ::std::vec::Vec<::pg_worm::query::Param<'_>> {
+                    ::std::vec![
+                        #(#params),*
                     ]
-                ).await?;
+                });
+                let params = ::core::iter::Iterator::collect(params);
 
-                // Everything's fine
-                Ok(())
+                ::pg_worm::query::insert_seed(#table_name, #stmt, params).await
             }
         )
     }
@@ -360,8 +1962,36 @@ impl ModelField {
     /// Initialization function called before each
     /// field is stored.
     fn init(mut field: ModelField) -> darling::Result<ModelField> {
+        field.auto = field.auto_options.is_some();
         let ty = &field.ty;
 
+        // Skipped fields aren't columns, so there is nothing to check
+        if field.skip {
+            let column_options = field.column_name.is_some()
+                || field.auto
+                || field.primary_key
+                || field.unique
+                || field.updated_at
+                || field.deprecated
+                || field.sensitive
+                || field.sql_type.is_some()
+                || field.custom
+                || field.max_length.is_some()
+                || field.min_length.is_some()
+                || field.max_bytes.is_some()
+                || field.min_bytes.is_some()
+                || field.collate.is_some()
+                || field.index.is_some()
+                || field.encode_with.is_some()
+                || field.decode_with.is_some()
+                || field.insert_expr.is_some();
+            if column_options {
+                spanned_error!("pg-worm: `skip` can't be combined with other options", ty)
+            }
+
+            return Ok(field);
+        }
+
         // Extract relevant type from the path
         let syn::Type::Path(path) = ty else {
             spanned_error!("pg-worm: unsupported type", &ty)
@@ -386,17 +2016,176 @@ impl ModelField {
             _ => (),
         };
 
-        if field.auto {
+        // The type wrapped in `Option`, if there is one
+        let inner_seg = if field.nullable {
+            generic_arg(last_seg).and_then(last_segment)
+        } else {
+            Some(last_seg)
+        };
+
+        // A `Vec<u8>` is binary data, not an array
+        if let Some(seg) = inner_seg.filter(|seg| seg.ident == "Vec") {
+            if generic_arg(seg)
+                .and_then(last_segment)
+                .is_some_and(|arg| arg.ident == "u8")
+            {
+                field.array = false;
+                field.bytes = true;
+            }
+        }
+
+        let is_string = inner_seg.is_some_and(|seg| seg.ident == "String");
+
+        // Collect all problems so they are reported at once
+        let mut errors = Error::accumulator();
+        let mut error = |msg: &str| errors.push(Error::custom(msg).with_span(ty));
+
+        if (field.max_length.is_some() || field.min_length.is_some()) && !is_string {
+            error("pg-worm: `max_length` and `min_length` are only available for `String`s");
+        }
+
+        if (field.max_bytes.is_some() || field.min_bytes.is_some()) && !(is_string || field.bytes) {
+            error("pg-worm: `max_bytes` and `min_bytes` are only available for `String`s and `Vec<u8>`s");
+        }
+
+        if matches!((field.min_length, field.max_length), (Some(min), Some(max)) if min > max)
+            || matches!((field.min_bytes, field.max_bytes), (Some(min), Some(max)) if min > max)
+        {
+            error("pg-worm: minimum is larger than maximum");
+        }
+
+        if field.collate.is_some() && !is_string {
+            error("pg-worm: `collate` is only available for `String`s");
+        }
+
+        if field
+            .collate
+            .as_ref()
+            .is_some_and(|c| c.is_empty() || c.contains('"'))
+        {
+            error("pg-worm: `collate` has to be the name of a collation");
+        }
+
+        if field.custom && (field.array || field.bytes) {
+            error("pg-worm: `custom` types can't be used in arrays, use `sql_type` instead");
+        }
+
+        if field.deprecated && (field.primary_key || field.unique || field.auto || field.updated_at)
+        {
+            error("pg-worm: `deprecated` can't be combined with `primary_key`, `unique`, `auto` or `updated_at`");
+        }
+
+        if (field.encode_with.is_some() || field.decode_with.is_some())
+            && (field.primary_key || field.auto || field.updated_at || field.deprecated)
+        {
+            error("pg-worm: `encode_with` and `decode_with` can't be combined with `primary_key`, `auto`, `updated_at` or `deprecated`");
+        }
+
+        if let Some(expr) = &field.insert_expr {
+            if expr.matches('?').count() != 1 {
+                error("pg-worm: `insert_expr` has to contain exactly one `?`, which is replaced by the value");
+            }
+
+            if field.auto || field.updated_at || field.deprecated {
+                error("pg-worm: `insert_expr` can't be combined with `auto`, `updated_at` or `deprecated`");
+            }
+        }
+
+        if let Some(index) = field.index.as_ref().map(|i| i.clone().unwrap_or_default()) {
+            let is_word = |s: &String| {
+                !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            };
+
+            if !index.using.iter().chain(&index.ops).all(is_word) {
+                error("pg-worm: the `using` and `ops` of an index have to be plain names");
+            }
+
+            if field.deprecated {
+                error("pg-worm: `deprecated` can't be combined with `index`");
+            }
+        }
+
+        if field.updated_at {
+            if field.auto || field.nullable {
+                error("pg-worm: `updated_at` can't be combined with `auto` or `Option`");
+            }
+
+            match last_seg.ident.to_string().as_str() {
+                "SystemTime" => (),
+                #[cfg(feature = "time")]
+                "OffsetDateTime" | "PrimitiveDateTime" => (),
+                _ => error("pg-worm: `updated_at` is only available for timestamps"),
+            }
+
+            // The column is set by the database, on insert by
+            // its default and on update by a trigger.
+            field.auto = true;
+            field.auto_gen_stmt = "DEFAULT now()".to_string();
+        } else if field.auto {
+            let options = field
+                .auto_options
+                .clone()
+                .unwrap_or_default()
+                .unwrap_or_default();
+            if options.uuid_v7 && last_seg.ident != "Uuid" {
+                error("pg-worm: `uuid_v7` is only available for uuid");
+            }
+
             field.auto_gen_stmt = match last_seg.ident.to_string().as_str() {
+                #[cfg(feature = "uuid")]
+                "Uuid" if options.uuid_v7 => "DEFAULT uuid_generate_v7()",
                 #[cfg(feature = "uuid")]
                 "Uuid" => "DEFAULT gen_random_uuid()",
                 "i16" | "i32" | "i64" => "GENERATED ALWAYS AS IDENTITY",
-                _ => spanned_error!("pg-worm: `auto` is only available for integers and uuid (with the `uuid` feature enabled)", &ty)
+                // Newtypes wrapping an integer, checked
+                // at compile time, see `impl_identity_check`
+                _ if field.custom => "GENERATED ALWAYS AS IDENTITY",
+                _ => {
+                    error("pg-worm: `auto` is only available for integers and uuid (with the `uuid` feature enabled)");
+                    ""
+                }
             }
             .to_string();
         }
 
-        Ok(field)
+        // `MONEY` values are sent as `Cents`, so `String`s are converted
+        #[cfg(feature = "money")]
+        if is_string
+            && field
+                .sql_type
+                .as_ref()
+                .is_some_and(|t| t.eq_ignore_ascii_case("money"))
+            && field.encode_with.is_none()
+            && field.decode_with.is_none()
+        {
+            if field.primary_key || field.auto || field.deprecated {
+                error("pg-worm: `String`s of `MONEY` columns can't be combined with `primary_key`, `auto` or `deprecated`");
+            }
+
+            let (encode, decode): (syn::Path, syn::Path) = if field.nullable {
+                (
+                    syn::parse_quote!(::pg_worm::money::text::encode_nullable),
+                    syn::parse_quote!(::pg_worm::money::text::decode_nullable),
+                )
+            } else {
+                (
+                    syn::parse_quote!(::pg_worm::money::text::encode),
+                    syn::parse_quote!(::pg_worm::money::text::decode),
+                )
+            };
+            field.encode_with = Some(encode);
+            field.decode_with = Some(decode);
+        }
+
+        // Check the type now instead of when generating the code
+        errors.handle(field.sql_data_type());
+
+        errors.finish_with(field)
+    }
+
+    /// Whether the column is generated using `uuid_generate_v7()`.
+    fn uuid_v7(&self) -> bool {
+        matches!(&self.auto_options, Some(Override::Explicit(options)) if options.uuid_v7)
     }
 
     /// Get the field's identifier.
@@ -412,18 +2201,50 @@ impl ModelField {
             return column_name.clone();
         }
 
-        self.ident().to_string().to_lowercase()
+        let ident = self.ident().unraw().to_string();
+        match self.rename_all {
+            RenameAll::Snake => word_converter()
+                .to_case(convert_case::Case::Snake)
+                .convert(ident),
+            RenameAll::Camel => word_converter()
+                .to_case(convert_case::Case::Camel)
+                .convert(ident),
+            RenameAll::None => ident,
+        }
     }
 
-    fn create_column(&self) -> TokenStream {
-        let name = self.column_name();
-        let mut dtype = self.try_pg_datatype().unwrap().to_string();
-        if self.array {
-            dtype.push_str("[]");
+    /// Get the column's name as it has to be written in SQL.
+    ///
+    /// Names which Postgres would otherwise fold to
+    /// lowercase (e.g. `camelCase` ones) are quoted.
+    fn sql_column_name(&self) -> String {
+        quote_if_needed(&self.column_name())
+    }
+
+    fn create_column(&self, table: &ModelInput) -> TokenStream {
+        let name = self.sql_column_name();
+        let model = table.ident();
+        let ident = self.ident();
+
+        // The type is taken from the column constant,
+        // so both always agree.
+        let mut res = quote!(::pg_worm::migration::Column::new(
+            ::std::string::ToString::to_string(#name),
+            ::std::string::ToString::to_string(#model::#ident.pg_type())
+        ));
+
+        if let Some(collation) = &self.collate {
+            res.extend(quote!(.collate(#collation)));
         }
 
-        let mut res =
-            quote!(::pg_worm::migration::Column::new(#name.to_string(), #dtype.to_string()));
+        for check in self.length_checks() {
+            res.extend(quote!(.check(#check)));
+        }
+
+        if self.auto {
+            let generated = &self.auto_gen_stmt;
+            res.extend(quote!(.generated(#generated)));
+        }
 
         if self.unique {
             res.extend(quote!(.unique()));
@@ -436,8 +2257,71 @@ impl ModelField {
         res
     }
 
+    /// Whether this field's values are checked for their length.
+    fn has_length_limits(&self) -> bool {
+        self.max_length.is_some()
+            || self.min_length.is_some()
+            || self.max_bytes.is_some()
+            || self.min_bytes.is_some()
+    }
+
+    /// Get the SQL data type used when creating the column.
+    ///
+    /// An explicit `sql_type` always wins.
+    fn sql_data_type(&self) -> Result<Sql, Error> {
+        if let Some(sql_type) = &self.sql_type {
+            return Ok(sql_type.as_str().into());
+        }
+
+        // The type is only known once the SQL is built at runtime
+        if self.custom {
+            let ty = self.nullable_inner_type().unwrap_or(&self.ty);
+            return Ok(Sql::pg_type(ty.clone()));
+        }
+
+        if let Some(max_length) = self.max_length {
+            return Ok(format!("VARCHAR({max_length})").into());
+        }
+
+        let mut data_type = self.try_pg_datatype()?.to_string();
+        if self.array {
+            data_type.push_str("[]");
+        }
+
+        Ok(data_type.into())
+    }
+
+    /// Get the `CHECK` expressions enforcing this column's
+    /// length constraints.
+    ///
+    /// A `max_length` is usually enforced by the `VARCHAR` type,
+    /// unless an explicit `sql_type` is set.
+    fn length_checks(&self) -> Vec<String> {
+        let name = self.sql_column_name();
+        let mut checks = Vec::new();
+
+        if let Some(max) = self.max_length.filter(|_| self.sql_type.is_some()) {
+            checks.push(format!("char_length({name}) <= {max}"));
+        }
+        if let Some(min) = self.min_length {
+            checks.push(format!("char_length({name}) >= {min}"));
+        }
+        if let Some(max) = self.max_bytes {
+            checks.push(format!("octet_length({name}) <= {max}"));
+        }
+        if let Some(min) = self.min_bytes {
+            checks.push(format!("octet_length({name}) >= {min}"));
+        }
+
+        checks
+    }
+
     /// Get the corresponding postgres type
     fn try_pg_datatype(&self) -> Result<Type, Error> {
+        if self.bytes {
+            return Ok(Type::BYTEA);
+        }
+
         let ty = self.ty.clone();
 
         let syn::Type::Path(path) = &self.ty else {
@@ -498,6 +2382,7 @@ impl ModelField {
             "f32" => Type::FLOAT4,
             "f64" => Type::FLOAT8,
             "bool" => Type::BOOL,
+            "SystemTime" => Type::TIMESTAMPTZ,
             // `serde_json` support
             #[cfg(feature = "serde-json")]
             "Value" => Type::JSONB,
@@ -510,9 +2395,17 @@ impl ModelField {
             "PrimitiveDateTime" => Type::TIMESTAMP,
             #[cfg(feature = "time")]
             "OffsetDateTime" => Type::TIMESTAMPTZ,
+            #[cfg(feature = "time")]
+            "PgInterval" => Type::INTERVAL,
             // `uuid` support
             #[cfg(feature = "uuid")]
             "Uuid" => Type::UUID,
+            // `money` support
+            #[cfg(feature = "money")]
+            "Cents" => Type::INT8,
+            // `net` support
+            #[cfg(feature = "net")]
+            "IpAddr" => Type::INET,
             _ => spanned_error!(
                 "pg-worm: unsupported type. did you forget to enable a feature?",
                 &ty
@@ -522,45 +2415,266 @@ impl ModelField {
 
     /// Get the SQL representing the column needed
     /// for creating a table.
-    fn column_creation_sql(&self) -> Result<String, Error> {
+    fn column_creation_sql(&self) -> Result<Sql, Error> {
         // The list of "args" for the sql statement.
         // Includes at least the column name and datatype.
-        let mut args = vec![self.column_name(), self.try_pg_datatype()?.to_string()];
+        let mut args = vec![self.sql_column_name().into(), self.sql_data_type()?];
+        if let Some(collation) = &self.collate {
+            args.push(format!("COLLATE \"{collation}\"").into());
+        }
 
         // This macro allows adding an arg to the list
         // under a given condition.
         macro_rules! arg {
             ($cond:expr, $sql:expr) => {
                 if $cond {
-                    args.push($sql.to_string());
+                    args.push($sql.to_string().into());
                 }
             };
         }
 
         // Add possible args
-        arg!(self.array, "[]");
         arg!(self.primary_key, "PRIMARY KEY");
         arg!(self.auto, self.auto_gen_stmt);
         arg!(self.unique, "UNIQUE");
         arg!(!(self.primary_key || self.nullable), "NOT NULL");
 
+        for check in self.length_checks() {
+            args.push(format!("CHECK ({check})").into());
+        }
+
         // Join the args, seperated by a space and return them
-        Ok(args.join(" "))
+        Ok(Sql::join(args, " "))
+    }
+
+    /// Get the SQL statement creating this column's index, if it has one.
+    fn index_creation_sql(&self, table: &ModelInput) -> Option<String> {
+        let index = self.index.clone()?.unwrap_or_default();
+        let column = self.sql_column_name();
+
+        let using = index
+            .using
+            .map(|using| format!(" USING {using}"))
+            .unwrap_or_default();
+        let ops = index.ops.map(|ops| format!(" {ops}")).unwrap_or_default();
+        let name = table.derived_name(&format!("{}_idx", column.trim_matches('"')));
+        let table_name = table.table_name();
+
+        Some(format!(
+            "CREATE INDEX {name} ON {table_name}{using} ({column}{ops})"
+        ))
+    }
+
+    /// Get the SQL representing the column in
+    /// the model's archive table.
+    ///
+    /// Archived rows are only ever inserted, so no
+    /// constraints except `NOT NULL` are needed.
+    fn archive_column_creation_sql(&self) -> Result<Sql, Error> {
+        let mut sql = Sql::from(format!("{} ", self.sql_column_name()));
+        sql.push(self.sql_data_type()?);
+        if !self.nullable {
+            sql.push(" NOT NULL");
+        }
+
+        Ok(sql)
     }
 
     /// The datatype which should be provided when
     /// calling the `insert` function.
+    ///
+    /// Nullable fields accept anything implementing `IntoNullable`,
+    /// so that e.g. `"foo"` and `None` can be passed directly.
     fn insert_arg_type(&self) -> TokenStream {
+        if let Some(inner) = self.nullable_inner_type() {
+            return quote!(impl ::pg_worm::query::IntoNullable<#inner>);
+        }
+
         let ty = self.ty.to_token_stream();
-        quote!(impl Into<#ty> + pg_worm::pg::types::ToSql + Sync)
+        quote!(impl ::core::convert::Into<#ty> + ::pg_worm::pg::types::ToSql + ::core::marker::Sync)
+    }
+
+    /// The expression converting an `insert` argument
+    /// to the field's type.
+    fn insert_conversion(&self) -> TokenStream {
+        let ident = self.ident();
+        if self.nullable_inner_type().is_some() {
+            return quote!(::pg_worm::query::IntoNullable::into_nullable(#ident));
+        }
+
+        quote!(::core::convert::Into::into(#ident))
+    }
+
+    /// The name of the type generated for encoding this
+    /// field's values, see `encode_with`.
+    fn codec_ident(&self, table: &ModelInput) -> Ident {
+        quote::format_ident!("__{}_{}_codec", table.ident, self.ident().unraw())
+    }
+
+    /// Generate a check that the `PgType` of an `auto` field using
+    /// `#[column(custom)]` is an integer type, which identity columns need.
+    fn impl_identity_check(&self) -> TokenStream {
+        if !(self.auto && self.custom) || self.sql_type.is_some() {
+            return quote!();
+        }
+
+        let ty = self.nullable_inner_type().unwrap_or(&self.ty);
+        quote_spanned!(ty.span() =>
+            const _: () = ::core::assert!(
+                ::pg_worm::is_integer_type(<#ty as ::pg_worm::PgType>::PG_TYPE),
+                "pg-worm: `auto` on a custom type needs its `PgType` to be an integer type"
+            );
+        )
+    }
+
+    /// Generate the type encoding this field's values
+    /// if it has an `encode_with` function.
+    fn impl_codec(&self, table: &ModelInput) -> TokenStream {
+        let Some(encode) = &self.encode_with else {
+            return quote!();
+        };
+        let codec = self.codec_ident(table);
+        let ty = &self.ty;
+
+        quote!(
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            struct #codec;
+
+            #[automatically_derived]
+            impl ::pg_worm::query::Codec<#ty> for #codec {
+                fn encode(
+                    value: &#ty,
+                    ty: &::pg_worm::pg::types::Type,
+                    out: &mut ::pg_worm::pg::types::private::BytesMut,
+                ) -> ::core::result::Result<::pg_worm::pg::types::IsNull, ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Sync + ::core::marker::Send>> {
+                    ::pg_worm::pg::types::ToSql::to_sql_checked(&#encode(value), ty, out)
+                }
+            }
+        )
+    }
+
+    /// The expression passing the field's `value` as a parameter,
+    /// encoded if it has an `encode_with` function and
+    /// masked if it is `sensitive`.
+    fn bind(&self, value: TokenStream, table: &ModelInput) -> TokenStream {
+        let ty = &self.ty;
+        if self.encode_with.is_none() && self.sensitive {
+            return quote!(::pg_worm::query::Sensitive::wrap::<#ty>(&#value));
+        }
+        if self.encode_with.is_none() {
+            return quote!(<::pg_worm::query::Param<'_> as ::core::convert::From<&#ty>>::from(&#value));
+        }
+
+        let wrap = self.encoded_wrap(table);
+        quote!(#wrap(&#value))
+    }
+
+    /// The function turning a reference to a value into an
+    /// encoded parameter, if the field has an `encode_with` function.
+    fn encoded_wrap(&self, table: &ModelInput) -> TokenStream {
+        let ty = &self.ty;
+        let codec = self.codec_ident(table);
+        if self.sensitive {
+            quote!(::pg_worm::query::Encoded::<#ty, #codec>::wrap_sensitive)
+        } else {
+            quote!(::pg_worm::query::Encoded::<#ty, #codec>::wrap)
+        }
+    }
+
+    /// Like [`ModelField::encoded_wrap`], but the function takes
+    /// the value, so the parameter owns it.
+    fn encoded_owned(&self, table: &ModelInput) -> TokenStream {
+        let ty = &self.ty;
+        let codec = self.codec_ident(table);
+        if self.sensitive {
+            quote!(::pg_worm::query::Encoded::<#ty, #codec>::owned_sensitive)
+        } else {
+            quote!(::pg_worm::query::Encoded::<#ty, #codec>::owned)
+        }
+    }
+
+    /// The placeholder of this field's value in an `INSERT`,
+    /// i.e. `$index` or its `insert_expr` using it.
+    fn insert_placeholder(&self, index: usize) -> String {
+        let placeholder = format!("${index}");
+
+        match &self.insert_expr {
+            Some(expr) => expr.replace('?', &placeholder),
+            None => placeholder,
+        }
+    }
+
+    /// The expression turning the `raw` value read from
+    /// a row into the field's value.
+    fn decode(&self, raw: TokenStream) -> TokenStream {
+        match &self.decode_with {
+            Some(decode) => quote!(#decode(#raw)?),
+            None => raw,
+        }
+    }
+
+    /// Whether this field's type is known to implement `Default`.
+    fn has_default(&self) -> bool {
+        if self.nullable || self.array || self.bytes {
+            return true;
+        }
+
+        last_segment(&self.ty).is_some_and(|seg| {
+            matches!(
+                seg.ident.to_string().as_str(),
+                "String" | "bool" | "i16" | "i32" | "i64" | "u64" | "f32" | "f64" | "Value"
+            )
+        })
+    }
+
+    /// Get the type string values of this column are parsed as,
+    /// if it is a common type implementing `FromStr`.
+    ///
+    /// Encoded values aren't parsed, since
+    /// the database only knows the encoded value.
+    fn parsed_type(&self) -> Option<&syn::Type> {
+        if self.encode_with.is_some() {
+            return None;
+        }
+
+        self.parsable_type()
+    }
+
+    /// Get the type a string value of this field is parsed as,
+    /// whether or not it is encoded afterwards.
+    fn parsable_type(&self) -> Option<&syn::Type> {
+        if self.array || self.bytes {
+            return None;
+        }
+
+        let ty = self.nullable_inner_type().unwrap_or(&self.ty);
+        let parsable = last_segment(ty).is_some_and(|seg| {
+            matches!(
+                seg.ident.to_string().as_str(),
+                "String" | "bool" | "i16" | "i32" | "i64" | "f32" | "f64" | "Uuid"
+            )
+        });
+
+        parsable.then_some(ty)
+    }
+
+    /// Get the `T` of an `Option<T>` field.
+    fn nullable_inner_type(&self) -> Option<&syn::Type> {
+        if !self.nullable {
+            return None;
+        }
+
+        last_segment(&self.ty).and_then(generic_arg)
     }
 
     /// Generate the code for creating this field's
     /// column constant.
     fn impl_column_const(&self, table: &ModelInput) -> TokenStream {
         let table_name = table.table_name();
-        let col_name = self.column_name();
+        let col_name = self.sql_column_name();
         let ident = self.ident();
+        let model = table.ident();
         let rs_type = &self.ty;
 
         // Vec containing the method calls.
@@ -581,11 +2695,180 @@ impl ModelField {
         prop!(self.unique, unique);
         prop!(self.primary_key, primary_key);
         prop!(self.nullable, nullable);
+        prop!(self.sensitive, sensitive);
+
+        // Macro for passing a limit to the constant
+        // if it's been set.
+        macro_rules! limit {
+            ($limit:ident) => {
+                if let Some(n) = self.$limit {
+                    props.push(quote!(.$limit(#n)));
+                }
+            };
+        }
+
+        limit!(max_length);
+        limit!(min_length);
+        limit!(max_bytes);
+        limit!(min_bytes);
+
+        // Unsupported types are reported when creating the table
+        if self.custom && self.sql_type.is_none() {
+            let ty = self.nullable_inner_type().unwrap_or(&self.ty);
+            props.push(quote!(.with_pg_type(<#ty as ::pg_worm::PgType>::PG_TYPE)));
+        } else if let Ok(pg_type) = self.sql_data_type() {
+            let pg_type = pg_type.to_string();
+            props.push(quote!(.with_pg_type(#pg_type)));
+        }
+
+        if self.encode_with.is_some() {
+            let wrap = self.encoded_wrap(table);
+            let owned = self.encoded_owned(table);
+            props.push(quote!(.encoded(#wrap, #owned)));
+
+            // Filters like `contains` encode the elements of arrays as an array
+            let element = last_segment(&self.ty)
+                .filter(|seg| seg.ident == "Vec")
+                .and_then(generic_arg);
+            if let Some(element) = element {
+                props.push(quote!(.with_elements(::pg_worm::query::clone_elements::<#element>)));
+            }
+        }
+
+        if let Some(expr) = &self.insert_expr {
+            props.push(quote!(.with_insert_expr(#expr)));
+        }
+
+        if let Some(ty) = self.parsed_type() {
+            props.push(quote!(.with_parser(::pg_worm::query::parse_value::<#ty>)));
+        } else if let (Some(ty), Some(_)) = (self.parsable_type(), &self.encode_with) {
+            let codec = self.codec_ident(table);
+            props.push(
+                quote!(.with_encoder(::pg_worm::query::parse_encoded::<#ty, #rs_type, #codec>)),
+            );
+        }
+
+        // Warn about filtering on a column which is going to be dropped
+        let deprecated = if self.deprecated {
+            quote!(#[deprecated(note = "this column is pending removal")])
+        } else {
+            quote!()
+        };
 
         quote!(
             #[allow(non_upper_case_globals)]
-            pub const #ident: pg_worm::query::TypedColumn<#rs_type> = pg_worm::query::TypedColumn::new(#table_name, #col_name)
+            #deprecated
+            pub const #ident: ::pg_worm::query::TypedColumn<#rs_type, #model> = ::pg_worm::query::TypedColumn::new(#table_name, #col_name)
+                .of_model::<#model>()
                 #(#props)*;
         )
     }
 }
+
+/// A converter which splits field names into words at underscores
+/// and case changes, but keeps digits with their word
+/// (i.e. `address1` stays `address1`).
+fn word_converter() -> convert_case::Converter {
+    use convert_case::Boundary;
+
+    convert_case::Converter::new().set_boundaries(&[
+        Boundary::Underscore,
+        Boundary::LowerUpper,
+        Boundary::Acronym,
+    ])
+}
+
+/// Pluralize an english noun, covering the common cases.
+fn pluralize(word: &str) -> String {
+    let consonant_y =
+        word.ends_with('y') && !word[..word.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u']);
+    if consonant_y {
+        return format!("{}ies", &word[..word.len() - 1]);
+    }
+
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|i| word.ends_with(i))
+    {
+        return format!("{word}es");
+    }
+
+    format!("{word}s")
+}
+
+/// Merge the code generated for singular and plural table names,
+/// picking each literal which differs, like `"SELECT * FROM books"`,
+/// using the crate's `__PG_WORM_TABLE_NAMING` set by `table_naming!`.
+///
+/// Crates which don't set it fall back to the one of `pg_worm`, as the
+/// glob import is shadowed by the one of the crate's root. The names
+/// only appear in expressions, so attributes are kept as they are.
+fn merge_namings(singular: TokenStream, plural: TokenStream) -> TokenStream {
+    let mut merged = TokenStream::new();
+    let mut in_attribute = false;
+    for (singular, plural) in singular.into_iter().zip(plural) {
+        let token = match (singular, plural) {
+            (TokenTree::Group(singular), TokenTree::Group(plural)) if !in_attribute => {
+                let stream = merge_namings(singular.stream(), plural.stream());
+                let mut group = Group::new(singular.delimiter(), stream);
+                group.set_span(singular.span());
+                TokenTree::Group(group)
+            }
+            (TokenTree::Literal(singular), TokenTree::Literal(plural))
+                if singular.to_string() != plural.to_string() =>
+            {
+                let choice = quote!((if {
+                    #[allow(unused_imports)]
+                    use ::pg_worm::__table_naming::*;
+                    {
+                        #[allow(unused_imports)]
+                        use crate::*;
+                        ::core::matches!(__PG_WORM_TABLE_NAMING, ::pg_worm::TableNaming::Plural)
+                    }
+                } {
+                    #plural
+                } else {
+                    #singular
+                }));
+                choice.into_iter().next().expect("the choice is a group")
+            }
+            (singular, _) => singular,
+        };
+
+        in_attribute = matches!(&token, TokenTree::Punct(p) if p.as_char() == '#');
+        merged.extend([token]);
+    }
+
+    merged
+}
+
+/// Get the last segment of a type's path.
+fn last_segment(ty: &syn::Type) -> Option<&PathSegment> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+
+    path.path.segments.last()
+}
+
+/// Get the first generic argument of a path segment,
+/// e.g. `T` for `Option<T>`.
+fn generic_arg(segment: &PathSegment) -> Option<&syn::Type> {
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Split a comma separated list of columns, e.g.
+/// `"author_id, title"`, into its columns.
+fn split_column_list(list: &str) -> Vec<&str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .collect()
+}
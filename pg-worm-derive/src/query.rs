@@ -0,0 +1,227 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident, LitStr, Path, Token,
+};
+
+/// The input of the `query!` macro, i.e. a model and a statement
+/// referencing local variables like `{title}`.
+pub struct QueryInput {
+    model: Path,
+    statement: LitStr,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream) -> syn::Result<QueryInput> {
+        let model = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let statement = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(QueryInput { model, statement })
+    }
+}
+
+impl QueryInput {
+    /// Generate the query, whose statement is already numbered.
+    pub fn expand(&self) -> TokenStream {
+        let (statement, names) = match interpolate(&self.statement.value()) {
+            Ok(res) => res,
+            Err(err) => return syn::Error::new(self.statement.span(), err).to_compile_error(),
+        };
+
+        // The variables are resolved where the macro is called
+        let params = names.iter().map(|name| {
+            // Raw identifiers like `r#type` can't be created using `Ident::new`
+            let mut param = syn::parse_str::<Ident>(name).expect("checked by `interpolate`");
+            param.set_span(self.statement.span());
+            param
+        });
+        let model = &self.model;
+
+        quote!(
+            // `Model::query` would turn jsonb operators like `?` into placeholders
+            ::pg_worm::query::Query::<::std::vec::Vec<#model>>::numbered(
+                ::std::string::String::from(#statement),
+                ::std::vec![#(&#params as &(dyn ::pg_worm::pg::types::ToSql + ::core::marker::Sync)),*]
+            )
+        )
+    }
+}
+
+/// Replace each `{name}` in a statement with a numbered placeholder.
+///
+/// Returns the new statement and the names in the order of their
+/// placeholders. A name used more than once is only bound once.
+/// `{{` and `}}` are kept as literal braces. String literals, quoted
+/// identifiers and comments are kept as they are.
+pub(crate) fn interpolate(stmt: &str) -> Result<(String, Vec<String>), String> {
+    let chars = stmt.chars().collect::<Vec<_>>();
+    let mut buf = String::with_capacity(stmt.len());
+    let mut names: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if let Some(len) = quoted_len(&chars, i) {
+            buf.extend(&chars[i..i + len]);
+            i += len;
+            continue;
+        }
+
+        match &chars[i..] {
+            ['{', '{', ..] => {
+                buf.push('{');
+                i += 2;
+            }
+            ['}', '}', ..] => {
+                buf.push('}');
+                i += 2;
+            }
+            ['{', rest @ ..] => {
+                let Some(len) = rest.iter().position(|c| *c == '}') else {
+                    return Err("pg-worm: unclosed `{` in statement".to_string());
+                };
+
+                let name = rest[..len].iter().collect::<String>().trim().to_string();
+                if syn::parse_str::<Ident>(&name).is_err() {
+                    return Err(format!(
+                        "pg-worm: `{{{name}}}` must contain the name of a variable"
+                    ));
+                }
+
+                let index = match names.iter().position(|i| *i == name) {
+                    Some(index) => index,
+                    None => {
+                        names.push(name);
+                        names.len() - 1
+                    }
+                };
+                buf.push('$');
+                buf.push_str(&(index + 1).to_string());
+                i += len + 2;
+            }
+            ['}', ..] => return Err("pg-worm: unmatched `}` in statement, use `}}`".to_string()),
+            ['?', ..] if expects_operand(&buf) => {
+                return Err("pg-worm: use `{name}` instead of `?` placeholders".to_string());
+            }
+            [c, ..] => {
+                buf.push(*c);
+                i += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+
+    Ok((buf, names))
+}
+
+/// Get the length of the string literal, quoted identifier, dollar
+/// quoted string or comment starting at `start`, if there is one.
+///
+/// Unterminated ones last until the end of the statement.
+fn quoted_len(chars: &[char], start: usize) -> Option<usize> {
+    let rest = &chars[start..];
+    let after_ident = start > 0 && is_ident_char(chars[start - 1]);
+    let end = match rest {
+        ['\'', ..] => {
+            // `E'..'` strings escape quotes using backslashes
+            let escapes = after_ident
+                && matches!(chars[start - 1], 'E' | 'e')
+                && !(start > 1 && is_ident_char(chars[start - 2]));
+            let mut i = 1;
+            loop {
+                match rest.get(i..) {
+                    Some(['\\', _, ..]) if escapes => i += 2,
+                    Some(['\'', '\'', ..]) => i += 2,
+                    Some(['\'', ..]) => break Some(i + 1),
+                    Some([_, ..]) => i += 1,
+                    _ => break None,
+                }
+            }
+        }
+        ['"', tail @ ..] => tail.iter().position(|c| *c == '"').map(|i| i + 2),
+        ['$', tail @ ..] if !after_ident => {
+            // A tag like `$body$`, but not a parameter like `$1`
+            let tag_len = tail
+                .iter()
+                .position(|c| !c.is_alphanumeric() && *c != '_')?;
+            if tail.first().is_some_and(char::is_ascii_digit) || tail[tag_len] != '$' {
+                return None;
+            }
+
+            let delimiter = &rest[..tag_len + 2];
+            rest[delimiter.len()..]
+                .windows(delimiter.len())
+                .position(|i| i == delimiter)
+                .map(|i| i + 2 * delimiter.len())
+        }
+        ['-', '-', tail @ ..] => tail.iter().position(|c| *c == '\n').map(|i| i + 3),
+        ['/', '*', ..] => {
+            // Block comments can be nested
+            let (mut depth, mut i) = (0, 0);
+            loop {
+                match rest.get(i..) {
+                    Some(['/', '*', ..]) => (depth, i) = (depth + 1, i + 2),
+                    Some(['*', '/', ..]) if depth == 1 => break Some(i + 2),
+                    Some(['*', '/', ..]) => (depth, i) = (depth - 1, i + 2),
+                    Some([_, ..]) => i += 1,
+                    _ => break None,
+                }
+            }
+        }
+        _ => return None,
+    };
+
+    Some(end.unwrap_or(rest.len()))
+}
+
+/// Whether a `?` following `stmt` would be an operand, i.e. a
+/// placeholder, rather than the jsonb operators `?`, `?|` and `?&`.
+fn expects_operand(stmt: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "ALL",
+        "AND",
+        "ANY",
+        "BETWEEN",
+        "BY",
+        "ELSE",
+        "FROM",
+        "HAVING",
+        "ILIKE",
+        "IN",
+        "IS",
+        "LIKE",
+        "LIMIT",
+        "NOT",
+        "OFFSET",
+        "ON",
+        "OR",
+        "RETURNING",
+        "SELECT",
+        "SET",
+        "THEN",
+        "VALUES",
+        "WHEN",
+        "WHERE",
+    ];
+
+    let stmt = stmt.trim_end();
+    match stmt.chars().last() {
+        None => true,
+        Some(')' | ']' | '\'' | '"') => false,
+        Some(c) if is_ident_char(c) => {
+            let word = stmt
+                .rsplit(|c: char| !is_ident_char(c))
+                .next()
+                .unwrap_or_default();
+            KEYWORDS.contains(&word.to_ascii_uppercase().as_str())
+        }
+        Some(_) => true,
+    }
+}
+
+/// Whether `c` can be part of an unquoted identifier or keyword.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$')
+}
@@ -0,0 +1,93 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{meta::ParseNestedMeta, spanned::Spanned, ItemFn, LitStr, Path};
+
+/// The arguments of the `#[pg_worm::test]` attribute.
+#[derive(Default)]
+pub struct TestArgs {
+    models: Vec<Path>,
+    url: Option<LitStr>,
+}
+
+impl TestArgs {
+    /// Parse a single argument, i.e. `models(..)` or `url = ".."`.
+    pub fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("models") {
+            return meta.parse_nested_meta(|model| {
+                self.models.push(model.path);
+                Ok(())
+            });
+        }
+
+        if meta.path.is_ident("url") {
+            self.url = Some(meta.value()?.parse()?);
+            return Ok(());
+        }
+
+        Err(meta.error("pg-worm: unknown argument, expected `models` or `url`"))
+    }
+
+    /// Wrap the test function so its body runs in a schema of its own.
+    pub fn expand(&self, test: ItemFn) -> TokenStream {
+        if test.sig.asyncness.is_none() {
+            return syn::Error::new(
+                test.sig.fn_token.span(),
+                "pg-worm: the test function has to be `async`",
+            )
+            .to_compile_error();
+        }
+
+        if !test.sig.inputs.is_empty() {
+            return syn::Error::new(
+                test.sig.inputs.span(),
+                "pg-worm: the test function can't take any arguments",
+            )
+            .to_compile_error();
+        }
+
+        let url = match &self.url {
+            Some(url) => quote!(::std::string::ToString::to_string(#url)),
+            None => quote!(::std::env::var("DATABASE_URL")
+                .expect("pg-worm: set `DATABASE_URL` or pass `url = \"..\"`")),
+        };
+
+        // One after another, so foreign keys can reference earlier models
+        let models = &self.models;
+        let create_tables = quote!(
+            #(
+                sandbox
+                    .run(::pg_worm::force_create_table::<#models>())
+                    .await
+                    .expect("pg-worm: couldn't create the tables");
+            )*
+        );
+
+        let ItemFn {
+            attrs,
+            vis,
+            sig,
+            block,
+        } = test;
+        let name = &sig.ident;
+        let output = &sig.output;
+
+        quote_spanned!(sig.span() =>
+            #(#attrs)*
+            #[::pg_worm::tokio::test(crate = "::pg_worm::tokio")]
+            #vis async fn #name() #output {
+                async fn body() #output #block
+
+                ::pg_worm::test_util::connect_once(#url)
+                    .await
+                    .expect("pg-worm: couldn't connect to the database");
+                let sandbox = ::pg_worm::test_util::SchemaSandbox::new()
+                    .await
+                    .expect("pg-worm: couldn't create the sandbox schema");
+                #create_tables
+
+                // The schema is dropped even if the body panics
+                sandbox.run_and_tear_down(body()).await
+            }
+        )
+    }
+}
@@ -0,0 +1,148 @@
+//! This crate contains the quoting of identifiers shared by `pg-worm`
+//! and `pg-worm-derive`, so names generated by the derive and names
+//! read back from the database are spelled the same way.
+
+/// The keywords Postgres doesn't accept as column or table names
+/// unless they are quoted, sorted for binary search.
+///
+/// See <https://www.postgresql.org/docs/current/sql-keywords-appendix.html>.
+pub const RESERVED_KEYWORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "binary",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "false",
+    "fetch",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "intersect",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "natural",
+    "not",
+    "notnull",
+    "null",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "outer",
+    "overlaps",
+    "placing",
+    "primary",
+    "references",
+    "returning",
+    "right",
+    "select",
+    "session_user",
+    "similar",
+    "some",
+    "symmetric",
+    "system_user",
+    "table",
+    "tablesample",
+    "then",
+    "to",
+    "trailing",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+/// Quote an identifier unless Postgres keeps it as is anyway,
+/// i.e. unless it is all lowercase and not a reserved keyword.
+pub fn quote_if_needed(ident: &str) -> String {
+    let plain = ident.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && ident
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && RESERVED_KEYWORDS.binary_search(&ident).is_err();
+
+    if plain {
+        ident.to_string()
+    } else {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_are_sorted() {
+        assert!(RESERVED_KEYWORDS.windows(2).all(|i| i[0] < i[1]));
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote_if_needed("book_id"), "book_id");
+        assert_eq!(quote_if_needed("_tmp2"), "_tmp2");
+        assert_eq!(quote_if_needed("AuthorID"), "\"AuthorID\"");
+        assert_eq!(quote_if_needed("2fa"), "\"2fa\"");
+        assert_eq!(quote_if_needed("user"), "\"user\"");
+        assert_eq!(quote_if_needed("users"), "users");
+        assert_eq!(quote_if_needed("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,72 @@
+//! Measures how long building statements takes, without any IO.
+
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pg_worm::prelude::*;
+use pg_worm::query::{Query, Select};
+
+#[derive(Model)]
+struct Book {
+    #[column(primary_key, auto)]
+    id: i64,
+    title: String,
+    subtitle: Option<String>,
+    author_id: i64,
+    pages: i32,
+    isbn: String,
+    published: bool,
+    tags: Vec<String>,
+}
+
+fn select(c: &mut Criterion) {
+    c.bench_function("select all", |b| {
+        b.iter(|| {
+            let query: Query<'_, Vec<Book>> = Book::select().into();
+            black_box(query);
+        })
+    });
+
+    c.bench_function("select all (uncached)", |b| {
+        b.iter(|| {
+            let query: Query<'_, Vec<Book>> =
+                Select::new(Book::columns(), Book::table_name()).into();
+            black_box(query);
+        })
+    });
+
+    let (author, min_pages, max_pages) = (3_i64, 100_i32, 500_i32);
+    let title = "Dune".to_string();
+    c.bench_function("select filtered", |b| {
+        b.iter(|| {
+            let query: Query<'_, Vec<Book>> = Book::select()
+                .where_(Book::author_id.eq(&author))
+                .where_(
+                    (Book::pages.gte(&min_pages) & Book::pages.lte(&max_pages))
+                        | Book::title.eq(&title),
+                )
+                .order_by(Book::id, Order::Desc)
+                .limit(10)
+                .into();
+            black_box(query);
+        })
+    });
+}
+
+fn update(c: &mut Criterion) {
+    let (id, pages) = (1_i64, 320_i32);
+    let title = "Dune Messiah".to_string();
+    c.bench_function("update", |b| {
+        b.iter(|| {
+            let query: Query<'_, u64> = Book::update()
+                .set(Book::title, &title)
+                .set(Book::pages, &pages)
+                .where_(Book::id.eq(&id))
+                .into();
+            black_box(query);
+        })
+    });
+}
+
+criterion_group!(benches, select, update);
+criterion_main!(benches);
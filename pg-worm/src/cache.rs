@@ -0,0 +1,173 @@
+//! This module contains an opt-in cache for looking up
+//! entities by their primary key.
+//!
+//! Register an implementation of [`ModelCache`] for a model with exactly
+//! one primary key using `Book::set_cache`. Afterwards `Book::get` consults
+//! it before querying the database, and the model's `update()` and `delete()`
+//! queries invalidate the rows they changed. Without a cache,
+//! queries are built and executed exactly as before.
+
+use std::sync::{Arc, RwLock};
+
+use tokio_postgres::types::{FromSql, ToSql};
+
+use crate::{
+    query::{after_commit, Column, RowHook, Where},
+    Error, Model, Row,
+};
+
+/// A cache for the entities of a model, keyed by their primary key.
+///
+/// # Example
+///
+/// ```ignore
+/// struct BookCache(Mutex<HashMap<i64, Book>>);
+///
+/// impl ModelCache<Book> for BookCache {
+///     fn get(&self, key: &i64) -> Option<Book> {
+///         self.0.lock().unwrap().get(key).cloned()
+///     }
+///
+///     fn put(&self, key: &i64, entity: &Book) {
+///         self.0.lock().unwrap().insert(*key, entity.clone());
+///     }
+///
+///     fn invalidate(&self, key: &i64) {
+///         self.0.lock().unwrap().remove(key);
+///     }
+/// }
+///
+/// Book::set_cache(Arc::new(BookCache(Mutex::default())));
+/// let book = Book::get(&1).await?;
+/// ```
+pub trait ModelCache<M: Cached>: Send + Sync {
+    /// Get the cached entity with this key, if any.
+    fn get(&self, key: &M::Key) -> Option<M>;
+
+    /// Store an entity which was loaded from the database.
+    fn put(&self, key: &M::Key, entity: &M);
+
+    /// Remove the entity with this key, since its row was
+    /// updated or deleted.
+    fn invalidate(&self, key: &M::Key);
+}
+
+/// A trait for models which can be cached.
+///
+/// It is implemented by the `Model` derive for
+/// models with exactly one primary key column.
+pub trait Cached: Model<Self> + Sized + 'static {
+    /// The type of the primary key column.
+    type Key: ToSql + Sync + for<'a> FromSql<'a>;
+
+    /// Returns the primary key column.
+    fn key_column() -> Column;
+
+    /// Returns where the model's cache is registered.
+    #[doc(hidden)]
+    fn cache_slot() -> &'static CacheSlot<Self>;
+}
+
+/// Where the cache of a model is registered.
+#[doc(hidden)]
+pub struct CacheSlot<M: Cached>(RwLock<Option<Arc<dyn ModelCache<M>>>>);
+
+impl<M: Cached> CacheSlot<M> {
+    /// Create a slot without a cache.
+    pub const fn new() -> CacheSlot<M> {
+        CacheSlot(RwLock::new(None))
+    }
+
+    /// Get the registered cache, if any.
+    fn get(&self) -> Option<Arc<dyn ModelCache<M>>> {
+        self.0.read().ok()?.clone()
+    }
+
+    /// Replace the registered cache.
+    fn set(&self, cache: Option<Arc<dyn ModelCache<M>>>) {
+        if let Ok(mut slot) = self.0.write() {
+            *slot = cache;
+        }
+    }
+}
+
+impl<M: Cached> Default for CacheSlot<M> {
+    fn default() -> Self {
+        CacheSlot::new()
+    }
+}
+
+/// The column a query has to return and a function invalidating
+/// the entities of the returned rows.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub struct Invalidate {
+    pub(crate) column: Column,
+    pub(crate) hook: RowHook,
+}
+
+/// Register a cache for a model, replacing the previous one.
+pub fn set_cache<M: Cached>(cache: Arc<dyn ModelCache<M>>) {
+    M::cache_slot().set(Some(cache));
+}
+
+/// Remove the cache of a model, if it has one.
+pub fn remove_cache<M: Cached>() {
+    M::cache_slot().set(None);
+}
+
+/// Get an entity by its primary key, using the model's cache if it has one.
+///
+/// On a miss the entity is selected and put into the cache.
+pub async fn get<M>(key: &M::Key) -> Result<Option<M>, Error>
+where
+    M: Cached + Send + Sync,
+{
+    let cache = M::cache_slot().get();
+    if let Some(entity) = cache.as_ref().and_then(|cache| cache.get(key)) {
+        return Ok(Some(entity));
+    }
+
+    let column = M::key_column();
+    let entity = M::select_one()
+        .where_(Where::new(format!("{} = ?", column.full_name()), vec![key]))
+        .await?;
+
+    if let (Some(cache), Some(entity)) = (cache, &entity) {
+        cache.put(key, entity);
+    }
+
+    Ok(entity)
+}
+
+/// Get what `update()` and `delete()` need to invalidate the
+/// changed entities, if the model has a cache.
+#[doc(hidden)]
+pub fn invalidator<M: Cached>() -> Option<Invalidate> {
+    M::cache_slot().get()?;
+
+    Some(Invalidate {
+        column: M::key_column(),
+        hook: invalidate_rows::<M>,
+    })
+}
+
+/// Invalidate the entities whose keys are part of the rows.
+///
+/// Inside a transaction they are invalidated again after the commit,
+/// since a concurrent `get` may put the old rows back in the meantime.
+fn invalidate_rows<M: Cached>(rows: &[Row]) -> Result<(), Error> {
+    let Some(cache) = M::cache_slot().get() else {
+        return Ok(());
+    };
+
+    let column = M::key_column().column_name.trim_matches('"');
+    for row in rows {
+        if let Ok(key) = row.try_get::<_, M::Key>(column) {
+            cache.invalidate(&key);
+        }
+    }
+    after_commit(invalidate_rows::<M>, rows);
+
+    Ok(())
+}
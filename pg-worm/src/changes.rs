@@ -0,0 +1,192 @@
+//! This module contains the logic for polling models
+//! for changes, based on their `updated_at` column.
+//!
+//! A model gets a change feed by marking a timestamp field
+//! as `#[column(updated_at)]` and having exactly one primary key.
+
+use std::{future::Future, time::Duration};
+
+use tokio::{sync::oneshot, task::JoinHandle};
+use tokio_postgres::types::ToSql;
+
+use crate::{
+    fetch_client,
+    query::{Column, Order, Query, Select, Where},
+    Error, Model,
+};
+
+/// The maximum number of rows fetched per query while polling.
+const BATCH_SIZE: u64 = 500;
+
+/// A trait for models whose changes can be polled.
+///
+/// It is implemented by the `Model` derive for models with an
+/// `updated_at` column and exactly one primary key column.
+pub trait ChangeFeed: Model<Self> + Send + Sized + 'static {
+    /// The type of the `updated_at` column.
+    type Timestamp: ToSql + Sync + Send + 'static;
+    /// The type of the primary key column.
+    type Id: ToSql + Sync + Send + 'static;
+
+    /// Returns the `updated_at` and primary key columns.
+    fn cursor_columns() -> (Column, Column);
+
+    /// Returns the values of the `updated_at` and primary key columns.
+    fn cursor(&self) -> (Self::Timestamp, Self::Id);
+
+    /// Select all entities which have changed after the given cursor,
+    /// ordered by when they were last updated.
+    ///
+    /// Entities updated at exactly the cursor's timestamp are
+    /// included if their primary key is larger than the cursor's.
+    /// This way no change is missed if multiple rows share a timestamp.
+    /// If the cursor is `None`, all entities are selected.
+    fn changes_after(cursor: Option<&(Self::Timestamp, Self::Id)>) -> Select<'_, Vec<Self>> {
+        let (updated_at, id) = Self::cursor_columns();
+        let select = Self::select()
+            .order_by(&updated_at, Order::Asc)
+            .order_by(&id, Order::Asc);
+
+        let Some((timestamp, pk)) = cursor else {
+            return select;
+        };
+
+        select.where_(Where::new(
+            format!("({}, {}) > (?, ?)", updated_at.full_name(), id.full_name()),
+            vec![timestamp, pk],
+        ))
+    }
+}
+
+/// A handle to a task started by [`poll_changes`].
+///
+/// Dropping it stops the task, too.
+pub struct ChangePoller {
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<Result<(), Error>>,
+}
+
+impl ChangePoller {
+    /// Stop polling.
+    ///
+    /// Waits for the batch which is currently being handled, if any.
+    /// Returns the error which stopped the poller early, if one occured,
+    /// or [`Error::PollerFailed`] if the handler panicked.
+    pub async fn shutdown(self) -> Result<(), Error> {
+        // If sending fails, the task is already done.
+        let _ = self.shutdown.send(());
+
+        self.task.await.map_err(Error::PollerFailed)?
+    }
+
+    /// Check whether the poller has stopped, i.e. because of an error.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+/// Regularly poll a model's table for changed entities.
+///
+/// Every `interval`, all entities inserted or updated since the last poll
+/// are passed to `handler` in batches, ordered by their `updated_at` column.
+/// The poller remembers the largest timestamp (along with the primary key)
+/// it has seen. The first poll returns all entities.
+///
+/// Since `updated_at` is the time a transaction started, a row may be
+/// committed after rows with a later timestamp. So each poll only returns
+/// rows changed before the oldest transaction which is still running
+/// started, i.e. changes show up once all earlier ones are committed.
+/// This requires the poller's role to see the other sessions in
+/// `pg_stat_activity`, e.g. by using the same role as the writers.
+///
+/// Only client sessions other than the poller's own are taken into
+/// account, so autovacuum and other background workers don't hold it back.
+/// A long-running transaction of any client in the same database still
+/// does, even one which is idle or never touches the model's table:
+/// no changes show up until it has finished.
+///
+/// The poller runs until [`ChangePoller::shutdown`] is called or
+/// a query fails.
+///
+/// # Example
+///
+/// ```ignore
+/// let poller = pg_worm::poll_changes::<Book, _, _>(Duration::from_secs(5), |batch| async move {
+///     for book in batch {
+///         println!("{} changed", book.title);
+///     }
+/// });
+///
+/// // ---- snip ----
+///
+/// poller.shutdown().await?;
+/// ```
+pub fn poll_changes<M, F, Fut>(interval: Duration, mut handler: F) -> ChangePoller
+where
+    M: ChangeFeed,
+    F: FnMut(Vec<M>) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let (shutdown, mut shutdown_rx) = oneshot::channel();
+
+    let task = tokio::spawn(async move {
+        let mut cursor = None;
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            // A closed channel means the handle has been dropped
+            tokio::select! {
+                _ = ticker.tick() => (),
+                _ = &mut shutdown_rx => return Ok(()),
+            }
+
+            // Fetch changes until all have been handled
+            loop {
+                let settled = settled_before().await?;
+                let (updated_at, _) = M::cursor_columns();
+                let query: Query<'_, Vec<M>> = M::changes_after(cursor.as_ref())
+                    .where_(Where::new(
+                        format!("{} < ?::text::timestamptz", updated_at.full_name()),
+                        vec![&settled],
+                    ))
+                    .limit(BATCH_SIZE)
+                    .into();
+                let batch = query.exec().await?;
+
+                let Some(last) = batch.last() else {
+                    break;
+                };
+                cursor = Some(last.cursor());
+
+                let done = (batch.len() as u64) < BATCH_SIZE;
+                handler(batch).await;
+
+                if done {
+                    break;
+                }
+            }
+        }
+    });
+
+    ChangePoller { shutdown, task }
+}
+
+/// Get the time the oldest client transaction of this database which
+/// is still running started, or the current time if there is none.
+/// All rows with an older `updated_at` are committed.
+///
+/// This runs as a statement of its own before the changes are selected,
+/// so every transaction which isn't listed anymore is visible to them.
+async fn settled_before() -> Result<String, Error> {
+    let row = fetch_client()
+        .await?
+        .query_one(
+            "SELECT coalesce(min(xact_start), now())::text FROM pg_stat_activity \
+            WHERE datname = current_database() \
+            AND backend_type = 'client backend' AND pid <> pg_backend_pid()",
+            &[],
+        )
+        .await?;
+
+    Ok(row.try_get(0)?)
+}
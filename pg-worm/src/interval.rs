@@ -0,0 +1,181 @@
+//! This module contains the [`PgInterval`] type for storing
+//! durations as `INTERVAL`.
+//!
+//! It is only available with the `time` feature enabled.
+
+use std::{error::Error, fmt, time::Duration as StdDuration};
+
+use bytes::{Buf, BufMut, BytesMut};
+use time::Duration;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+/// The number of microseconds Postgres counts per day of an interval.
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// The number of days Postgres counts per month of an interval.
+const DAYS_PER_MONTH: i64 = 30;
+
+/// A duration stored as `INTERVAL`.
+///
+/// Neither `time::Duration` nor `std::time::Duration` can be sent to
+/// Postgres as is, so fields of this type are used instead. It converts
+/// from and into both.
+///
+/// Postgres stores intervals with microsecond precision, so nanoseconds
+/// are cut off. Intervals written by others may contain months, which
+/// are read as 30 days each, like Postgres itself does when comparing them.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Model)]
+/// struct Book {
+///     id: i64,
+///     reading_time: PgInterval,
+/// }
+///
+/// let long = PgInterval::from(Duration::hours(10));
+/// let long_reads = Book::select()
+///     .where_(Book::reading_time.gt(&long))
+///     .await?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PgInterval(pub Duration);
+
+impl PgInterval {
+    /// An interval of no time at all.
+    pub const ZERO: PgInterval = PgInterval(Duration::ZERO);
+
+    /// Get the duration of this interval.
+    pub const fn duration(self) -> Duration {
+        self.0
+    }
+
+    /// Convert this interval into a `std::time::Duration`,
+    /// returning `None` if it is negative.
+    pub fn to_std(self) -> Option<StdDuration> {
+        StdDuration::try_from(self.0).ok()
+    }
+}
+
+impl From<Duration> for PgInterval {
+    fn from(value: Duration) -> PgInterval {
+        PgInterval(value)
+    }
+}
+
+impl From<PgInterval> for Duration {
+    fn from(value: PgInterval) -> Duration {
+        value.0
+    }
+}
+
+impl From<StdDuration> for PgInterval {
+    /// Convert a `std::time::Duration`, saturating at `time::Duration::MAX`.
+    fn from(value: StdDuration) -> PgInterval {
+        PgInterval(Duration::try_from(value).unwrap_or(Duration::MAX))
+    }
+}
+
+impl fmt::Display for PgInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<'a> FromSql<'a> for PgInterval {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<PgInterval, Box<dyn Error + Sync + Send>> {
+        if raw.len() != 16 {
+            return Err("invalid message length: interval size mismatch".into());
+        }
+
+        // Microseconds, days and months, each counted separately
+        let micros = raw.get_i64();
+        let days = i64::from(raw.get_i32()) + i64::from(raw.get_i32()) * DAYS_PER_MONTH;
+
+        Ok(PgInterval(
+            Duration::microseconds(micros) + Duration::days(days),
+        ))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+}
+
+impl ToSql for PgInterval {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let micros = i64::try_from(self.0.whole_microseconds())
+            .map_err(|_| "interval out of range for postgres")?;
+
+        // Keep whole days separate, like Postgres does
+        out.put_i64(micros % MICROS_PER_DAY);
+        out.put_i32(i32::try_from(micros / MICROS_PER_DAY)?);
+        out.put_i32(0);
+
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(interval: PgInterval) -> PgInterval {
+        let mut buf = BytesMut::new();
+        interval.to_sql(&Type::INTERVAL, &mut buf).unwrap();
+
+        PgInterval::from_sql(&Type::INTERVAL, &buf).unwrap()
+    }
+
+    #[test]
+    fn encoding() {
+        for interval in [
+            PgInterval::ZERO,
+            PgInterval(Duration::microseconds(1_500_250)),
+            PgInterval(Duration::days(3) + Duration::minutes(-5)),
+            PgInterval(-Duration::days(400) - Duration::microseconds(1)),
+        ] {
+            assert_eq!(round_trip(interval), interval);
+        }
+
+        // Nanoseconds are cut off
+        let interval = PgInterval(Duration::nanoseconds(1_999));
+        assert_eq!(round_trip(interval), PgInterval(Duration::microseconds(1)));
+
+        // Too long for postgres
+        let mut buf = BytesMut::new();
+        assert!(PgInterval(Duration::MAX)
+            .to_sql(&Type::INTERVAL, &mut buf)
+            .is_err());
+    }
+
+    #[test]
+    fn months() {
+        // 1 month, 2 days and 3 microseconds
+        let raw = [0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1];
+        let interval = PgInterval::from_sql(&Type::INTERVAL, &raw).unwrap();
+
+        assert_eq!(
+            interval.duration(),
+            Duration::days(32) + Duration::microseconds(3)
+        );
+    }
+
+    #[test]
+    fn std_durations() {
+        let std = StdDuration::from_millis(2_500);
+        let interval = PgInterval::from(std);
+
+        assert_eq!(interval.duration(), Duration::milliseconds(2_500));
+        assert_eq!(interval.to_std(), Some(std));
+        assert_eq!(PgInterval(Duration::seconds(-1)).to_std(), None);
+        assert_eq!(PgInterval::from(StdDuration::MAX).duration(), Duration::MAX);
+    }
+}
@@ -0,0 +1,361 @@
+//! This module contains counters for monitoring the pool
+//! and the queries executed, e.g. to export them to Prometheus.
+//!
+//! It is only available with the `metrics` feature enabled.
+//! The counters are global and start at zero when the program starts.
+//! Call [`metrics_snapshot`] to read them:
+//!
+//! ```ignore
+//! let metrics = pg_worm::metrics::metrics_snapshot();
+//! println!("{} selects", metrics.select.count);
+//! println!("{:?} cache hit ratio", metrics.prepared_hit_ratio());
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+/// The upper bounds of the buckets of a [`LatencyHistogram`].
+///
+/// Durations above the last bound are counted in an extra bucket.
+pub const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+/// The kind of a statement, taken from its first keyword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// A `SELECT`.
+    Select,
+    /// An `INSERT`.
+    Insert,
+    /// An `UPDATE`.
+    Update,
+    /// A `DELETE`.
+    Delete,
+    /// Any other statement.
+    Raw,
+}
+
+impl StatementKind {
+    /// Get the kind of a statement.
+    ///
+    /// The common table expressions of a `WITH` query are skipped,
+    /// so the kind is that of the main statement.
+    pub fn of(statement: &str) -> StatementKind {
+        let mut depth = 0_usize;
+        let mut quote = None;
+        let mut words = 0;
+        let mut rest = statement;
+
+        while let Some(c) = rest.chars().next() {
+            let len = match (quote, c) {
+                (Some(q), c) if c == q => {
+                    quote = None;
+                    1
+                }
+                (Some(_), c) => c.len_utf8(),
+                (None, '\'' | '"') => {
+                    quote = Some(c);
+                    1
+                }
+                (None, '(') => {
+                    depth += 1;
+                    1
+                }
+                (None, ')') => {
+                    depth = depth.saturating_sub(1);
+                    1
+                }
+                (None, c) if c.is_ascii_alphabetic() => {
+                    let len = rest
+                        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len());
+                    let word = &rest[..len];
+                    words += 1;
+
+                    // Only the main statement is outside of any parentheses
+                    if depth == 0 {
+                        match word.to_ascii_uppercase().as_str() {
+                            "SELECT" => return StatementKind::Select,
+                            "INSERT" => return StatementKind::Insert,
+                            "UPDATE" => return StatementKind::Update,
+                            "DELETE" => return StatementKind::Delete,
+                            // Skip over the names of the expressions
+                            "WITH" if words == 1 => (),
+                            _ if words == 1 => return StatementKind::Raw,
+                            _ => (),
+                        }
+                    }
+
+                    len
+                }
+                (None, c) => c.len_utf8(),
+            };
+
+            rest = &rest[len..];
+        }
+
+        StatementKind::Raw
+    }
+}
+
+/// The counters of one [`StatementKind`].
+#[derive(Debug, Default)]
+struct StatementCounters {
+    count: AtomicU64,
+    errors: AtomicU64,
+    latency: HistogramCounters,
+}
+
+/// The counters of a [`LatencyHistogram`].
+#[derive(Debug, Default)]
+struct HistogramCounters {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl HistogramCounters {
+    fn record(&self, duration: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| duration <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        LatencyHistogram {
+            buckets: std::array::from_fn(|i| self.buckets[i].load(Ordering::Relaxed)),
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// All counters, see [`MetricsSnapshot`] for what they mean.
+#[derive(Debug, Default)]
+struct Counters {
+    pool_checkouts: AtomicU64,
+    pool_wait: HistogramCounters,
+    pool_timeouts: AtomicU64,
+    statements: [StatementCounters; 5],
+    prepared_hits: AtomicU64,
+    prepared_misses: AtomicU64,
+    commits: AtomicU64,
+    rollbacks: AtomicU64,
+}
+
+static COUNTERS: Lazy<Counters> = Lazy::new(Counters::default);
+
+/// How long something took, counted in buckets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// How many durations were at most the bound
+    /// of the same index in [`LATENCY_BUCKETS`],
+    /// but above the one before.
+    ///
+    /// The last one counts all longer durations.
+    pub buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    /// The sum of all durations.
+    pub sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// Get the number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// The metrics of the statements of one [`StatementKind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementMetrics {
+    /// How many statements were executed.
+    pub count: u64,
+    /// How many of them failed.
+    pub errors: u64,
+    /// How long executing them took.
+    pub latency: LatencyHistogram,
+}
+
+/// The state of all counters at one point in time,
+/// see [`metrics_snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// How many clients were taken from the pool.
+    pub pool_checkouts: u64,
+    /// How long taking a client from the pool took,
+    /// including the time spent waiting for a free one.
+    pub pool_wait: LatencyHistogram,
+    /// How often no client was free within the pool's wait timeout.
+    pub pool_timeouts: u64,
+    /// The `SELECT` statements executed.
+    pub select: StatementMetrics,
+    /// The `INSERT` statements executed.
+    pub insert: StatementMetrics,
+    /// The `UPDATE` statements executed.
+    pub update: StatementMetrics,
+    /// The `DELETE` statements executed.
+    pub delete: StatementMetrics,
+    /// All other statements executed.
+    pub raw: StatementMetrics,
+    /// How often [`ensure_prepared`](crate::pool::ensure_prepared) found
+    /// the statement prepared already.
+    pub prepared_hits: u64,
+    /// How often [`ensure_prepared`](crate::pool::ensure_prepared)
+    /// had to prepare the statement.
+    pub prepared_misses: u64,
+    /// How many transactions were committed.
+    pub commits: u64,
+    /// How many transactions were rolled back explicitly.
+    pub rollbacks: u64,
+}
+
+impl MetricsSnapshot {
+    /// Get the metrics of the statements of one kind.
+    pub fn statements(&self, kind: StatementKind) -> &StatementMetrics {
+        match kind {
+            StatementKind::Select => &self.select,
+            StatementKind::Insert => &self.insert,
+            StatementKind::Update => &self.update,
+            StatementKind::Delete => &self.delete,
+            StatementKind::Raw => &self.raw,
+        }
+    }
+
+    /// Get the share of prepared statements which were found
+    /// in the cache, or `None` if none were looked up yet.
+    pub fn prepared_hit_ratio(&self) -> Option<f64> {
+        let total = self.prepared_hits + self.prepared_misses;
+
+        (total > 0).then(|| self.prepared_hits as f64 / total as f64)
+    }
+}
+
+/// Read the current state of all counters.
+pub fn metrics_snapshot() -> MetricsSnapshot {
+    let counters = &*COUNTERS;
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    let statements = |kind: StatementKind| {
+        let counters = &counters.statements[kind as usize];
+
+        StatementMetrics {
+            count: load(&counters.count),
+            errors: load(&counters.errors),
+            latency: counters.latency.snapshot(),
+        }
+    };
+
+    MetricsSnapshot {
+        pool_checkouts: load(&counters.pool_checkouts),
+        pool_wait: counters.pool_wait.snapshot(),
+        pool_timeouts: load(&counters.pool_timeouts),
+        select: statements(StatementKind::Select),
+        insert: statements(StatementKind::Insert),
+        update: statements(StatementKind::Update),
+        delete: statements(StatementKind::Delete),
+        raw: statements(StatementKind::Raw),
+        prepared_hits: load(&counters.prepared_hits),
+        prepared_misses: load(&counters.prepared_misses),
+        commits: load(&counters.commits),
+        rollbacks: load(&counters.rollbacks),
+    }
+}
+
+/// Record taking a client from the pool.
+pub(crate) fn record_checkout(wait: Duration, timed_out: bool) {
+    COUNTERS.pool_wait.record(wait);
+
+    let counter = if timed_out {
+        &COUNTERS.pool_timeouts
+    } else {
+        &COUNTERS.pool_checkouts
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record executing a statement.
+pub(crate) fn record_statement(statement: &str, duration: Duration, failed: bool) {
+    let counters = &COUNTERS.statements[StatementKind::of(statement) as usize];
+    counters.count.fetch_add(1, Ordering::Relaxed);
+    counters.latency.record(duration);
+
+    if failed {
+        counters.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Record looking up a prepared statement.
+pub(crate) fn record_prepared(hit: bool) {
+    let counter = if hit {
+        &COUNTERS.prepared_hits
+    } else {
+        &COUNTERS.prepared_misses
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record the end of a transaction.
+pub(crate) fn record_transaction(committed: bool) {
+    let counter = if committed {
+        &COUNTERS.commits
+    } else {
+        &COUNTERS.rollbacks
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statement_kinds() {
+        use StatementKind::*;
+
+        assert_eq!(StatementKind::of("SELECT * FROM book"), Select);
+        assert_eq!(StatementKind::of("  insert INTO book VALUES ($1)"), Insert);
+        assert_eq!(
+            StatementKind::of("WITH old AS (SELECT id FROM book) DELETE FROM book"),
+            Delete
+        );
+        assert_eq!(
+            StatementKind::of("WITH a AS (SELECT 1), \"b\" AS (SELECT ')') UPDATE book SET x = 1"),
+            Update
+        );
+        assert_eq!(
+            StatementKind::of("WITH \"select\" AS (SELECT 1) SELECT * FROM \"select\""),
+            Select
+        );
+        assert_eq!(StatementKind::of("TRUNCATE TABLE book"), Raw);
+        assert_eq!(StatementKind::of("CREATE TABLE book (id INT8)"), Raw);
+    }
+
+    #[test]
+    fn histogram() {
+        let histogram = HistogramCounters::default();
+        histogram.record(Duration::from_micros(500));
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_secs(10));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.buckets[0], 1);
+        assert_eq!(snapshot.buckets[1], 1);
+        assert_eq!(snapshot.buckets[LATENCY_BUCKETS.len()], 1);
+        assert_eq!(snapshot.count(), 3);
+        assert_eq!(snapshot.sum, Duration::from_micros(10_005_500));
+    }
+}
@@ -0,0 +1,209 @@
+//! This module contains the [`MigrationPlan`], which records
+//! each statement of a migration together with the reason for it.
+
+use std::fmt::{self, Display};
+
+/// A single statement of a migration.
+///
+/// Besides the SQL it records which table, column, constraint or
+/// enum type it originates from and a human readable reason,
+/// e.g. ``column `subtitle` added to table `book` ``.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    sql: String,
+    table: String,
+    column: Option<String>,
+    constraint: Option<String>,
+    enum_type: Option<String>,
+    transactional: bool,
+    reason: String,
+}
+
+/// The statements needed to migrate a schema, in the order
+/// they are executed.
+///
+/// Its `Display` implementation renders an SQL script in which
+/// each statement is preceded by a comment stating its reason.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationPlan {
+    steps: Vec<MigrationStep>,
+}
+
+impl MigrationStep {
+    /// Create a new step originating from a table.
+    pub(super) fn new(
+        table: impl Into<String>,
+        sql: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> MigrationStep {
+        MigrationStep {
+            sql: sql.into(),
+            table: table.into(),
+            column: None,
+            constraint: None,
+            enum_type: None,
+            transactional: true,
+            reason: reason.into(),
+        }
+    }
+
+    /// Create a new step which only alters an enum type.
+    pub(super) fn for_enum_type(
+        enum_type: impl Into<String>,
+        sql: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> MigrationStep {
+        MigrationStep::new(String::new(), sql, reason).with_enum_type(enum_type)
+    }
+
+    /// Record the column this step originates from.
+    pub(super) fn with_column(mut self, column: impl Into<String>) -> MigrationStep {
+        self.column = Some(column.into());
+        self
+    }
+
+    /// Record the constraint this step originates from.
+    pub(super) fn with_constraint(mut self, constraint: impl Into<String>) -> MigrationStep {
+        self.constraint = Some(constraint.into());
+        self
+    }
+
+    /// Record the enum type this step originates from.
+    pub(super) fn with_enum_type(mut self, enum_type: impl Into<String>) -> MigrationStep {
+        self.enum_type = Some(enum_type.into());
+        self
+    }
+
+    /// Mark this step as one which has to be committed on its own,
+    /// like `ALTER TYPE .. ADD VALUE`.
+    pub(super) fn non_transactional(mut self) -> MigrationStep {
+        self.transactional = false;
+        self
+    }
+
+    /// Get the SQL statement of this step.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Get the name of the table this step originates from.
+    ///
+    /// Empty for steps which only alter an enum type,
+    /// see [`MigrationStep::enum_type`].
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Get the name of the column this step originates from, if any.
+    pub fn column(&self) -> Option<&str> {
+        self.column.as_deref()
+    }
+
+    /// Get the definition of the constraint this step originates from, if any,
+    /// e.g. `"UNIQUE"` or `"CONSTRAINT book_title_key UNIQUE (title)"`.
+    pub fn constraint(&self) -> Option<&str> {
+        self.constraint.as_deref()
+    }
+
+    /// Get the name of the enum type this step originates from, if any.
+    pub fn enum_type(&self) -> Option<&str> {
+        self.enum_type.as_deref()
+    }
+
+    /// Check whether this step can run in a transaction together
+    /// with other steps.
+    ///
+    /// Variants added to an enum type can't be used before they
+    /// are committed, so these steps are run on their own.
+    pub fn is_transactional(&self) -> bool {
+        self.transactional
+    }
+
+    /// Get the reason for this step.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Get this step as a JSON object.
+    #[cfg(feature = "serde-json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "sql": self.sql,
+            "table": self.table,
+            "column": self.column,
+            "constraint": self.constraint,
+            "enum_type": self.enum_type,
+            "transactional": self.transactional,
+            "reason": self.reason,
+        })
+    }
+}
+
+impl Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep the reason on the comment's line
+        writeln!(f, "-- {}", self.reason.replace(['\r', '\n'], " "))?;
+        if !self.transactional {
+            writeln!(f, "-- run outside of a transaction")?;
+        }
+        write!(f, "{};", self.sql)
+    }
+}
+
+impl MigrationPlan {
+    /// Get the steps of this plan.
+    pub fn steps(&self) -> &[MigrationStep] {
+        &self.steps
+    }
+
+    /// Check whether there is nothing to do.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Get this plan as a JSON array of its steps.
+    #[cfg(feature = "serde-json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        self.steps.iter().map(MigrationStep::to_json).collect()
+    }
+
+    /// Join the statements into batches which can each be sent at once.
+    ///
+    /// Consecutive transactional steps share a batch,
+    /// all others get a batch of their own.
+    pub(super) fn batches(&self) -> Vec<String> {
+        let mut batches: Vec<Vec<&str>> = Vec::new();
+        let mut previous_transactional = false;
+
+        for step in &self.steps {
+            match batches.last_mut() {
+                Some(batch) if step.transactional && previous_transactional => {
+                    batch.push(step.sql())
+                }
+                _ => batches.push(vec![step.sql()]),
+            }
+            previous_transactional = step.transactional;
+        }
+
+        batches.into_iter().map(|i| i.join("; ")).collect()
+    }
+}
+
+impl From<Vec<MigrationStep>> for MigrationPlan {
+    fn from(steps: Vec<MigrationStep>) -> MigrationPlan {
+        MigrationPlan { steps }
+    }
+}
+
+impl Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{step}")?;
+        }
+
+        Ok(())
+    }
+}
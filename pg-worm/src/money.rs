@@ -0,0 +1,403 @@
+//! This module contains the [`Cents`] type for storing
+//! amounts of money as integers.
+//!
+//! It is only available with the `money` feature enabled.
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    iter::Sum,
+    ops::{Add, Neg, Sub},
+    str::FromStr,
+};
+
+use bytes::BytesMut;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+/// An amount of money in the smallest unit of its currency, e.g. cents.
+///
+/// It is stored as `BIGINT`, so no precision is lost on the way.
+/// `MONEY` columns can be read and written, too, since Postgres
+/// sends those as an integer in the smallest unit as well. That
+/// relies on the database's `lc_monetary` using two fraction digits.
+///
+/// The operators panic on overflow, use the `checked_*` methods
+/// to handle it yourself.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Model)]
+/// struct Product {
+///     id: i64,
+///     price: Cents,
+/// }
+///
+/// let cheap = Product::select()
+///     .where_(Product::price.lt(&Cents::from_units(10, 0)))
+///     .await?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cents(pub i64);
+
+impl Cents {
+    /// No money at all.
+    pub const ZERO: Cents = Cents(0);
+
+    /// Create an amount from whole units (e.g. dollars) and cents.
+    ///
+    /// The cents take the sign of the units, so
+    /// `Cents::from_units(-1, 50)` is `-1.50`.
+    ///
+    /// # Panics
+    /// Panics if the amount overflows, use
+    /// [`Cents::checked_from_units`] to handle it yourself.
+    pub const fn from_units(units: i64, cents: i64) -> Cents {
+        match Cents::checked_from_units(units, cents) {
+            Some(res) => res,
+            None => panic!("overflow when creating cents"),
+        }
+    }
+
+    /// Like [`Cents::from_units`], but returns `None` on overflow.
+    pub const fn checked_from_units(units: i64, cents: i64) -> Option<Cents> {
+        let Some(units) = units.checked_mul(100) else {
+            return None;
+        };
+        let res = if units < 0 {
+            units.checked_sub(cents)
+        } else {
+            units.checked_add(cents)
+        };
+
+        match res {
+            Some(res) => Some(Cents(res)),
+            None => None,
+        }
+    }
+
+    /// Get the whole units (e.g. dollars) of this amount.
+    pub const fn units(self) -> i64 {
+        self.0 / 100
+    }
+
+    /// Get the cents of this amount without the whole units.
+    ///
+    /// They have the same sign as the amount.
+    pub const fn cents(self) -> i64 {
+        self.0 % 100
+    }
+
+    /// Add another amount, returning `None` on overflow.
+    pub const fn checked_add(self, other: Cents) -> Option<Cents> {
+        match self.0.checked_add(other.0) {
+            Some(res) => Some(Cents(res)),
+            None => None,
+        }
+    }
+
+    /// Subtract another amount, returning `None` on overflow.
+    pub const fn checked_sub(self, other: Cents) -> Option<Cents> {
+        match self.0.checked_sub(other.0) {
+            Some(res) => Some(Cents(res)),
+            None => None,
+        }
+    }
+
+    /// Negate this amount, returning `None` on overflow,
+    /// which only happens for `Cents(i64::MIN)`.
+    pub const fn checked_neg(self) -> Option<Cents> {
+        match self.0.checked_neg() {
+            Some(res) => Some(Cents(res)),
+            None => None,
+        }
+    }
+
+    /// Multiply by a quantity, returning `None` on overflow.
+    pub const fn checked_mul(self, quantity: i64) -> Option<Cents> {
+        match self.0.checked_mul(quantity) {
+            Some(res) => Some(Cents(res)),
+            None => None,
+        }
+    }
+
+    /// Split this amount into `parts` amounts which add up to it.
+    ///
+    /// The remainder is distributed one cent at a time,
+    /// starting with the first part. Returns an empty `Vec`
+    /// if `parts` is zero.
+    pub fn split(self, parts: usize) -> Vec<Cents> {
+        let Ok(n) = i64::try_from(parts) else {
+            return Vec::new();
+        };
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let (share, remainder) = (self.0 / n, self.0 % n);
+        (0..n)
+            .map(|i| {
+                if i < remainder.abs() {
+                    Cents(share + remainder.signum())
+                } else {
+                    Cents(share)
+                }
+            })
+            .collect()
+    }
+}
+
+impl Add for Cents {
+    type Output = Cents;
+
+    fn add(self, other: Cents) -> Cents {
+        self.checked_add(other).expect("overflow when adding cents")
+    }
+}
+
+impl Sub for Cents {
+    type Output = Cents;
+
+    fn sub(self, other: Cents) -> Cents {
+        self.checked_sub(other)
+            .expect("overflow when subtracting cents")
+    }
+}
+
+impl Neg for Cents {
+    type Output = Cents;
+
+    fn neg(self) -> Cents {
+        self.checked_neg().expect("overflow when negating cents")
+    }
+}
+
+impl Sum for Cents {
+    fn sum<I: Iterator<Item = Cents>>(iter: I) -> Cents {
+        iter.fold(Cents::ZERO, Add::add)
+    }
+}
+
+impl From<i64> for Cents {
+    fn from(value: i64) -> Cents {
+        Cents(value)
+    }
+}
+
+impl Display for Cents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{sign}{}.{:02}", self.units().abs(), self.cents().abs())
+    }
+}
+
+/// The error returned when parsing [`Cents`] from a string fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseCentsError(String);
+
+impl Display for ParseCentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid amount of money {:?}, expected e.g. `12.34`",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseCentsError {}
+
+impl FromStr for Cents {
+    type Err = ParseCentsError;
+
+    /// Parse an amount like `12.34`, `-0.5` or `12`, as
+    /// written by its `Display` implementation.
+    fn from_str(s: &str) -> Result<Cents, ParseCentsError> {
+        let err = || ParseCentsError(s.to_string());
+        let (negative, amount) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (units, cents) = amount.split_once('.').unwrap_or((amount, "00"));
+
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if !digits(units) || !digits(cents) || cents.len() > 2 {
+            return Err(err());
+        }
+
+        // `.5` means 50 cents
+        let cents = format!("{cents:0<2}").parse::<i64>().map_err(|_| err())?;
+        let units = units.parse::<i64>().map_err(|_| err())?;
+
+        // Negative amounts are built up as such to allow `i64::MIN`
+        let amount = match negative {
+            true => units
+                .checked_mul(-100)
+                .and_then(|units| units.checked_sub(cents)),
+            false => units
+                .checked_mul(100)
+                .and_then(|units| units.checked_add(cents)),
+        };
+
+        amount.map(Cents).ok_or_else(err)
+    }
+}
+
+impl<'a> FromSql<'a> for Cents {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Cents, Box<dyn Error + Sync + Send>> {
+        // Both are sent as a 64-bit integer
+        i64::from_sql(&Type::INT8, raw).map(Cents)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::INT8 | Type::MONEY)
+    }
+}
+
+impl ToSql for Cents {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.0.to_sql(&Type::INT8, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::INT8 | Type::MONEY)
+    }
+
+    to_sql_checked!();
+}
+
+/// Reading and writing `String` fields of `MONEY` columns,
+/// which the `Model` derive converts from and into [`Cents`].
+#[doc(hidden)]
+pub mod text {
+    use super::*;
+
+    /// An amount of money given as a string, parsed when it is sent.
+    #[derive(Debug)]
+    pub struct MoneyText<'a>(&'a str);
+
+    impl ToSql for MoneyText<'_> {
+        fn to_sql(
+            &self,
+            ty: &Type,
+            out: &mut BytesMut,
+        ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+            self.0.parse::<Cents>()?.to_sql(ty, out)
+        }
+
+        fn accepts(ty: &Type) -> bool {
+            <Cents as ToSql>::accepts(ty)
+        }
+
+        to_sql_checked!();
+    }
+
+    pub fn encode(value: &str) -> MoneyText<'_> {
+        MoneyText(value)
+    }
+
+    pub fn encode_nullable(value: &Option<String>) -> Option<MoneyText<'_>> {
+        value.as_deref().map(MoneyText)
+    }
+
+    pub fn decode(value: Cents) -> Result<String, crate::Error> {
+        Ok(value.to_string())
+    }
+
+    pub fn decode_nullable(value: Option<Cents>) -> Result<Option<String>, crate::Error> {
+        Ok(value.map(|i| i.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cents;
+
+    #[test]
+    fn units_and_cents() {
+        let amount = Cents::from_units(12, 34);
+        assert_eq!(amount, Cents(1234));
+        assert_eq!((amount.units(), amount.cents()), (12, 34));
+        assert_eq!(amount.to_string(), "12.34");
+
+        let negative = Cents::from_units(-1, 5);
+        assert_eq!(negative, Cents(-105));
+        assert_eq!(negative.to_string(), "-1.05");
+        assert_eq!(Cents(-5).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Cents(150) + Cents(50), Cents(200));
+        assert_eq!(Cents(150) - Cents(200), Cents(-50));
+        assert_eq!(
+            [Cents(1), Cents(2), Cents(3)].into_iter().sum::<Cents>(),
+            Cents(6)
+        );
+
+        assert_eq!(Cents(i64::MAX).checked_add(Cents(1)), None);
+        assert_eq!(Cents(i64::MIN).checked_sub(Cents(1)), None);
+        assert_eq!(Cents(i64::MAX / 2 + 1).checked_mul(2), None);
+        assert_eq!(Cents(25).checked_mul(3), Some(Cents(75)));
+        assert_eq!(-Cents(5), Cents(-5));
+        assert_eq!(Cents(i64::MIN).checked_neg(), None);
+    }
+
+    #[test]
+    fn checked_from_units() {
+        assert_eq!(Cents::checked_from_units(-1, 50), Some(Cents(-150)));
+        assert_eq!(
+            Cents::checked_from_units(i64::MAX / 100, 7),
+            Some(Cents(i64::MAX))
+        );
+        assert_eq!(Cents::checked_from_units(i64::MAX / 100, 8), None);
+        assert_eq!(Cents::checked_from_units(i64::MIN, 0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when creating cents")]
+    fn from_units_overflow() {
+        let _ = Cents::from_units(i64::MAX, 0);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("12.34".parse(), Ok(Cents(1234)));
+        assert_eq!("-0.05".parse(), Ok(Cents(-5)));
+        assert_eq!("-1.5".parse(), Ok(Cents(-150)));
+        assert_eq!("7".parse(), Ok(Cents(700)));
+        assert_eq!(Cents(-105).to_string().parse(), Ok(Cents(-105)));
+        assert_eq!(
+            Cents(i64::MIN).to_string().parse::<Cents>(),
+            Ok(Cents(i64::MIN))
+        );
+
+        for invalid in [
+            "",
+            "-",
+            "1.",
+            ".5",
+            "1.234",
+            "1,50",
+            "$1.50",
+            "+1",
+            "92233720368547758.08",
+        ] {
+            assert!(invalid.parse::<Cents>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when negating cents")]
+    fn neg_overflow() {
+        let _ = -Cents(i64::MIN);
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(Cents(100).split(3), [Cents(34), Cents(33), Cents(33)]);
+        assert_eq!(Cents(-100).split(3), [Cents(-34), Cents(-33), Cents(-33)]);
+        assert_eq!(Cents(100).split(3).into_iter().sum::<Cents>(), Cents(100));
+        assert!(Cents(100).split(0).is_empty());
+    }
+}
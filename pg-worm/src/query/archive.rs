@@ -0,0 +1,119 @@
+use std::{
+    future::{Future, IntoFuture},
+    pin::Pin,
+};
+
+use tokio_postgres::types::ToSql;
+
+use super::{PushChunk, Query, Where};
+use crate::Column;
+
+/// A struct for building queries which move rows into
+/// a model's archive table instead of only deleting them.
+///
+/// The rows are deleted and inserted into the archive table,
+/// along with the time they were archived at, in a single statement.
+/// Executing the query returns the number of archived rows.
+pub struct Archive<'a> {
+    table: &'static str,
+    archive: &'static str,
+    cols: Vec<&'static str>,
+    where_: Where<'a>,
+}
+
+impl<'a> Archive<'a> {
+    #[doc(hidden)]
+    pub fn new(table: &'static str, archive: &'static str, cols: &[Column]) -> Archive<'a> {
+        Archive {
+            table,
+            archive,
+            cols: cols.iter().map(|i| i.column_name()).collect(),
+            where_: Where::Empty,
+        }
+    }
+
+    /// Add a `WHERE` clause to your query.
+    ///
+    /// If called multiple times, the conditions are joined using `AND`.
+    pub fn where_(mut self, where_: Where<'a>) -> Archive<'a> {
+        self.where_ = self.where_.and(where_);
+
+        self
+    }
+
+    /// Add a raw `WHERE` clause to your query.
+    ///
+    /// You can reference the `params` by using the `?` placeholder in your statement.
+    ///
+    /// Otherwise this behaves exactly like `where_`.
+    pub fn where_raw(
+        self,
+        statement: impl Into<String>,
+        params: Vec<&'a (dyn ToSql + Sync)>,
+    ) -> Archive<'a> {
+        let where_ = Where::new(statement.into(), params);
+
+        self.where_(where_)
+    }
+}
+
+impl<'a> From<Archive<'a>> for Query<'a, u64> {
+    fn from(mut archive: Archive<'a>) -> Query<'a, u64> {
+        let mut buffer = Query::default();
+        buffer.statement.push_str("WITH archived AS (DELETE FROM ");
+        buffer.statement.push_str(archive.table);
+
+        if !archive.where_.is_empty() {
+            buffer.statement.push_str(" WHERE ");
+            archive.where_.push_to_buffer(&mut buffer);
+        }
+
+        // Name the columns explicitly so their
+        // order in the tables doesn't matter.
+        let cols = archive.cols.join(", ");
+        buffer.statement.push_str(&format!(
+            " RETURNING *) INSERT INTO {} ({cols}, archived_at) SELECT {cols}, now() FROM archived",
+            archive.archive
+        ));
+
+        buffer
+    }
+}
+
+impl<'a> IntoFuture for Archive<'a> {
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+    type Output = Result<u64, crate::Error>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let query = Query::from(self);
+
+        Box::pin(query.exec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use pg_worm::prelude::*;
+    use pg_worm::Query;
+
+    #[derive(Model)]
+    #[table(archive_to = "book_archive")]
+    struct Book {
+        id: i64,
+        title: String,
+    }
+
+    #[test]
+    fn archive_statement() {
+        let q: Query<'_, u64> = Book::archive().where_(Book::id.gt(&4)).into();
+
+        assert_eq!(
+            q.statement(),
+            "WITH archived AS (DELETE FROM book WHERE book.id > $1 RETURNING *) \
+            INSERT INTO book_archive (id, title, archived_at) \
+            SELECT id, title, now() FROM archived"
+        );
+    }
+}
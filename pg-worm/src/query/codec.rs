@@ -0,0 +1,169 @@
+use std::{
+    borrow::Borrow,
+    error::Error,
+    fmt::{self, Display},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+use bytes::BytesMut;
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+use super::Param;
+
+/// Encodes the values of a field using `#[column(encode_with = "..")]`.
+///
+/// It is implemented by the `Model` derive, once per such field.
+#[doc(hidden)]
+pub trait Codec<T> {
+    /// Encode the value and serialize the result.
+    fn encode(
+        value: &T,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>>;
+}
+
+/// Turns values into parameters which are encoded
+/// using `C` when they are sent to the database.
+#[doc(hidden)]
+pub struct Encoded<T, C> {
+    marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: fmt::Debug + Sync, C: Codec<T> + 'static> Encoded<T, C> {
+    /// Use a value as a parameter which is encoded when it is sent.
+    pub fn wrap(value: &T) -> Param<'_> {
+        Param::owned(EncodedValue::<_, T, C> {
+            value,
+            marker: PhantomData,
+        })
+    }
+
+    /// Like [`Encoded::wrap`], but the value is rendered as `***`,
+    /// see [`Sensitive`](super::Sensitive).
+    pub fn wrap_sensitive(value: &T) -> Param<'_> {
+        Encoded::<T, C>::wrap(value).sensitive()
+    }
+
+    /// Like [`Encoded::wrap`], but the parameter owns the value.
+    pub fn owned(value: T) -> Param<'static>
+    where
+        T: Send + 'static,
+    {
+        Param::owned(EncodedValue::<_, T, C> {
+            value,
+            marker: PhantomData,
+        })
+    }
+
+    /// Like [`Encoded::owned`], but the value is rendered as `***`.
+    pub fn owned_sensitive(value: T) -> Param<'static>
+    where
+        T: Send + 'static,
+    {
+        Encoded::<T, C>::owned(value).sensitive()
+    }
+}
+
+/// Parse a string value of a column using `encode_with` and encode it, see
+/// [`Select::filter_eq_by_name`](super::Select::filter_eq_by_name).
+///
+/// The value is parsed as `T`, e.g. the `String` of an `Option<String>`.
+#[doc(hidden)]
+pub fn parse_encoded<T, V, C>(value: &str) -> Result<Param<'static>, String>
+where
+    T: FromStr,
+    T::Err: Display,
+    V: From<T> + fmt::Debug + Send + Sync + 'static,
+    C: Codec<V> + 'static,
+{
+    let value = V::from(value.parse::<T>().map_err(|err| err.to_string())?);
+
+    Ok(Encoded::<V, C>::owned(value))
+}
+
+/// A value which is encoded using `C` when it is sent to the database.
+///
+/// It is either a `T` or a reference to one.
+struct EncodedValue<R, T, C> {
+    value: R,
+    marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<R: Borrow<T>, T: fmt::Debug, C> fmt::Debug for EncodedValue<R, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.borrow().fmt(f)
+    }
+}
+
+impl<R: Borrow<T>, T: fmt::Debug, C: Codec<T>> ToSql for EncodedValue<R, T, C> {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>>
+    where
+        Self: Sized,
+    {
+        C::encode(self.value.borrow(), ty, out)
+    }
+
+    // The encoded value checks the type itself
+    fn accepts(_: &Type) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        C::encode(self.value.borrow(), ty, out)
+    }
+}
+
+/// The value of a column as it was received, whatever its type.
+///
+/// Use it as the input of a `decode_with` function to read
+/// types `pg-worm` doesn't know, e.g. PostGIS' `geometry`:
+///
+/// ```ignore
+/// fn decode_point(raw: RawValue) -> Result<Point, pg_worm::Error> {
+///     // `raw.bytes()` is the point in Postgres' binary format
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawValue {
+    ty: Type,
+    bytes: Vec<u8>,
+}
+
+impl RawValue {
+    /// Get the Postgres type of the value.
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+
+    /// Get the value in Postgres' binary format.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Take the value in Postgres' binary format.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<'a> FromSql<'a> for RawValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<RawValue, Box<dyn Error + Sync + Send>> {
+        Ok(RawValue {
+            ty: ty.clone(),
+            bytes: raw.to_vec(),
+        })
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
@@ -1,16 +1,23 @@
 use std::{
     future::{Future, IntoFuture},
     pin::Pin,
+    sync::Arc,
 };
 
 use tokio_postgres::types::ToSql;
 
-use super::{replace_question_marks, PushChunk, Query, QueryOutcome, Where};
+use super::{OpenTransaction, PushChunk, Query, Where};
+use crate::cache::Invalidate;
 
 /// A struct for building `DELETE` queries.
 pub struct Delete<'a> {
     table: &'static str,
     where_: Where<'a>,
+    idempotent: bool,
+    invalidate: Option<Invalidate>,
+    /// The transaction the query is executed in,
+    /// see [`Transaction::delete`](super::Transaction::delete).
+    transaction: Option<Arc<OpenTransaction>>,
 }
 
 impl<'a> Delete<'a> {
@@ -19,6 +26,9 @@ impl<'a> Delete<'a> {
         Delete {
             table,
             where_: Where::Empty,
+            idempotent: false,
+            invalidate: None,
+            transaction: None,
         }
     }
 
@@ -57,33 +67,67 @@ impl<'a> Delete<'a> {
 
         self.where_(where_)
     }
+
+    /// Mark this query as safe to run more than once,
+    /// which allows retrying it after a transient error.
+    ///
+    /// See [`ConnectionBuilder::retry_policy`](crate::pool::ConnectionBuilder::retry_policy).
+    pub fn idempotent(mut self) -> Delete<'a> {
+        self.idempotent = true;
+
+        self
+    }
+
+    /// Invalidate the cached entities of the deleted rows.
+    #[doc(hidden)]
+    pub fn invalidating(mut self, invalidate: Option<Invalidate>) -> Delete<'a> {
+        self.invalidate = invalidate;
+
+        self
+    }
+
+    /// Execute this query as part of a transaction.
+    pub(super) fn bound_to(mut self, transaction: Arc<OpenTransaction>) -> Delete<'a> {
+        self.transaction = Some(transaction);
+
+        self
+    }
 }
 
 impl<'a> From<Delete<'a>> for Query<'a, u64> {
     fn from(mut delete: Delete<'a>) -> Query<'a, u64> {
         let mut buffer = Query::default();
-        buffer.0.push_str("DELETE FROM ");
-        buffer.0.push_str(delete.table);
+        buffer.statement.push_str("DELETE FROM ");
+        buffer.statement.push_str(delete.table);
 
         if !delete.where_.is_empty() {
-            buffer.0.push_str(" WHERE ");
+            buffer.statement.push_str(" WHERE ");
             delete.where_.push_to_buffer(&mut buffer);
         }
 
-        buffer.0 = replace_question_marks(buffer.0);
+        if let Some(invalidate) = delete.invalidate {
+            buffer.statement.push_str(" RETURNING ");
+            buffer.statement.push_str(&invalidate.column.full_name());
+            buffer.inspect = Some(invalidate.hook);
+        }
+
+        if delete.idempotent {
+            buffer = buffer.idempotent();
+        }
+        buffer.transaction = delete.transaction;
 
         buffer
     }
 }
 
 impl<'a> IntoFuture for Delete<'a> {
-    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'a>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
     type Output = Result<u64, crate::Error>;
 
     fn into_future(self) -> Self::IntoFuture {
         let query = Query::from(self);
 
-        Box::pin(async move { u64::exec(&query.0, query.1.as_slice()).await })
+        Box::pin(query.exec())
     }
 }
 
@@ -103,13 +147,13 @@ mod tests {
     #[test]
     fn delete_statement() {
         let q: Query<'_, u64> = Book::delete().into();
-        assert_eq!(q.0, "DELETE FROM book");
+        assert_eq!(q.statement(), "DELETE FROM book");
     }
 
     #[test]
     fn delete_statement_with_where() {
         let q: Query<'_, u64> = Book::delete().where_(Book::id.eq(&4)).into();
 
-        assert_eq!(q.0, "DELETE FROM book WHERE book.id = $1")
+        assert_eq!(q.statement(), "DELETE FROM book WHERE book.id = $1")
     }
 }
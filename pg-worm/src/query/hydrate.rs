@@ -0,0 +1,104 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    future::{Future, IntoFuture},
+    hash::Hash,
+    pin::Pin,
+};
+
+use super::Select;
+use crate::{Error, FromRow};
+
+/// A struct for updating an in-memory map of entities, keyed
+/// by their primary key, with the rows matching a filter.
+///
+/// Entries whose persisted fields didn't change are left untouched.
+/// Executing it returns how many entries were added, updated and removed.
+pub struct Hydrate<'a, K, M> {
+    map: &'a mut HashMap<K, M>,
+    select: Select<'a, Vec<M>>,
+    key: fn(&M) -> K,
+    eq: fn(&M, &M) -> bool,
+    remove_missing: bool,
+}
+
+/// How a map was changed by [`Hydrate`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hydration {
+    /// The number of entities which weren't in the map before.
+    pub added: usize,
+    /// The number of entries which were replaced since they changed.
+    pub updated: usize,
+    /// The number of entries which were removed since they
+    /// didn't match the filter, see [`Hydrate::remove_missing`].
+    pub removed: usize,
+}
+
+impl<'a, K, M> Hydrate<'a, K, M> {
+    #[doc(hidden)]
+    pub fn new(
+        map: &'a mut HashMap<K, M>,
+        select: Select<'a, Vec<M>>,
+        key: fn(&M) -> K,
+        eq: fn(&M, &M) -> bool,
+    ) -> Hydrate<'a, K, M> {
+        Hydrate {
+            map,
+            select,
+            key,
+            eq,
+            remove_missing: false,
+        }
+    }
+
+    /// Also remove the entries of the map which
+    /// aren't part of the result (anymore).
+    pub fn remove_missing(mut self) -> Hydrate<'a, K, M> {
+        self.remove_missing = true;
+
+        self
+    }
+}
+
+impl<'a, K, M> IntoFuture for Hydrate<'a, K, M>
+where
+    K: Eq + Hash + Send + Sync + 'a,
+    M: FromRow + Send + Sync + 'a,
+{
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+    type Output = Result<Hydration, Error>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            let entities = self.select.await?;
+            let mut res = Hydration::default();
+            let mut seen = HashSet::new();
+
+            for entity in entities {
+                if self.remove_missing {
+                    seen.insert((self.key)(&entity));
+                }
+
+                match self.map.entry((self.key)(&entity)) {
+                    Entry::Occupied(mut entry) => {
+                        if !(self.eq)(entry.get(), &entity) {
+                            entry.insert(entity);
+                            res.updated += 1;
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(entity);
+                        res.added += 1;
+                    }
+                }
+            }
+
+            if self.remove_missing {
+                let before = self.map.len();
+                self.map.retain(|key, _| seen.contains(key));
+                res.removed = before - self.map.len();
+            }
+
+            Ok(res)
+        })
+    }
+}
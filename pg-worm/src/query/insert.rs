@@ -1,58 +1,1052 @@
-use tokio_postgres::types::ToSql;
+use std::{
+    future::{Future, IntoFuture},
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+};
 
-use super::Query;
+use tokio_postgres::{error::SqlState, types::ToSql, GenericClient, Row as PgRow};
 
-type Entry<'a> = (&'static str, &'a (dyn ToSql + Sync));
+use super::{
+    fetch_scoped_client, Column, ColumnOf, NoneSet, OpenTransaction, Param, PushChunk, Query,
+    QueryOutcome, ScopedClient, Select, SomeSet, SqlChunk, Where,
+};
+use crate::{Error, FromRow, Row, TypedColumn};
 
+/// The name of a column, the expression its value
+/// is inserted with and the value.
+type Entry<'a> = (&'static str, &'static str, Param<'a>);
+
+/// A trait for values which may be inserted into a nullable
+/// column, i.e. one whose field is an `Option<T>`.
+///
+/// It is implemented for `T`, `Option<T>` (so a plain `None` works, too)
+/// and `&str` if `T` is `String`. Note that `Option<&str>` is not supported,
+/// as that would make the type of `None` ambiguous.
+pub trait IntoNullable<T> {
+    /// Convert the value to an `Option<T>`.
+    fn into_nullable(self) -> Option<T>;
+}
+
+impl<T> IntoNullable<T> for T {
+    fn into_nullable(self) -> Option<T> {
+        Some(self)
+    }
+}
+
+impl<T> IntoNullable<T> for Option<T> {
+    fn into_nullable(self) -> Option<T> {
+        self
+    }
+}
+
+impl IntoNullable<String> for &str {
+    fn into_nullable(self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+/// A struct for building `INSERT` queries.
 ///
-pub struct Insert<'a> {
+/// Columns without an entry get their default value. The query
+/// can only be executed once at least one entry has been added.
+///
+/// By default it returns the number of inserted rows,
+/// use [`Insert::returning`] to get the row itself.
+///
+/// # Example
+///
+/// ```ignore
+/// Book::insert_builder()
+///     .entry(Book::title, &title)
+///     .await?;
+/// ```
+///
+/// Use [`Insert::on_conflict`] to handle rows which already exist:
+///
+/// ```ignore
+/// // Only overwrite the stored row if the incoming one is newer
+/// Book::insert_builder()
+///     .entry(Book::id, &id)
+///     .entry(Book::title, &title)
+///     .entry(Book::updated_at, &updated_at)
+///     .on_conflict(Book::id)
+///     .do_update_where(Book::updated_at.excluded().gt_column(Book::updated_at))
+///     .await?;
+/// ```
+///
+/// An `INSERT` without any entries doesn't compile:
+///
+/// ```compile_fail
+/// use pg_worm::prelude::*;
+///
+/// #[derive(Model)]
+/// struct Book {
+///     title: String,
+/// }
+///
+/// async fn insert_nothing() {
+///     Book::insert_builder().await;
+/// }
+/// ```
+///
+/// The inserts into a model's table, e.g. `Book::insert_builder()`,
+/// only accept the model's own columns, see [`TypedColumn`].
+pub struct Insert<'a, State = NoneSet, T = u64, M = ()> {
     table: &'static str,
     entries: Vec<Entry<'a>>,
+    /// The columns filled by a `SELECT` and its statement,
+    /// see [`Insert::from_select`].
+    source: Option<(Vec<&'static str>, SqlChunk<'a>)>,
+    conflict_target: Vec<&'static str>,
+    /// The constraint named as conflict target,
+    /// see [`Insert::on_conflict_constraint`].
+    conflict_constraint: Option<&'static str>,
+    /// The predicate of the partial unique index used as
+    /// conflict target, see [`Insert::on_conflict_where`].
+    conflict_where: Where<'a>,
+    on_conflict: Option<ConflictAction<'a>>,
+    returning: bool,
+    idempotent: bool,
+    state: PhantomData<State>,
+    output: PhantomData<T>,
+    /// The model whose table rows are inserted into.
+    model: PhantomData<fn() -> M>,
+    error: Option<Error>,
+    /// The transaction the query is executed in,
+    /// see [`Transaction::insert_builder`](super::Transaction::insert_builder).
+    transaction: Option<Arc<OpenTransaction>>,
+}
+
+/// What to do with a row which conflicts with an existing one.
+enum ConflictAction<'a> {
+    /// Skip the row.
+    Nothing,
+    /// Update the existing row if the condition holds.
+    Update(Where<'a>),
 }
 
 impl<'a> Insert<'a> {
     /// Start building a new `INSERT` query.
-    pub fn new(table: &'static str) -> Self {
+    pub fn new(table: &'static str) -> Insert<'a, NoneSet> {
         Insert {
             table,
             entries: Vec::new(),
+            source: None,
+            conflict_target: Vec::new(),
+            conflict_constraint: None,
+            conflict_where: Where::Empty,
+            on_conflict: None,
+            returning: false,
+            idempotent: false,
+            state: PhantomData::<NoneSet>,
+            output: PhantomData::<u64>,
+            model: PhantomData,
+            error: None,
+            transaction: None,
+        }
+    }
+
+    /// Only accept the columns of model `M`.
+    #[doc(hidden)]
+    pub fn of_model<M>(self) -> Insert<'a, NoneSet, u64, M> {
+        self.cast()
+    }
+}
+
+impl<'a, S, M> Insert<'a, S, u64, M> {
+    /// Return the inserted rows instead of their number
+    /// by adding `RETURNING *`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let books: Vec<Book> = Book::insert_builder()
+    ///     .entry(Book::title, &title)
+    ///     .returning()
+    ///     .await?;
+    /// ```
+    pub fn returning<R: FromRow>(mut self) -> Insert<'a, S, Vec<R>, M> {
+        self.returning = true;
+
+        self.cast()
+    }
+}
+
+impl<'a, T, M> Insert<'a, NoneSet, T, M> {
+    /// Insert the rows returned by a `SELECT` query, e.g. to copy rows
+    /// between tables, using `INSERT INTO ... SELECT ...`.
+    ///
+    /// The selected columns are inserted into `columns`, in order.
+    /// Entries added using [`Insert::entry`] are inserted into every row
+    /// along with them. Columns of another table are rejected with
+    /// [`Error::UnknownColumn`] when the query is executed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Copy the id and title of each of the author's books
+    /// let archived = BookArchive::insert_builder()
+    ///     .from_select(
+    ///         &[BookArchive::id.column(), BookArchive::title.column()],
+    ///         Select::<Vec<Book>>::new(&[Book::id.column(), Book::title.column()], Book::table_name())
+    ///             .where_(Book::author_id.eq(&author)),
+    ///     )
+    ///     .await?;
+    /// ```
+    pub fn from_select<U>(
+        mut self,
+        columns: &[Column],
+        select: Select<'a, U>,
+    ) -> Insert<'a, SomeSet, T, M>
+    where
+        Query<'a, U>: From<Select<'a, U>>,
+    {
+        let mut select = Query::from(select);
+        if let Some(err) = select.take_error() {
+            self.error.get_or_insert(err);
+        }
+        if self.transaction.is_none() {
+            self.transaction = select.transaction;
+        }
+        self.check_columns(columns);
+
+        let columns = columns.iter().map(|col| col.column_name).collect();
+        self.source = Some((columns, SqlChunk(select.statement, select.params)));
+
+        self.cast()
+    }
+}
+
+impl<'a, S, T, M> Insert<'a, S, T, M> {
+    /// Record an error if a column isn't one of the table's. Only
+    /// the columns' names are used, so another table's column would
+    /// silently refer to the column of the same name.
+    fn check_columns(&mut self, columns: &[Column]) {
+        if let Some(col) = columns.iter().find(|col| col.table_name != self.table) {
+            self.error.get_or_insert(Error::UnknownColumn(format!(
+                "{}.{}",
+                col.table_name, col.column_name
+            )));
+        }
+    }
+
+    /// Change the state, output type or model of this query.
+    fn cast<S2, T2, M2>(self) -> Insert<'a, S2, T2, M2> {
+        Insert {
+            table: self.table,
+            entries: self.entries,
+            source: self.source,
+            conflict_target: self.conflict_target,
+            conflict_constraint: self.conflict_constraint,
+            conflict_where: self.conflict_where,
+            on_conflict: self.on_conflict,
+            returning: self.returning,
+            idempotent: self.idempotent,
+            state: PhantomData::<S2>,
+            output: PhantomData::<T2>,
+            model: PhantomData,
+            error: self.error,
+            transaction: self.transaction,
+        }
+    }
+
+    /// Execute this query as part of a transaction.
+    pub(super) fn bound_to(mut self, transaction: Arc<OpenTransaction>) -> Insert<'a, S, T, M> {
+        self.transaction = Some(transaction);
+
+        self
+    }
+
+    /// Add a value for a column to the query.
+    ///
+    /// This function has to be called at least once before
+    /// you can execute the query.
+    ///
+    /// If the value doesn't satisfy the column's constraints
+    /// (e.g. its `max_length`), executing the query returns
+    /// an [`Error::Validation`].
+    pub fn entry<U: ToSql + Sync, C: ColumnOf<M>>(
+        mut self,
+        col: TypedColumn<U, C>,
+        val: &'a U,
+    ) -> Insert<'a, SomeSet, T, M> {
+        if let Err(err) = col.validate(val) {
+            self.error.get_or_insert(err);
         }
+
+        self.entries
+            .push((col.column_name, col.insert_expr(), col.bind(val)));
+
+        self.cast()
     }
 
+    /// Add a column to the conflict target, i.e. a column
+    /// of the unique constraint which rows may violate.
     ///
-    pub fn entry(mut self, col: &'static str, val: &'a (dyn ToSql + Sync)) -> Self {
-        self.entries.push((col, val));
+    /// Call this once per column for composite constraints.
+    /// Conflicting rows are skipped unless [`Insert::do_update`]
+    /// or [`Insert::do_update_where`] is called.
+    pub fn on_conflict<U: ToSql + Sync, C: ColumnOf<M>>(
+        mut self,
+        col: TypedColumn<U, C>,
+    ) -> Insert<'a, S, T, M> {
+        self.conflict_target.push(col.column_name);
+        self.on_conflict.get_or_insert(ConflictAction::Nothing);
+
+        self
+    }
+
+    /// Use the unique index over `columns` whose predicate is implied by
+    /// the condition as conflict target, i.e. a partial unique index like
+    /// `CREATE UNIQUE INDEX ON book (title) WHERE deleted_at IS NULL`.
+    ///
+    /// Postgres has to match the condition against the index's predicate
+    /// while planning the query, so it can't take any parameters.
+    /// Conditions which do are rejected with [`Error::ParameterizedConflictTarget`]
+    /// and columns of another table with [`Error::UnknownColumn`]
+    /// when the query is executed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Titles only have to be unique among books which aren't deleted
+    /// Book::insert_builder()
+    ///     .entry(Book::title, &title)
+    ///     .entry(Book::pages, &pages)
+    ///     .on_conflict_where(&[Book::title.column()], Book::deleted_at.is_null())
+    ///     .do_update()
+    ///     .await?;
+    /// ```
+    pub fn on_conflict_where(
+        mut self,
+        columns: &[Column],
+        where_: Where<'a>,
+    ) -> Insert<'a, S, T, M> {
+        self.check_columns(columns);
+        if where_.has_params() {
+            self.error.get_or_insert(Error::ParameterizedConflictTarget);
+        }
+        self.conflict_target
+            .extend(columns.iter().map(|col| col.column_name));
+        self.conflict_where = std::mem::take(&mut self.conflict_where).and(where_);
+        self.on_conflict.get_or_insert(ConflictAction::Nothing);
+
+        self
+    }
+
+    /// Use the constraint `name` as conflict target,
+    /// rendered as `ON CONFLICT ON CONSTRAINT name`.
+    ///
+    /// This takes precedence over columns added using [`Insert::on_conflict`].
+    /// Conflicting rows are skipped unless [`Insert::do_update`]
+    /// or [`Insert::do_update_where`] is called, which overwrite every
+    /// inserted column.
+    pub fn on_conflict_constraint(mut self, name: &'static str) -> Insert<'a, S, T, M> {
+        self.conflict_constraint = Some(name);
+        self.on_conflict.get_or_insert(ConflictAction::Nothing);
+
+        self
+    }
+
+    /// Skip rows which conflict with an existing one using
+    /// `ON CONFLICT DO NOTHING`, no matter which constraint they violate.
+    pub fn do_nothing(mut self) -> Insert<'a, S, T, M> {
+        self.on_conflict = Some(ConflictAction::Nothing);
+
+        self
+    }
+
+    /// Overwrite the existing row with the entries of the
+    /// conflicting one, except for the conflict target.
+    ///
+    /// This requires a conflict target, see [`Insert::on_conflict`],
+    /// [`Insert::on_conflict_where`] and [`Insert::on_conflict_constraint`].
+    pub fn do_update(mut self) -> Insert<'a, S, T, M> {
+        if !matches!(self.on_conflict, Some(ConflictAction::Update(_))) {
+            self.on_conflict = Some(ConflictAction::Update(Where::Empty));
+        }
+
+        self
+    }
+
+    /// Like [`Insert::do_update`], but only overwrite the existing
+    /// row if the condition holds. Otherwise the row is skipped.
+    ///
+    /// The condition may reference the existing row using the
+    /// model's columns and the conflicting one using
+    /// [`TypedColumn::excluded`].
+    ///
+    /// If called multiple times, the conditions are
+    /// joined using `AND`.
+    pub fn do_update_where(mut self, where_: Where<'a>) -> Insert<'a, S, T, M> {
+        let current = match self.on_conflict.take() {
+            Some(ConflictAction::Update(current)) => current,
+            _ => Where::Empty,
+        };
+        self.on_conflict = Some(ConflictAction::Update(current.and(where_)));
+
+        self
+    }
+
+    /// Mark this query as safe to run more than once, which allows
+    /// retrying it after a transient error. Only do this if inserting
+    /// the row twice is harmless, e.g. because of a unique constraint.
+    ///
+    /// See [`ConnectionBuilder::retry_policy`](crate::pool::ConnectionBuilder::retry_policy).
+    pub fn idempotent(mut self) -> Insert<'a, S, T, M> {
+        self.idempotent = true;
 
         self
     }
 }
 
-impl<'a> From<Insert<'a>> for Query<'a, u64> {
-    fn from(value: Insert<'a>) -> Self {
-        let mut buffer = Query::default();
+impl<'a, T, M> From<Insert<'a, SomeSet, T, M>> for Query<'a, T> {
+    fn from(value: Insert<'a, SomeSet, T, M>) -> Self {
+        let (mut columns, SqlChunk(select, mut params)) = value
+            .source
+            .unwrap_or_else(|| (Vec::new(), SqlChunk(String::new(), Vec::new())));
+        let offset = params.len();
 
-        buffer.0.push_str("INSERT INTO ");
-        buffer.0.push_str(value.table);
-        buffer.0.push_str(" (");
+        columns.extend(value.entries.iter().map(|(col, _, _)| *col));
+        let placeholders = value
+            .entries
+            .iter()
+            .zip(offset + 1..)
+            .map(|((_, expr, _), i)| expr.replace('?', &format!("${i}")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        params.extend(value.entries.into_iter().map(|(_, _, val)| val));
 
-        value.entries.iter().for_each(|i| {
-            buffer.0.push_str(i.0);
-            buffer.0.push_str(", ");
-        });
+        let statement = if select.is_empty() {
+            format!("VALUES ({placeholders})")
+        } else if placeholders.is_empty() {
+            select
+        } else {
+            // The entries are added to each selected row
+            format!("SELECT source.*, {placeholders} FROM ({select}) AS source")
+        };
 
-        buffer.0.pop();
-        buffer.0.pop();
-        buffer.0.push_str(" ) VALUES (");
+        let mut buffer = Query {
+            statement: format!(
+                "INSERT INTO {} ({}) {statement}",
+                value.table,
+                columns.join(", ")
+            ),
+            params,
+            transaction: value.transaction,
+            ..Query::default()
+        };
 
-        value.entries.iter().enumerate().for_each(|(i, (_, val))| {
-            buffer.0.push_str(&format!("${}, ", i + 1));
-            buffer.1.push(*val);
-        });
+        if let Some(action) = value.on_conflict {
+            buffer.statement.push_str(" ON CONFLICT ");
+            if let Some(name) = value.conflict_constraint {
+                buffer.statement.push_str("ON CONSTRAINT ");
+                buffer.statement.push_str(name);
+                buffer.statement.push(' ');
+            } else if !value.conflict_target.is_empty() {
+                buffer.statement.push('(');
+                buffer.statement.push_str(&value.conflict_target.join(", "));
+                buffer.statement.push_str(") ");
 
-        buffer.0.pop();
-        buffer.0.pop();
-        buffer.0.push(')');
+                let mut index_where = value.conflict_where;
+                if !index_where.is_empty() {
+                    buffer.statement.push_str("WHERE ");
+                    index_where.push_to_buffer(&mut buffer);
+                    buffer.statement.push(' ');
+                }
+            }
+
+            match action {
+                ConflictAction::Nothing => buffer.statement.push_str("DO NOTHING"),
+                ConflictAction::Update(mut where_) => {
+                    let mut updates = columns
+                        .iter()
+                        .filter(|col| {
+                            value.conflict_constraint.is_some()
+                                || !value.conflict_target.contains(col)
+                        })
+                        .map(|col| format!("{col} = EXCLUDED.{col}"))
+                        .collect::<Vec<_>>();
+                    // Updating the target to itself still lets the
+                    // condition decide and makes `RETURNING` work.
+                    if updates.is_empty() {
+                        updates = value
+                            .conflict_target
+                            .iter()
+                            .map(|col| format!("{col} = EXCLUDED.{col}"))
+                            .collect();
+                    }
+
+                    buffer.statement.push_str("DO UPDATE SET ");
+                    buffer.statement.push_str(&updates.join(", "));
+
+                    if !where_.is_empty() {
+                        buffer.statement.push_str(" WHERE ");
+                        where_.push_to_buffer(&mut buffer);
+                    }
+                }
+            }
+        }
+
+        if value.returning {
+            buffer.statement.push_str(" RETURNING *");
+        }
+
+        if value.idempotent {
+            buffer = buffer.idempotent();
+        }
+
+        if let Some(err) = value.error {
+            buffer.fail(err);
+        }
 
         buffer
     }
 }
+
+impl<'a, T, M> IntoFuture for Insert<'a, SomeSet, T, M>
+where
+    T: QueryOutcome + Send + Sync + 'a,
+{
+    type Output = Result<T, crate::Error>;
+
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let query = Query::from(self);
+
+        Box::pin(query.exec())
+    }
+}
+
+/// Insert a row unless one with the same idempotency key exists.
+///
+/// Returns the new or the existing row along with whether it was inserted.
+/// Usually this is a single `INSERT ... ON CONFLICT DO UPDATE` statement
+/// which tells new rows apart by their `xmax`. Since that statement
+/// updates existing rows, tables with update triggers (`touches_rows`)
+/// use an `ON CONFLICT DO NOTHING` followed by a `SELECT` in a transaction.
+#[doc(hidden)]
+pub async fn insert_idempotent<M: FromRow>(
+    table: &'static str,
+    columns: &[&'static str],
+    placeholders: &str,
+    key: &Column,
+    params: &[Param<'_>],
+    touches_rows: bool,
+) -> Result<(M, bool), Error> {
+    let params = Param::values(params);
+    let key_index = columns.iter().position(|i| *i == key.column_name);
+    let (Some(key_index), true, true) = (
+        key_index,
+        key.table_name == table,
+        key.unique() || key.primary_key(),
+    ) else {
+        return Err(Error::InvalidIdempotencyKey(key.column_name));
+    };
+
+    let key = key.column_name;
+    let insert = format!(
+        "INSERT INTO {table} ({}) VALUES ({placeholders}) ON CONFLICT ({key})",
+        columns.join(", ")
+    );
+
+    let client = fetch_scoped_client().await?;
+
+    if !touches_rows {
+        // Updating the key to itself makes the row available to
+        // `RETURNING`. Only freshly inserted rows have no `xmax`.
+        let stmt = format!(
+            "{insert} DO UPDATE SET {key} = EXCLUDED.{key} \
+            RETURNING *, (xmax = 0) AS pg_worm_inserted"
+        );
+        let row = client.query_one(&stmt, &params).await?;
+        let inserted = row.try_get("pg_worm_inserted")?;
+
+        return Ok((M::try_from(Row::from(row))?, inserted));
+    }
+
+    let insert = format!("{insert} DO NOTHING RETURNING *");
+    let select = format!("SELECT * FROM {table} WHERE {key} = $1");
+    let (row, inserted) = match client {
+        ScopedClient::Pooled(mut client) => {
+            let transaction = client.transaction().await?;
+            let res = insert_or_select(&transaction, &insert, &select, &params, key_index).await?;
+            transaction.commit().await?;
+
+            res
+        }
+        ScopedClient::Scoped(inner) => {
+            insert_or_select(inner.transaction(), &insert, &select, &params, key_index).await?
+        }
+    };
+
+    Ok((M::try_from(Row::from(row))?, inserted))
+}
+
+/// Try to insert a row, selecting the existing one by its key
+/// (the parameter at `key_index`) if nothing was inserted.
+async fn insert_or_select(
+    client: &impl GenericClient,
+    insert: &str,
+    select: &str,
+    params: &[&(dyn ToSql + Sync)],
+    key_index: usize,
+) -> Result<(PgRow, bool), Error> {
+    match client.query_opt(insert, params).await? {
+        Some(row) => Ok((row, true)),
+        None => Ok((client.query_one(select, &[params[key_index]]).await?, false)),
+    }
+}
+
+/// The outcome of a lenient bulk insert, see `insert_many_lenient`.
+#[derive(Debug)]
+pub struct InsertReport {
+    /// The number of rows which were inserted.
+    pub inserted: u64,
+    /// The rows which couldn't be inserted, by their
+    /// index in the input, along with the reason.
+    pub failed: Vec<(usize, RowError)>,
+}
+
+/// Why a single row couldn't be inserted.
+#[derive(Debug)]
+pub struct RowError {
+    /// What kind of problem this is.
+    pub kind: RowErrorKind,
+    /// The underlying error.
+    pub error: Error,
+}
+
+/// The kinds of problems which may prevent a row from being inserted.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowErrorKind {
+    /// A value failed validation before it was sent.
+    Validation,
+    /// The row violates a `UNIQUE` constraint or primary key.
+    UniqueViolation,
+    /// The row references a row which doesn't exist.
+    ForeignKeyViolation,
+    /// A required value is `NULL`.
+    NotNullViolation,
+    /// The row violates a `CHECK` constraint.
+    CheckViolation,
+    /// Any other error reported by the database.
+    Other,
+}
+
+impl RowError {
+    /// Classify an error which occurred while inserting a row.
+    fn new(error: Error) -> RowError {
+        let code = match &error {
+            Error::Validation { .. } => {
+                return RowError {
+                    kind: RowErrorKind::Validation,
+                    error,
+                }
+            }
+            Error::PostgresError(err) => err.code(),
+            _ => None,
+        };
+
+        let kind = match code {
+            Some(c) if *c == SqlState::UNIQUE_VIOLATION => RowErrorKind::UniqueViolation,
+            Some(c) if *c == SqlState::FOREIGN_KEY_VIOLATION => RowErrorKind::ForeignKeyViolation,
+            Some(c) if *c == SqlState::NOT_NULL_VIOLATION => RowErrorKind::NotNullViolation,
+            Some(c) if *c == SqlState::CHECK_VIOLATION => RowErrorKind::CheckViolation,
+            _ => RowErrorKind::Other,
+        };
+
+        RowError { kind, error }
+    }
+}
+
+/// Insert rows one by one, each in a savepoint of a single transaction
+/// (or the one of the enclosing [`Transaction::scope`](super::Transaction::scope)),
+/// so that bad rows are rolled back and reported while the others are
+/// committed.
+///
+/// Rows which already failed (e.g. validation) are passed as `Err`.
+/// Errors which aren't caused by a row, like a lost connection,
+/// abort the whole insert.
+///
+/// Concurrent calls in the same scope take turns. Other statements
+/// the scope runs concurrently are rolled back along with a failing row.
+#[doc(hidden)]
+pub async fn insert_lenient(
+    statement: &str,
+    rows: Vec<Result<Vec<Param<'_>>, Error>>,
+) -> Result<InsertReport, Error> {
+    match fetch_scoped_client().await? {
+        ScopedClient::Pooled(mut client) => {
+            let transaction = client.transaction().await?;
+            let report = insert_rows(&transaction, statement, rows).await?;
+            transaction.commit().await?;
+
+            Ok(report)
+        }
+        // The savepoints are nested in the scope's transaction,
+        // which is committed or rolled back by its owner.
+        ScopedClient::Scoped(inner) => {
+            let _savepoints = inner.lock_savepoints().await;
+            insert_rows(inner.transaction(), statement, rows).await
+        }
+    }
+}
+
+/// Insert rows one by one, each in its own savepoint.
+async fn insert_rows(
+    client: &impl GenericClient,
+    statement: &str,
+    rows: Vec<Result<Vec<Param<'_>>, Error>>,
+) -> Result<InsertReport, Error> {
+    // Calls sharing a scope's transaction take turns,
+    // see `OpenTransaction::lock_savepoints`.
+    let savepoint = "pg_worm_insert_row";
+    let statement = client.prepare(statement).await?;
+
+    let mut report = InsertReport {
+        inserted: 0,
+        failed: Vec::new(),
+    };
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let params = match row {
+            Ok(params) => params,
+            Err(err) => {
+                report.failed.push((index, RowError::new(err)));
+                continue;
+            }
+        };
+
+        client
+            .batch_execute(&format!("SAVEPOINT {savepoint}"))
+            .await?;
+        match client.execute(&statement, &Param::values(&params)).await {
+            Ok(n) => {
+                client
+                    .batch_execute(&format!("RELEASE SAVEPOINT {savepoint}"))
+                    .await?;
+                report.inserted += n;
+            }
+            Err(err) if err.as_db_error().is_some() => {
+                client
+                    .batch_execute(&format!(
+                        "ROLLBACK TO SAVEPOINT {savepoint}; RELEASE SAVEPOINT {savepoint}"
+                    ))
+                    .await?;
+                report.failed.push((index, RowError::new(err.into())));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Insert rows only if the table is empty, in a single transaction
+/// (or the one of the enclosing [`Transaction::scope`](super::Transaction::scope)).
+///
+/// The table is locked against writes first, so that two processes
+/// seeding it at once don't both find it empty. Returns the number
+/// of inserted rows, which is zero if the table had rows already.
+#[doc(hidden)]
+pub async fn insert_seed(
+    table: &'static str,
+    statement: &str,
+    rows: Vec<Vec<Param<'_>>>,
+) -> Result<u64, Error> {
+    match fetch_scoped_client().await? {
+        ScopedClient::Pooled(mut client) => {
+            let transaction = client.transaction().await?;
+            let inserted = seed_rows(&transaction, table, statement, rows).await?;
+            transaction.commit().await?;
+
+            Ok(inserted)
+        }
+        ScopedClient::Scoped(inner) => seed_rows(inner.transaction(), table, statement, rows).await,
+    }
+}
+
+/// Insert rows unless the table has any.
+async fn seed_rows(
+    client: &impl GenericClient,
+    table: &'static str,
+    statement: &str,
+    rows: Vec<Vec<Param<'_>>>,
+) -> Result<u64, Error> {
+    // Conflicts with itself, but not with reading the table
+    client
+        .batch_execute(&format!("LOCK TABLE {table} IN SHARE ROW EXCLUSIVE MODE"))
+        .await?;
+
+    let exists = format!("SELECT EXISTS (SELECT 1 FROM {table})");
+    let has_rows: bool = client.query_one(&exists, &[]).await?.try_get(0)?;
+    if has_rows {
+        return Ok(0);
+    }
+
+    let statement = client.prepare(statement).await?;
+    let mut inserted = 0;
+    for params in rows {
+        inserted += client.execute(&statement, &Param::values(&params)).await?;
+    }
+
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use crate::prelude::*;
+    use crate::query::{Query, Select};
+    use crate::Error;
+
+    #[derive(Model)]
+    struct Book {
+        #[column(primary_key, auto)]
+        id: i64,
+        #[column(max_length = 5)]
+        title: String,
+        pages: Option<i32>,
+    }
+
+    #[test]
+    fn insert_statement() {
+        let title = String::from("Foo");
+        let pages = Some(3);
+
+        let q: Query<'_, u64> = Book::insert_builder().entry(Book::title, &title).into();
+        assert_eq!(q.statement(), "INSERT INTO book (title) VALUES ($1)");
+
+        let q: Query<'_, Vec<Book>> = Book::insert_builder()
+            .entry(Book::title, &title)
+            .entry(Book::pages, &pages)
+            .returning()
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (title, pages) VALUES ($1, $2) RETURNING *"
+        );
+        assert_eq!(q.params().len(), 2);
+    }
+
+    #[test]
+    fn on_conflict() {
+        let (id, title, pages) = (1, String::from("Foo"), Some(3));
+        let insert = || {
+            Book::insert_builder()
+                .entry(Book::id, &id)
+                .entry(Book::title, &title)
+                .entry(Book::pages, &pages)
+        };
+
+        let q: Query<'_, u64> = insert().on_conflict(Book::id).into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title, pages) VALUES ($1, $2, $3) \
+            ON CONFLICT (id) DO NOTHING"
+        );
+
+        let q: Query<'_, u64> = insert().do_nothing().into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title, pages) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+        );
+
+        let q: Query<'_, Vec<Book>> = insert()
+            .on_conflict(Book::id)
+            .do_update()
+            .returning()
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title, pages) VALUES ($1, $2, $3) \
+            ON CONFLICT (id) DO UPDATE SET title = EXCLUDED.title, pages = EXCLUDED.pages \
+            RETURNING *"
+        );
+    }
+
+    #[test]
+    fn on_conflict_constraint() {
+        let (id, title) = (1, String::from("Foo"));
+
+        let q: Query<'_, u64> = Book::insert_builder()
+            .entry(Book::id, &id)
+            .entry(Book::title, &title)
+            .on_conflict_constraint("book_title_key")
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title) VALUES ($1, $2) \
+            ON CONFLICT ON CONSTRAINT book_title_key DO NOTHING"
+        );
+
+        let q: Query<'_, u64> = Book::insert_builder()
+            .entry(Book::id, &id)
+            .entry(Book::title, &title)
+            .on_conflict_constraint("book_title_key")
+            .do_update_where(Book::id.excluded().gt_column(Book::id))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title) VALUES ($1, $2) \
+            ON CONFLICT ON CONSTRAINT book_title_key \
+            DO UPDATE SET id = EXCLUDED.id, title = EXCLUDED.title \
+            WHERE excluded.id > book.id"
+        );
+    }
+
+    #[test]
+    fn on_conflict_where() {
+        let (id, title, pages) = (1, String::from("Foo"), Some(3));
+        let insert = || {
+            Book::insert_builder()
+                .entry(Book::id, &id)
+                .entry(Book::title, &title)
+                .entry(Book::pages, &pages)
+                .on_conflict_where(&[Book::title.column()], Book::pages.is_null())
+        };
+
+        let q: Query<'_, u64> = insert().into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title, pages) VALUES ($1, $2, $3) \
+            ON CONFLICT (title) WHERE book.pages IS NULL DO NOTHING"
+        );
+
+        let q: Query<'_, u64> = insert().do_update_where(Book::id.eq(&id)).into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title, pages) VALUES ($1, $2, $3) \
+            ON CONFLICT (title) WHERE book.pages IS NULL \
+            DO UPDATE SET id = EXCLUDED.id, pages = EXCLUDED.pages WHERE book.id = $4"
+        );
+        assert_eq!(q.params().len(), 4);
+
+        // The condition can't take parameters
+        let mut q: Query<'_, u64> = Book::insert_builder()
+            .entry(Book::title, &title)
+            .on_conflict_where(&[Book::title.column()], Book::pages.eq(&pages))
+            .into();
+        assert!(matches!(
+            q.take_error(),
+            Some(Error::ParameterizedConflictTarget)
+        ));
+    }
+
+    #[test]
+    fn guarded_upsert() {
+        let (id, title, pages) = (1, String::from("Foo"), Some(3));
+        let other = String::from("Bar");
+
+        let q: Query<'_, u64> = Book::insert_builder()
+            .entry(Book::id, &id)
+            .entry(Book::title, &title)
+            .entry(Book::pages, &pages)
+            .on_conflict(Book::id)
+            .do_update_where(Book::pages.excluded().gt_column(Book::pages))
+            .do_update_where(Book::title.eq(&other))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id, title, pages) VALUES ($1, $2, $3) \
+            ON CONFLICT (id) DO UPDATE SET title = EXCLUDED.title, pages = EXCLUDED.pages \
+            WHERE (excluded.pages > book.pages) AND (book.title = $4)"
+        );
+        assert_eq!(q.params().len(), 4);
+
+        // Only the conflict target is inserted
+        let q: Query<'_, u64> = Book::insert_builder()
+            .entry(Book::id, &id)
+            .on_conflict(Book::id)
+            .do_update_where(Book::id.excluded().eq(&id))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO book (id) VALUES ($1) \
+            ON CONFLICT (id) DO UPDATE SET id = EXCLUDED.id WHERE excluded.id = $2"
+        );
+    }
+
+    #[derive(Model)]
+    struct Shelf {
+        #[column(primary_key, auto)]
+        id: i64,
+        title: String,
+        shelved: bool,
+    }
+
+    #[test]
+    fn insert_from_select() {
+        let (min_pages, shelved) = (Some(100), true);
+        let select = || {
+            Select::<Vec<Book>>::new(
+                &[Book::id.column(), Book::title.column()],
+                Book::table_name(),
+            )
+            .where_(Book::pages.gt(&min_pages))
+            .limit(5)
+        };
+
+        let q: Query<'_, u64> = Shelf::insert_builder()
+            .from_select(&[Shelf::id.column(), Shelf::title.column()], select())
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO shelf (id, title) \
+            SELECT book.id, book.title FROM book WHERE book.pages > $1 LIMIT $2"
+        );
+        assert_eq!(format!("{:?}", q.params()), "[Some(100), 5]");
+
+        // Entries are numbered after the select's parameters
+        let q: Query<'_, u64> = Shelf::insert_builder()
+            .from_select(&[Shelf::id.column(), Shelf::title.column()], select())
+            .entry(Shelf::shelved, &shelved)
+            .on_conflict(Shelf::id)
+            .do_update_where(Shelf::shelved.eq(&shelved))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "INSERT INTO shelf (id, title, shelved) SELECT source.*, $3 FROM (\
+            SELECT book.id, book.title FROM book WHERE book.pages > $1 LIMIT $2\
+            ) AS source ON CONFLICT (id) \
+            DO UPDATE SET title = EXCLUDED.title, shelved = EXCLUDED.shelved \
+            WHERE shelf.shelved = $4"
+        );
+        assert_eq!(format!("{:?}", q.params()), "[Some(100), 5, true, true]");
+    }
+
+    #[test]
+    fn columns_of_another_table() {
+        let title = String::from("Foo");
+        let select = Select::<Vec<Book>>::new(&[Book::title.column()], Book::table_name());
+
+        let q: Query<'_, u64> = Shelf::insert_builder()
+            .from_select(&[Book::title.column()], select)
+            .into();
+        assert!(matches!(q.error, Some(crate::Error::UnknownColumn(col)) if col == "book.title"));
+
+        let q: Query<'_, u64> = Shelf::insert_builder()
+            .entry(Shelf::title, &title)
+            .on_conflict_where(&[Book::title.column()], Shelf::shelved.eq(&true))
+            .into();
+        assert!(matches!(q.error, Some(crate::Error::UnknownColumn(col)) if col == "book.title"));
+    }
+
+    #[test]
+    fn insert_validation() {
+        let title = String::from("Too long");
+        let q: Query<'_, u64> = Book::insert_builder().entry(Book::title, &title).into();
+        assert!(matches!(
+            q.error,
+            Some(crate::Error::Validation {
+                column: "title",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use tokio_postgres::types::ToSql;
+
+use super::{Column, Param, TypedColumn};
+
+/// The columns a query is paginated by, see [`Select::after`](super::Select::after).
+///
+/// It is implemented for single columns and
+/// for tuples of up to four columns.
+pub trait Keyset<'a> {
+    /// The values of the last row seen, one per column.
+    type Values;
+
+    /// Get the columns, in order.
+    fn columns(&self) -> Vec<Column>;
+
+    /// Get the values as parameters, in order.
+    fn params(&self, values: Self::Values) -> Vec<Param<'a>>;
+}
+
+impl<'a, T: ToSql + Sync + Send + 'static, M> Keyset<'a> for TypedColumn<T, M> {
+    type Values = &'a T;
+
+    fn columns(&self) -> Vec<Column> {
+        vec![self.column()]
+    }
+
+    fn params(&self, value: &'a T) -> Vec<Param<'a>> {
+        vec![self.bind(value)]
+    }
+}
+
+macro_rules! impl_keyset {
+    ($(($($ty:ident $model:ident $idx:tt),+);)+) => {
+        $(
+            impl<'a, $($ty: ToSql + Sync + Send + 'static, $model),+> Keyset<'a>
+                for ($(TypedColumn<$ty, $model>,)+)
+            {
+                type Values = ($(&'a $ty,)+);
+
+                fn columns(&self) -> Vec<Column> {
+                    vec![$(self.$idx.column()),+]
+                }
+
+                fn params(&self, values: Self::Values) -> Vec<Param<'a>> {
+                    vec![$(self.$idx.bind(values.$idx)),+]
+                }
+            }
+        )+
+    };
+}
+
+impl_keyset! {
+    (A MA 0, B MB 1);
+    (A MA 0, B MB 1, C MC 2);
+    (A MA 0, B MB 1, C MC 2, D MD 3);
+}
+
+/// A page of rows fetched using [`Select::page`](super::Select::page).
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeysetPage<T, C> {
+    /// The rows of this page.
+    pub items: Vec<T>,
+    /// The key of the last row, which is passed to
+    /// [`Select::after`](super::Select::after) to fetch the next page.
+    ///
+    /// It is `None` if this is the last page, i.e. if it
+    /// has fewer rows than the query's limit.
+    pub next_cursor: Option<C>,
+}
+
+impl<T, C> KeysetPage<T, C> {
+    /// Make a page out of the rows fetched with the given limit,
+    /// taking the cursor from the last row.
+    pub(super) fn new(items: Vec<T>, limit: Option<u64>, cursor: impl FnOnce(&T) -> C) -> Self {
+        let full = limit.is_some_and(|limit| items.len() as u64 >= limit);
+        let next_cursor = items.last().filter(|_| full).map(cursor);
+
+        KeysetPage { items, next_cursor }
+    }
+}
@@ -0,0 +1,149 @@
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+
+use super::Query;
+use crate::{fetch_client, pool::with_retries, Error};
+
+/// A `VACUUM` or `ANALYZE` statement.
+///
+/// Calling `.await` executes it on a connection of its own, outside of any
+/// transaction. `ANALYZE` can also be passed to
+/// [`Transaction::execute`](super::Transaction::execute), but `VACUUM` can't
+/// run inside a transaction: doing so, or executing it as a prepared
+/// statement, returns an [`Error::NeedsOwnConnection`].
+///
+/// # Example
+///
+/// ```ignore
+/// // ---- snip bulk load ----
+/// Book::analyze().await?;
+/// Author::vacuum(true).await?;
+/// analyze!(Book, Author).await?;
+/// ```
+pub struct Maintenance {
+    command: &'static str,
+    statement: String,
+    /// Whether no table was passed, which would
+    /// make the statement cover the whole database.
+    no_tables: bool,
+}
+
+impl Maintenance {
+    /// Update the planner statistics of the given tables.
+    ///
+    /// Use [`analyze!`](crate::analyze) to analyze the tables of several models.
+    ///
+    /// Executing it fails with [`Error::NoTablesNamed`] if `tables` is empty,
+    /// instead of analyzing every table of the database.
+    pub fn analyze(tables: Vec<&'static str>) -> Maintenance {
+        Maintenance {
+            command: "ANALYZE",
+            statement: format!("ANALYZE {}", tables.join(", ")),
+            no_tables: tables.is_empty(),
+        }
+    }
+
+    /// Reclaim the storage of a table and optionally
+    /// update its planner statistics, too.
+    pub(crate) fn vacuum(table: &str, analyze: bool) -> Maintenance {
+        let statement = if analyze {
+            format!("VACUUM (ANALYZE) {table}")
+        } else {
+            format!("VACUUM {table}")
+        };
+
+        Maintenance {
+            command: "VACUUM",
+            statement,
+            no_tables: false,
+        }
+    }
+
+    /// Get the statement which is executed.
+    pub fn statement(&self) -> &str {
+        &self.statement
+    }
+}
+
+impl<'a> From<Maintenance> for Query<'a, u64> {
+    fn from(value: Maintenance) -> Self {
+        if value.no_tables {
+            let mut query = Query::default();
+            query.fail(Error::NoTablesNamed(value.command));
+
+            return query;
+        }
+
+        if value.command == "ANALYZE" {
+            return Query::new(value.statement, vec![]);
+        }
+
+        let mut query = Query::default();
+        query.fail(Error::NeedsOwnConnection(value.command));
+
+        query
+    }
+}
+
+impl IntoFuture for Maintenance {
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+    type Output = Result<(), Error>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            if self.no_tables {
+                return Err(Error::NoTablesNamed(self.command));
+            }
+
+            // Both are safe to repeat
+            with_retries(|| async {
+                // A fresh client from the pool is never inside a transaction
+                let client = fetch_client().await?;
+                client.batch_execute(&self.statement).await?;
+
+                Ok(())
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use crate::prelude::*;
+    use crate::query::{Maintenance, Query};
+    use crate::Error;
+
+    #[derive(Model)]
+    struct Book {
+        id: i64,
+    }
+
+    #[derive(Model)]
+    struct Author {
+        id: i64,
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(Book::analyze().statement(), "ANALYZE book");
+        assert_eq!(Book::vacuum(false).statement(), "VACUUM book");
+        assert_eq!(Book::vacuum(true).statement(), "VACUUM (ANALYZE) book");
+        assert_eq!(
+            crate::analyze!(Book, Author).statement(),
+            "ANALYZE book, author"
+        );
+    }
+
+    #[tokio::test]
+    async fn analyze_without_tables() {
+        // Without a table, `ANALYZE` would cover the whole database
+        let res = Maintenance::analyze(vec![]).await;
+        assert!(matches!(res, Err(Error::NoTablesNamed("ANALYZE"))));
+
+        let query: Query<'_, u64> = Maintenance::analyze(vec![]).into();
+        assert!(matches!(query.await, Err(Error::NoTablesNamed("ANALYZE"))));
+    }
+}
@@ -1,33 +1,71 @@
 //! This module contains the logic for building queries,
 //! as well as struct for representing columns.
 
+mod archive;
+mod codec;
 mod delete;
+mod hydrate;
 mod insert;
+mod keyset;
+mod maintenance;
+mod owned;
+mod param;
+mod placeholder;
+mod render;
 mod select;
 mod table;
 mod transaction;
+mod truncate;
+mod union;
 mod update;
+mod with;
 
-pub use table::{Column, ColumnInfo, TypedColumn};
+#[cfg(feature = "time")]
+pub use table::ZonedColumn;
+pub use table::{
+    clone_elements, parse_value, Column, ColumnInfo, ColumnOf, ColumnValueEncoder,
+    ColumnValueParser, TypedColumn,
+};
 
 use std::{
     future::{Future, IntoFuture},
     marker::PhantomData,
     ops::{BitAnd, BitOr, Not},
     pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use async_trait::async_trait;
-use tokio_postgres::{types::ToSql, Row, Transaction as PgTransaction};
+use tokio_postgres::{types::ToSql, Client as PgClient, Transaction as PgTransaction};
 
-use crate::{fetch_client, pool::fetch_prepared_client, Client, Error, FromRow};
+use crate::{
+    pool::{fetch_prepared_client, pgbouncer_transaction_mode, sandboxed, unnamed, with_retries},
+    Error, FromRow, Row,
+};
 
+pub use archive::Archive;
+pub use codec::{parse_encoded, Codec, Encoded, RawValue};
 pub use delete::Delete;
-pub use insert::Insert;
+pub use hydrate::{Hydrate, Hydration};
+pub use insert::{
+    insert_idempotent, insert_lenient, insert_seed, Insert, InsertReport, IntoNullable, RowError,
+    RowErrorKind,
+};
+pub use keyset::{Keyset, KeysetPage};
+pub use maintenance::Maintenance;
+pub use owned::OwnedQuery;
+pub use param::Param;
+pub use render::{format_sql_value, Sensitive};
 pub use select::Select;
 pub use transaction::*;
+pub use truncate::Truncate;
+pub use union::{Order, Union};
 pub use update::{NoneSet, SomeSet, Update};
 
+use placeholder::{number_placeholders, push_numbered};
+use with::{With, WithState};
+
 /// A trait implemented by everything that goes inside a query.
 #[doc(hidden)]
 pub trait PushChunk<'a> {
@@ -51,7 +89,7 @@ pub trait Executor {
 pub trait QueryOutcome: Sized {
     /// The actual function for executing a query.
     async fn exec(statement: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Self, crate::Error> {
-        let client = fetch_client().await?;
+        let client = fetch_scoped_client().await?;
         Self::exec_with(statement, params, &client).await
     }
 
@@ -63,33 +101,54 @@ pub trait QueryOutcome: Sized {
     ) -> Result<Self, crate::Error>;
 }
 
-///
+/// Trait used to execute queries as prepared statements.
 #[async_trait]
 pub trait Prepared<T>: Sized {
-    ///
+    /// Execute the query as a prepared statement.
     async fn prepared(self) -> Result<T, crate::Error>;
 }
 
 /// A struct for storing a complete query along with
 /// parameters and output type.
-pub struct Query<'a, T = Vec<Row>>(pub String, Vec<&'a (dyn ToSql + Sync)>, PhantomData<T>);
+///
+/// Errors which occur while building the query (e.g. a value
+/// failing validation) are stored and returned upon execution.
+///
+/// Use [`Query::statement`] and [`Query::params`] to inspect it.
+pub struct Query<'a, T = Vec<Row>> {
+    statement: String,
+    params: Vec<Param<'a>>,
+    output: PhantomData<T>,
+    /// The first error which occurred while building the query.
+    error: Option<Error>,
+    with: WithState,
+    /// Whether the query may be retried according to
+    /// the pool's [`RetryPolicy`](crate::pool::RetryPolicy).
+    idempotent: bool,
+    /// Called with the returned rows, e.g. to invalidate the
+    /// cached entities they belong to, see [`crate::cache`].
+    inspect: Option<RowHook>,
+    /// The transaction the query is executed in,
+    /// see [`Transaction::select`].
+    transaction: Option<Arc<OpenTransaction>>,
+}
 
 /// A basic chunk of SQL and it's params.
 ///
 /// This is bundes the params with the relevant part of the statement
 /// and thus makes ordering them much easier.
+///
+/// Its `?` placeholders are numbered when it is pushed to a buffer,
+/// counting from the number of parameters the buffer already holds.
 #[doc(hidden)]
-pub struct SqlChunk<'a>(pub String, pub Vec<&'a (dyn ToSql + Sync)>);
-
-/// A generic implementation of `IntoFuture` for all viable query builders
-/// ensures that each can be built _and_ executed simply
-/// by calling `.await`.
+#[derive(Clone)]
+pub struct SqlChunk<'a>(pub String, pub Vec<Param<'a>>);
 
 /// Push multiple `PushChunk` objects to a buffer with a separator
 /// between each of them.
 ///
 /// Like `Vec::join()`.
-fn push_all_with_sep<'a, T, U: PushChunk<'a>>(
+pub(super) fn push_all_with_sep<'a, T, U: PushChunk<'a>>(
     vec: &mut Vec<U>,
     buffer: &mut Query<'a, T>,
     sep: &str,
@@ -100,15 +159,62 @@ fn push_all_with_sep<'a, T, U: PushChunk<'a>>(
 
     for i in vec {
         i.push_to_buffer(buffer);
-        buffer.0.push_str(sep);
+        buffer.statement.push_str(sep);
     }
 
     // Remove the last `sep` as it's not
     // in between elements.
-    buffer.0.truncate(buffer.0.len() - sep.len());
+    buffer
+        .statement
+        .truncate(buffer.statement.len() - sep.len());
+}
+
+/// The outcome of a query together with the number of
+/// rows it returned or affected.
+///
+/// Created by calling `.with_count()` on a query builder.
+///
+/// # Example
+///
+/// ```ignore
+/// let res = Book::select().with_count().await?;
+/// println!("found {} books", res.count);
+/// let books: Vec<Book> = res.rows;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecResult<T> {
+    /// The actual outcome of the query.
+    pub rows: T,
+    /// The number of rows returned or affected by the query.
+    pub count: u64,
+}
+
+/// How long executing a query took.
+///
+/// Returned next to the outcome by queries built
+/// with `.with_timing()`.
+///
+/// # Example
+///
+/// ```ignore
+/// let (books, timing) = Book::select().with_timing().await?;
+/// println!("waited {:?} for a connection", timing.wait_for_client);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryTiming {
+    /// The time spent waiting for a connection from the pool.
+    ///
+    /// It is zero if the query ran on a connection which was already
+    /// checked out, e.g. inside a transaction or as a prepared statement.
+    pub wait_for_client: Duration,
+    /// The time spent executing the statement, without parsing the rows.
+    pub execution: Duration,
+    /// The number of rows returned or affected by the query.
+    pub rows: u64,
 }
 
 /// An enum representing the `WHERE` clause of a query.
+#[derive(Clone)]
 pub enum Where<'a> {
     /// A number of conditions joined by `AND`.
     And(Vec<Where<'a>>),
@@ -116,6 +222,9 @@ pub enum Where<'a> {
     Or(Vec<Where<'a>>),
     /// A negated condition.
     Not(Box<Where<'a>>),
+    /// A condition which is kept in its own parentheses
+    /// and never merged with the conditions around it.
+    Group(Box<Where<'a>>),
     /// A raw condition.
     Raw(SqlChunk<'a>),
     /// An empty `WHERE` clause.
@@ -130,39 +239,15 @@ where
     T: Send + Sync,
 {
     async fn prepared(self) -> Result<U, crate::Error> {
-        let client = fetch_prepared_client().await?;
         let q: Query<'a, U> = self.into();
-        U::exec_with(&q.0, q.1.as_slice(), client).await
-    }
-}
-
-/// Replace all `?` placeholders with the Postgres variant
-/// `$1`, `$2`, etc.
-fn replace_question_marks(stmt: String) -> String {
-    // Since we change '?' to e.g. '$1' we need to
-    // reserve some more space to avoid reallocating.
-    const RESERVED: usize = 9;
-    let mut buf = String::with_capacity(stmt.len() + RESERVED);
-
-    // Tracking variable
-    let mut last_index = 0;
-
-    // Looping through all '?' in the string
-    for (count, (i, _)) in stmt.match_indices('?').enumerate() {
-        // Push everything until the '?'
-        buf.push_str(&stmt[last_index..i]);
-
-        // Push '$' including the number
-        buf.push('$');
-        buf.push_str(&(count + 1).to_string());
+        // Prepared statements would end up on the wrong connection
+        if pgbouncer_transaction_mode() || sandboxed() || scoped() || q.transaction.is_some() {
+            return q.exec().await;
+        }
 
-        last_index = i + 1;
+        let client = fetch_prepared_client().await?;
+        q.exec_with(&**client).await
     }
-
-    // Push the tail
-    buf.push_str(&stmt[last_index..]);
-
-    buf
 }
 
 #[async_trait]
@@ -187,7 +272,7 @@ where
         client: impl Executor + Sync + Send,
     ) -> Result<Vec<T>, crate::Error> {
         let res = client.query(statement, params).await?;
-        res.into_iter().map(T::try_from).collect()
+        T::from_rows(res)
     }
 }
 
@@ -206,49 +291,458 @@ where
     }
 }
 
+#[async_trait]
+impl<T> QueryOutcome for ExecResult<Vec<T>>
+where
+    T: FromRow,
+{
+    async fn exec_with(
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        client: impl Executor + Sync + Send,
+    ) -> Result<ExecResult<Vec<T>>, crate::Error> {
+        let res = client.query(statement, params).await?;
+        let count = res.len() as u64;
+
+        Ok(ExecResult {
+            rows: T::from_rows(res)?,
+            count,
+        })
+    }
+}
+
+#[async_trait]
+impl<T> QueryOutcome for ExecResult<Option<T>>
+where
+    T: FromRow,
+{
+    async fn exec_with(
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        client: impl Executor + Sync + Send,
+    ) -> Result<ExecResult<Option<T>>, crate::Error> {
+        let res = client.query(statement, params).await?;
+        let count = res.len() as u64;
+
+        Ok(ExecResult {
+            rows: res.into_iter().map(T::try_from).next().transpose()?,
+            count,
+        })
+    }
+}
+
+#[async_trait]
+impl QueryOutcome for ExecResult<u64> {
+    async fn exec_with(
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        client: impl Executor + Sync + Send,
+    ) -> Result<ExecResult<u64>, crate::Error> {
+        let count = client.execute(statement, params).await?;
+
+        Ok(ExecResult { rows: count, count })
+    }
+}
+
+#[async_trait]
+impl<T> QueryOutcome for (T, QueryTiming)
+where
+    T: QueryOutcome + Send,
+{
+    async fn exec(
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<(T, QueryTiming), crate::Error> {
+        let start = Instant::now();
+        let client = fetch_scoped_client().await?;
+        let wait_for_client = start.elapsed();
+
+        let (outcome, timing) = Self::exec_with(statement, params, &client).await?;
+
+        Ok((
+            outcome,
+            QueryTiming {
+                wait_for_client,
+                ..timing
+            },
+        ))
+    }
+
+    async fn exec_with(
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        client: impl Executor + Sync + Send,
+    ) -> Result<(T, QueryTiming), crate::Error> {
+        let client = Timing {
+            inner: client,
+            timing: Mutex::default(),
+        };
+        let outcome = T::exec_with(statement, params, &client).await?;
+        let timing = client.timing.into_inner().unwrap_or_default();
+
+        Ok((outcome, timing))
+    }
+}
+
 impl<'a, T> Default for Query<'a, T> {
     fn default() -> Self {
-        Self("".into(), vec![], PhantomData::<T>)
+        Self {
+            statement: String::new(),
+            params: vec![],
+            output: PhantomData::<T>,
+            error: None,
+            with: WithState::default(),
+            idempotent: false,
+            inspect: None,
+            transaction: None,
+        }
     }
 }
 
 impl<'a, T> Query<'a, T> {
+    /// Create an empty buffer which builders push their chunks to,
+    /// with room for a statement of about `len` bytes.
+    pub(super) fn with_capacity(len: usize) -> Query<'a, T> {
+        Query {
+            statement: String::with_capacity(len),
+            ..Query::default()
+        }
+    }
+
     /// Create a new query by passing a raw statement as well as parameters.
     pub fn new(stmt: String, params: Vec<&'a (dyn ToSql + Sync)>) -> Query<'a, T> {
-        Query(replace_question_marks(stmt), params, PhantomData::<T>)
+        Query::with_params(stmt, params.into_iter().map(Param::from).collect())
+    }
+
+    /// Like [`Query::new`], but the parameters may be owned
+    /// or marked as sensitive, see [`Param`].
+    pub fn with_params(stmt: String, params: Vec<Param<'a>>) -> Query<'a, T> {
+        Query {
+            statement: number_placeholders(&stmt, 0),
+            params,
+            ..Default::default()
+        }
+    }
+
+    /// Like [`Query::new`], but the statement already references its
+    /// parameters as `$1`, `$2`, etc. and is used as is. A `?` is not
+    /// turned into a placeholder, e.g. to use the jsonb operator `?`.
+    pub fn numbered(stmt: String, params: Vec<&'a (dyn ToSql + Sync)>) -> Query<'a, T> {
+        Query {
+            statement: stmt,
+            params: params.into_iter().map(Param::from).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Get the query's statement.
+    ///
+    /// The parameters are referenced as `$1`, `$2`, etc.
+    pub fn statement(&self) -> &str {
+        &self.statement
+    }
+
+    /// Get the query's parameters in the order
+    /// they are referenced by the statement.
+    pub fn params(&self) -> &[Param<'a>] {
+        &self.params
+    }
+
+    /// Render the statement and its parameters, e.g. for logging.
+    ///
+    /// The parameters are rendered using [`format_sql_value`], except for
+    /// the values of columns marked with `#[column(sensitive)]`,
+    /// which are hidden:
+    ///
+    /// ```ignore
+    /// let query = Query::from(
+    ///     Account::insert_builder()
+    ///         .entry(Account::name, &name)
+    ///         .entry(Account::password, &password),
+    /// );
+    /// // INSERT INTO account (name, password) VALUES ($1, $2) -- $1 = 'alice', $2 = ***
+    /// println!("{}", query.describe());
+    /// ```
+    pub fn describe(&self) -> String {
+        if self.params.is_empty() {
+            return self.statement.clone();
+        }
+
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| match param.is_sensitive() {
+                true => format!("${} = {param:?}", i + 1),
+                false => format!("${} = {}", i + 1, format_sql_value(param.value())),
+            })
+            .collect();
+
+        format!("{} -- {}", self.statement, params.join(", "))
+    }
+
+    /// Change the query's statement, e.g. to add a clause
+    /// which isn't supported by the query builders.
+    ///
+    /// The parameters stay the same, so the statement passed to `f`
+    /// already uses `$1`, `$2`, etc. and the result has to
+    /// reference them the same way.
+    ///
+    /// The changed statement, e.g. locking rows using `FOR UPDATE`, may
+    /// not be safe to repeat, so the query isn't considered
+    /// [`idempotent`](Query::idempotent) anymore.
+    ///
+    /// ```ignore
+    /// let query = Query::<Vec<Book>>::from(Book::select())
+    ///     .map_statement(|stmt| stmt + " FOR UPDATE");
+    /// ```
+    pub fn map_statement(mut self, f: impl FnOnce(String) -> String) -> Query<'a, T> {
+        self.statement = f(self.statement);
+        self.idempotent = false;
+        self
+    }
+
+    /// Also return how long executing the query took, see [`QueryTiming`].
+    pub fn with_timing(self) -> Query<'a, (T, QueryTiming)> {
+        Query {
+            statement: self.statement,
+            params: self.params,
+            output: PhantomData,
+            error: self.error,
+            with: self.with,
+            idempotent: self.idempotent,
+            inspect: self.inspect,
+            transaction: self.transaction,
+        }
+    }
+
+    /// Mark this query as safe to run more than once, which allows
+    /// retrying it after a transient error. `SELECT`s are considered
+    /// idempotent, unless a common table expression has a raw body
+    /// or the statement was changed using [`Query::map_statement`].
+    ///
+    /// See [`ConnectionBuilder::retry_policy`](crate::pool::ConnectionBuilder::retry_policy).
+    pub fn idempotent(mut self) -> Query<'a, T> {
+        self.idempotent = true;
+        self
+    }
+
+    /// Store an error which is returned when executing the query.
+    ///
+    /// Only the first error is kept.
+    pub(crate) fn fail(&mut self, error: Error) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Take the error which occurred while building the query, if any.
+    pub(crate) fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+impl<'a, T: QueryOutcome + Send> Query<'a, T> {
+    /// Execute the query using a client from the pool,
+    /// or the transaction it is bound to.
+    ///
+    /// Idempotent queries are retried after transient errors,
+    /// unless they are part of a transaction.
+    pub(crate) async fn exec(mut self) -> Result<T, Error> {
+        let transaction = self.transaction.take();
+
+        run_bound(transaction, self.exec_unbound()).await
+    }
+
+    /// Execute the query using a client from the pool
+    /// (or the transaction of the enclosing [`Transaction::scope`]).
+    async fn exec_unbound(self) -> Result<T, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let params = Param::values(&self.params);
+        let run = || exec_inspecting::<T>(&self.statement, &params, self.inspect);
+
+        if !self.idempotent || scoped() {
+            return run().await;
+        }
+
+        with_retries(run).await
+    }
+}
+
+/// Execute a statement using a client from the pool (or the transaction
+/// of the enclosing [`Transaction::scope`]), passing the
+/// returned rows to `inspect` if the query has such a hook.
+pub(super) async fn exec_inspecting<T: QueryOutcome + Send>(
+    statement: &str,
+    params: &[&(dyn ToSql + Sync)],
+    inspect: Option<RowHook>,
+) -> Result<T, Error> {
+    let Some(hook) = inspect else {
+        return T::exec(statement, params).await;
+    };
+
+    let client = fetch_scoped_client().await?;
+    let client = Inspecting {
+        inner: &client,
+        hook,
+    };
+    T::exec_with(statement, params, client).await
+}
+
+impl<'a, T: QueryOutcome> Query<'a, T> {
+    /// Execute the query using any viable `Executor`.
+    pub(crate) async fn exec_with(self, client: impl Executor + Sync + Send) -> Result<T, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let params = Param::values(&self.params);
+        match self.inspect {
+            Some(hook) => {
+                let client = Inspecting {
+                    inner: client,
+                    hook,
+                };
+                T::exec_with(&self.statement, &params, client).await
+            }
+            None => T::exec_with(&self.statement, &params, client).await,
+        }
     }
 }
 
 impl<'a> PushChunk<'a> for SqlChunk<'a> {
     fn push_to_buffer<T>(&mut self, buffer: &mut Query<'a, T>) {
-        buffer.0.push_str(&self.0);
-        buffer.1.append(&mut self.1);
+        // Numbering the placeholders right away ties each of them
+        // to its parameter, no matter where the chunk ends up.
+        push_numbered(&mut buffer.statement, &self.0, buffer.params.len());
+        buffer.params.append(&mut self.1);
     }
 }
 
+/// Run a statement, recording it in the [`metrics`](crate::metrics)
+/// if they are enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(super) async fn measured<T, E>(
+    stmt: &str,
+    run: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    #[cfg(feature = "metrics")]
+    let start = Instant::now();
+    let res = run.await;
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_statement(stmt, start.elapsed(), res.is_err());
+
+    res
+}
+
 #[async_trait]
 impl<'a> Executor for &PgTransaction<'a> {
     async fn query(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error> {
-        PgTransaction::query(self, stmt, params)
+        let rows = measured(stmt, PgTransaction::query(self, stmt, params))
             .await
-            .map_err(Error::from)
+            .map_err(|err| Error::from_query(err, stmt))?;
+
+        Ok(rows.into_iter().map(Row::from).collect())
     }
 
     async fn execute(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
-        PgTransaction::execute(self, stmt, params)
+        measured(stmt, PgTransaction::execute(self, stmt, params))
+            .await
+            .map_err(|err| Error::from_query(err, stmt))
+    }
+}
+
+#[async_trait]
+impl Executor for &PgClient {
+    async fn query(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error> {
+        let run = unnamed(
+            self,
+            params,
+            |typed| async move { self.query_typed(stmt, &typed).await },
+            || PgClient::query(self, stmt, params),
+        );
+        let rows = measured(stmt, run)
             .await
-            .map_err(Error::from)
+            .map_err(|err| Error::from_query(err, stmt))?;
+
+        Ok(rows.into_iter().map(Row::from).collect())
+    }
+
+    async fn execute(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
+        let run = unnamed(
+            self,
+            params,
+            |typed| async move { self.execute_typed(stmt, &typed).await },
+            || PgClient::execute(self, stmt, params),
+        );
+        measured(stmt, run)
+            .await
+            .map_err(|err| Error::from_query(err, stmt))
+    }
+}
+
+/// A function called with the rows returned by a query before they
+/// are parsed. Returning an error fails the query.
+pub(crate) type RowHook = fn(&[Row]) -> Result<(), Error>;
+
+/// An `Executor` passing the returned rows to a [`RowHook`].
+///
+/// Its statements always return rows, e.g. using a `RETURNING` clause,
+/// so the number of affected rows is the number of returned rows.
+struct Inspecting<E> {
+    inner: E,
+    hook: RowHook,
+}
+
+#[async_trait]
+impl<E: Executor + Sync + Send> Executor for Inspecting<E> {
+    async fn query(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error> {
+        let rows = self.inner.query(stmt, params).await?;
+        (self.hook)(&rows)?;
+
+        Ok(rows)
+    }
+
+    async fn execute(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
+        let rows = self.query(stmt, params).await?;
+
+        Ok(rows.len() as u64)
+    }
+}
+
+/// An `Executor` measuring how long executing
+/// statements takes and how many rows they return.
+struct Timing<E> {
+    inner: E,
+    timing: Mutex<QueryTiming>,
+}
+
+impl<E> Timing<E> {
+    fn record(&self, execution: Duration, rows: u64) {
+        if let Ok(mut timing) = self.timing.lock() {
+            timing.execution += execution;
+            timing.rows += rows;
+        }
     }
 }
 
 #[async_trait]
-impl Executor for &Client {
+impl<E: Executor + Sync + Send> Executor for &Timing<E> {
     async fn query(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error> {
-        (***self).query(stmt, params).await.map_err(Error::from)
+        let start = Instant::now();
+        let rows = self.inner.query(stmt, params).await?;
+        self.record(start.elapsed(), rows.len() as u64);
+
+        Ok(rows)
     }
 
     async fn execute(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
-        (***self).execute(stmt, params).await.map_err(Error::from)
+        let start = Instant::now();
+        let count = self.inner.execute(stmt, params).await?;
+        self.record(start.elapsed(), count);
+
+        Ok(count)
     }
 }
 
@@ -258,17 +752,22 @@ impl<'a, T: Send + 'a> IntoFuture for Query<'a, T>
 where
     T: QueryOutcome + Sync,
 {
-    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'a>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
     type Output = Result<T, crate::Error>;
 
     fn into_future(self) -> Self::IntoFuture {
-        Box::pin(async move { T::exec(&self.0, self.1.as_slice()).await })
+        Box::pin(self.exec())
     }
 }
 
 impl<'a> Where<'a> {
     /// Create a new WHERE expression with parameters.
     pub(crate) fn new(expr: String, params: Vec<&'a (dyn ToSql + Sync)>) -> Where<'a> {
+        Where::with_params(expr, params.into_iter().map(Param::from).collect())
+    }
+
+    /// Like [`Where::new`], but the parameters may be owned or sensitive.
+    pub(crate) fn with_params(expr: String, params: Vec<Param<'a>>) -> Where<'a> {
         Self::Raw(SqlChunk(expr, params))
     }
 
@@ -280,11 +779,23 @@ impl<'a> Where<'a> {
             Empty => true,
             And(vec) => vec.iter().all(|i| i.is_empty()),
             Or(vec) => vec.iter().all(|i| i.is_empty()),
-            Not(inner) => inner.is_empty(),
+            Not(inner) | Group(inner) => inner.is_empty(),
             Raw(chunk) => chunk.0.is_empty(),
         }
     }
 
+    /// Check whether the condition takes any parameters.
+    pub(crate) fn has_params(&self) -> bool {
+        use Where::*;
+
+        match self {
+            Empty => false,
+            And(vec) | Or(vec) => vec.iter().any(|i| i.has_params()),
+            Not(inner) | Group(inner) => inner.has_params(),
+            Raw(chunk) => !chunk.1.is_empty(),
+        }
+    }
+
     /// Combine two conditions using AND.
     ///
     /// You can also use the `&` operator.
@@ -304,6 +815,132 @@ impl<'a> Where<'a> {
     pub fn or(self, other: Where<'a>) -> Where<'a> {
         self.bitor(other)
     }
+
+    /// Wrap a condition in parentheses of its own.
+    ///
+    /// Each operand of `&` and `|` is already parenthesized,
+    /// so `a & (b | c)` and `(a & b) | c` produce different SQL.
+    /// A group additionally keeps its conditions from being merged
+    /// into the surrounding `AND` or `OR`, which preserves the
+    /// structure you built.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // (book.id = $1) AND (((book.id > $2) AND (book.id < $3)))
+    /// Book::id.eq(&1) & Where::group(Book::id.gt(&2) & Book::id.lt(&3))
+    /// ```
+    pub fn group(inner: Where<'a>) -> Where<'a> {
+        use Where::*;
+
+        match inner {
+            Empty | Group(_) => inner,
+            _ => Group(Box::new(inner)),
+        }
+    }
+
+    /// Combine any number of conditions using AND.
+    ///
+    /// Empty conditions are skipped. If no conditions are
+    /// left, the result is [`Where::Empty`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let filters = titles.iter().map(|title| Book::title.neq(title));
+    /// Book::select().where_(Where::all(filters))
+    /// ```
+    pub fn all(conditions: impl IntoIterator<Item = Where<'a>>) -> Where<'a> {
+        conditions
+            .into_iter()
+            .filter(|i| !i.is_empty())
+            .fold(Where::Empty, BitAnd::bitand)
+    }
+
+    /// Combine any number of conditions using OR.
+    ///
+    /// Empty conditions are skipped. If no conditions are
+    /// left, the result is [`Where::Empty`].
+    pub fn any(conditions: impl IntoIterator<Item = Where<'a>>) -> Where<'a> {
+        conditions
+            .into_iter()
+            .filter(|i| !i.is_empty())
+            .fold(Where::Empty, BitOr::bitor)
+    }
+
+    /// Check whether the values of several columns are
+    /// equal to any of some tuples, e.g. to match composite keys.
+    ///
+    /// The columns are passed as a tuple, so each row has to be a tuple
+    /// of their types. The values are bound by their columns, so they
+    /// are encoded or masked like in [`TypedColumn::eq`].
+    /// No tuples never match, i.e. the condition is `FALSE`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // (book.author_id, book.title) IN ((?, ?), (?, ?))
+    /// let keys = [(1_i64, "Dune".to_string()), (2, "Emma".to_string())];
+    /// Book::select().where_(Where::tuple_in((Book::author_id, Book::title), &keys))
+    /// ```
+    pub fn tuple_in<C: Tuple>(columns: C, rows: &'a [C::Values]) -> Where<'a> {
+        if rows.is_empty() {
+            return Where::new("FALSE".into(), vec![]);
+        }
+
+        let names = columns.full_names();
+        let placeholders = format!("({})", vec!["?"; names.len()].join(", "));
+
+        Where::with_params(
+            format!(
+                "({}) IN ({})",
+                names.join(", "),
+                vec![placeholders; rows.len()].join(", ")
+            ),
+            rows.iter().flat_map(|row| columns.bind(row)).collect(),
+        )
+    }
+}
+
+/// A tuple of columns compared to tuples of values at once,
+/// see [`Where::tuple_in`].
+///
+/// It is implemented for tuples of up to eight [`TypedColumn`]s.
+pub trait Tuple {
+    /// The values of one row, e.g. `(i64, String)`.
+    type Values;
+
+    /// Get the full names of the columns, in order.
+    fn full_names(&self) -> Vec<String>;
+
+    /// Get the values of a row as parameters, bound by their columns.
+    fn bind<'a>(&self, values: &'a Self::Values) -> Vec<Param<'a>>;
+}
+
+macro_rules! impl_tuple {
+    ($(($($ty:ident $model:ident $idx:tt),+);)+) => {
+        $(
+            impl<$($ty: ToSql + Sync, $model),+> Tuple for ($(TypedColumn<$ty, $model>,)+) {
+                type Values = ($($ty,)+);
+
+                fn full_names(&self) -> Vec<String> {
+                    vec![$(self.$idx.full_name()),+]
+                }
+
+                fn bind<'a>(&self, values: &'a Self::Values) -> Vec<Param<'a>> {
+                    vec![$(self.$idx.bind(&values.$idx)),+]
+                }
+            }
+        )+
+    };
+}
+
+impl_tuple! {
+    (A MA 0);
+    (A MA 0, B MB 1);
+    (A MA 0, B MB 1, C MC 2);
+    (A MA 0, B MB 1, C MC 2, D MD 3);
+    (A MA 0, B MB 1, C MC 2, D MD 3, E ME 4);
+    (A MA 0, B MB 1, C MC 2, D MD 3, E ME 4, F MF 5);
+    (A MA 0, B MB 1, C MC 2, D MD 3, E ME 4, F MF 5, G MG 6);
+    (A MA 0, B MB 1, C MC 2, D MD 3, E ME 4, F MF 5, G MG 6, H MH 7);
 }
 
 impl<'a> Default for Where<'a> {
@@ -339,8 +976,9 @@ impl<'a> BitAnd for Where<'a> {
             return self;
         }
 
+        // Keep the order of the conditions (and their parameters)
         if let And(ref mut vec) = other {
-            vec.push(self);
+            vec.insert(0, self);
             return other;
         }
 
@@ -366,7 +1004,7 @@ impl<'a> BitOr for Where<'a> {
         // This prevents unnecessary nesting.
         if let Or(ref mut vec) = self {
             // If other is also OR append the whole vec.
-            if let And(ref mut other_vec) = other {
+            if let Or(ref mut other_vec) = other {
                 vec.append(other_vec);
             } else {
                 vec.push(other);
@@ -374,8 +1012,9 @@ impl<'a> BitOr for Where<'a> {
             return self;
         }
 
+        // Keep the order of the conditions (and their parameters)
         if let Or(ref mut vec) = other {
-            vec.push(self);
+            vec.insert(0, self);
             return other;
         }
 
@@ -410,19 +1049,24 @@ impl<'a> PushChunk<'a> for Where<'a> {
                 chunk.push_to_buffer(buffer);
             }
             Not(inner) => {
-                buffer.0.push_str("NOT (");
+                buffer.statement.push_str("NOT (");
+                inner.push_to_buffer(buffer);
+                buffer.statement.push(')');
+            }
+            Group(inner) => {
+                buffer.statement.push('(');
                 inner.push_to_buffer(buffer);
-                buffer.0.push(')');
+                buffer.statement.push(')');
             }
             And(vec) => {
-                buffer.0.push('(');
+                buffer.statement.push('(');
                 push_all_with_sep(vec, buffer, ") AND (");
-                buffer.0.push(')');
+                buffer.statement.push(')');
             }
             Or(vec) => {
-                buffer.0.push('(');
+                buffer.statement.push('(');
                 push_all_with_sep(vec, buffer, ") OR (");
-                buffer.0.push(')');
+                buffer.statement.push(')');
             }
             Empty => (),
         }
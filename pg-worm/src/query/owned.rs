@@ -0,0 +1,266 @@
+use std::{
+    future::{Future, IntoFuture},
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+};
+
+use tokio_postgres::types::ToSql;
+
+use super::{
+    exec_inspecting, number_placeholders, run_bound, scoped, Delete, Insert, OpenTransaction,
+    Param, Query, QueryOutcome, RowHook, Select, SomeSet, Union, Update,
+};
+use crate::{pool::with_retries, Error};
+
+/// A complete query which owns its parameters.
+///
+/// Unlike [`Query`], it doesn't borrow anything, so it can be built
+/// ahead of time, stored in a struct or moved to another task and
+/// executed later by calling `.await`.
+///
+/// It is created either from a raw statement with boxed parameters
+/// or by calling `.into_owned()` on a query or query builder, which
+/// copies its parameters. Only values of common types built into
+/// Postgres, like integers, strings, timestamps and arrays of them,
+/// can be copied, others have to be passed to [`OwnedQuery::new`].
+///
+/// # Example
+///
+/// ```ignore
+/// fn books_by(author_id: i64) -> OwnedQuery<Vec<Book>> {
+///     OwnedQuery::new("SELECT * FROM book WHERE author_id = ?", vec![Box::new(author_id)])
+/// }
+///
+/// let query = books_by(1);
+/// // ---- snip ----
+/// let books = query.await?;
+/// ```
+pub struct OwnedQuery<T = Vec<crate::Row>> {
+    statement: String,
+    params: Vec<Param<'static>>,
+    error: Option<Error>,
+    idempotent: bool,
+    inspect: Option<RowHook>,
+    transaction: Option<Arc<OpenTransaction>>,
+    output: PhantomData<T>,
+}
+
+impl<T> OwnedQuery<T> {
+    /// Create a new owned query by passing a raw statement as well as parameters.
+    ///
+    /// You can reference the `params` by using the `?` placeholder in your statement.
+    pub fn new(
+        statement: impl Into<String>,
+        params: Vec<Box<dyn ToSql + Sync + Send>>,
+    ) -> OwnedQuery<T> {
+        OwnedQuery {
+            statement: number_placeholders(&statement.into(), 0),
+            params: params.into_iter().map(Param::boxed).collect(),
+            error: None,
+            idempotent: false,
+            inspect: None,
+            transaction: None,
+            output: PhantomData::<T>,
+        }
+    }
+
+    /// Get the query's statement.
+    pub fn statement(&self) -> &str {
+        &self.statement
+    }
+
+    /// Mark this query as safe to run more than once,
+    /// which allows retrying it after a transient error.
+    ///
+    /// See [`Query::idempotent`].
+    pub fn idempotent(mut self) -> OwnedQuery<T> {
+        self.idempotent = true;
+        self
+    }
+}
+
+impl<T: QueryOutcome + Send> OwnedQuery<T> {
+    /// Execute the query using a client from the pool,
+    /// or the transaction it is bound to.
+    async fn exec(mut self) -> Result<T, Error> {
+        let transaction = self.transaction.take();
+
+        run_bound(transaction, self.exec_unbound()).await
+    }
+
+    /// Execute the query using a client from the pool
+    /// (or the transaction of the enclosing [`Transaction::scope`](super::Transaction::scope)).
+    async fn exec_unbound(self) -> Result<T, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let params = Param::values(&self.params);
+        let run = || exec_inspecting::<T>(&self.statement, &params, self.inspect);
+        if !self.idempotent || scoped() {
+            return run().await;
+        }
+
+        with_retries(run).await
+    }
+}
+
+impl<T> Query<'_, T> {
+    /// Turn this query into an [`OwnedQuery`] which can be
+    /// stored and executed later.
+    ///
+    /// The parameters are copied, see [`OwnedQuery`].
+    pub fn into_owned(self) -> OwnedQuery<T> {
+        let mut error = self.error;
+        let mut params = Vec::with_capacity(self.params.len());
+        for (index, param) in self.params.into_iter().enumerate() {
+            match param.into_static() {
+                Ok(param) => params.push(param),
+                Err(source) => {
+                    error.get_or_insert(Error::InvalidParameter {
+                        index,
+                        statement: self.statement.clone(),
+                        source,
+                    });
+                }
+            }
+        }
+
+        OwnedQuery {
+            statement: self.statement,
+            params,
+            error,
+            idempotent: self.idempotent,
+            inspect: self.inspect,
+            transaction: self.transaction,
+            output: PhantomData::<T>,
+        }
+    }
+}
+
+macro_rules! impl_into_owned {
+    ($([$($generic:ident),*] $builder:ty => $output:ty),+) => {
+        $(
+            impl<'a, $($generic),*> $builder {
+                /// Turn this query into an [`OwnedQuery`] which can be
+                /// stored and executed later.
+                ///
+                /// The parameters are copied, see [`OwnedQuery`].
+                pub fn into_owned(self) -> OwnedQuery<$output> {
+                    Query::from(self).into_owned()
+                }
+            }
+        )*
+    };
+}
+
+impl_into_owned!(
+    [T] Select<'a, T> => T,
+    [T] Union<'a, T> => T,
+    [M] Update<'a, SomeSet, u64, M> => u64,
+    [M] Insert<'a, SomeSet, u64, M> => u64,
+    [] Delete<'a> => u64
+);
+
+impl<T: Send + Sync + 'static> IntoFuture for OwnedQuery<T>
+where
+    T: QueryOutcome,
+{
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+    type Output = Result<T, crate::Error>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.exec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use bytes::BytesMut;
+    use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type};
+
+    use crate::prelude::*;
+    use crate::query::OwnedQuery;
+    use crate::Error;
+
+    #[derive(Model)]
+    struct Book {
+        id: i64,
+        title: String,
+    }
+
+    fn assert_static<T: Send + 'static>(_: &T) {}
+
+    #[test]
+    fn select_into_owned() {
+        let q = Book::select().where_(Book::id.gt(&3)).limit(2).into_owned();
+        assert_static(&q);
+
+        assert_eq!(
+            q.statement(),
+            "SELECT book.id, book.title FROM book WHERE book.id > $1 LIMIT $2"
+        );
+        assert_eq!(q.params.len(), 2);
+    }
+
+    #[test]
+    fn copies_borrowed_params() {
+        let q = {
+            let title = String::from("Foo");
+            Book::select().where_(Book::title.eq(&title)).into_owned()
+        };
+        assert_static(&q);
+
+        assert!(q.error.is_none());
+        assert_eq!(format!("{:?}", q.params), r#"["Foo"]"#);
+    }
+
+    #[test]
+    fn rejects_uncopyable_params() {
+        #[derive(Debug)]
+        struct Mood;
+
+        impl ToSql for Mood {
+            fn to_sql(
+                &self,
+                _: &Type,
+                out: &mut BytesMut,
+            ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                out.extend_from_slice(b"happy");
+                Ok(IsNull::No)
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                ty.name() == "mood"
+            }
+
+            to_sql_checked!();
+        }
+
+        let q: OwnedQuery<u64> = Book::delete()
+            .where_(Where::new("mood = ?".to_string(), vec![&Mood]))
+            .into_owned();
+        assert!(matches!(
+            q.error,
+            Some(Error::InvalidParameter { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn raw_owned() {
+        let title = String::from("Foo");
+        let q: OwnedQuery<Vec<Book>> = OwnedQuery::new(
+            "SELECT * FROM book WHERE title = ? AND id > ?",
+            vec![Box::new(title), Box::new(1i64)],
+        );
+        assert_static(&q);
+
+        assert_eq!(
+            q.statement(),
+            "SELECT * FROM book WHERE title = $1 AND id > $2"
+        );
+    }
+}
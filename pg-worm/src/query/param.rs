@@ -0,0 +1,271 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use bytes::{Bytes, BytesMut};
+use tokio_postgres::types::{to_sql_checked, Format, IsNull, ToSql, Type};
+
+use super::render::MASK;
+
+/// A parameter of a query.
+///
+/// It usually borrows the value, but may also own it, e.g. if the
+/// value is sent encoded (see `#[column(encode_with = "..")]`).
+///
+/// Parameters of columns marked with `#[column(sensitive)]` are
+/// flagged as such and never shown when the query is rendered
+/// or debugged, see [`Sensitive`](super::Sensitive).
+#[derive(Clone)]
+pub struct Param<'a> {
+    value: Value<'a>,
+    sensitive: bool,
+}
+
+#[derive(Clone)]
+enum Value<'a> {
+    Borrowed(&'a (dyn ToSql + Sync)),
+    Owned(Arc<dyn ToSql + Send + Sync + 'a>),
+}
+
+impl<'a> Param<'a> {
+    /// Use an owned value as a parameter.
+    pub fn owned(value: impl ToSql + Send + Sync + 'a) -> Param<'a> {
+        Param {
+            value: Value::Owned(Arc::new(value)),
+            sensitive: false,
+        }
+    }
+
+    /// Use a boxed value as a parameter, see [`OwnedQuery::new`](super::OwnedQuery::new).
+    pub(super) fn boxed(value: Box<dyn ToSql + Send + Sync + 'a>) -> Param<'a> {
+        Param {
+            value: Value::Owned(Arc::from(value)),
+            sensitive: false,
+        }
+    }
+
+    /// Use a parameter which can't be sent, so the query fails
+    /// with [`Error::InvalidParameter`](crate::Error::InvalidParameter).
+    pub(super) fn invalid(reason: String) -> Param<'static> {
+        Param::owned(Invalid(reason))
+    }
+
+    /// Flag this parameter as sensitive, so it is rendered as `***`.
+    pub fn sensitive(mut self) -> Param<'a> {
+        self.sensitive = true;
+        self
+    }
+
+    /// Whether this parameter is rendered as `***`.
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Get the value which is sent to the database.
+    pub fn value(&self) -> &(dyn ToSql + Sync) {
+        match &self.value {
+            Value::Borrowed(value) => *value,
+            Value::Owned(value) => value.as_ref(),
+        }
+    }
+
+    /// Copy the value, so the parameter no longer borrows anything,
+    /// see [`OwnedQuery`](super::OwnedQuery).
+    ///
+    /// The value is encoded for each of the [`COPYABLE_TYPES`] which it
+    /// accepts, as there is no way to clone a `dyn ToSql`. So values
+    /// of other types, like enums, can't be copied.
+    pub(super) fn into_static(self) -> Result<Param<'static>, Box<dyn Error + Sync + Send>> {
+        let value = self.value();
+        let mut encodings = Vec::new();
+        let mut error = None;
+        for ty in COPYABLE_TYPES {
+            let mut buf = BytesMut::new();
+            match value.to_sql_checked(ty, &mut buf) {
+                Ok(IsNull::No) => encodings.push((ty, value.encode_format(ty), Some(buf.freeze()))),
+                Ok(IsNull::Yes) => encodings.push((ty, value.encode_format(ty), None)),
+                Err(err) if !err.is::<tokio_postgres::types::WrongType>() => {
+                    error.get_or_insert(err);
+                }
+                Err(_) => (),
+            }
+        }
+
+        if encodings.is_empty() {
+            return Err(error.unwrap_or_else(|| {
+                format!(
+                    "`{value:?}` isn't of a common type built into Postgres, so \
+                    it can't be copied; pass it to `OwnedQuery::new` instead"
+                )
+                .into()
+            }));
+        }
+
+        Ok(Param {
+            value: Value::Owned(Arc::new(Copied {
+                encodings,
+                debug: format!("{value:?}"),
+            })),
+            sensitive: self.sensitive,
+        })
+    }
+
+    /// Get the values of some parameters, e.g. to pass them to `tokio_postgres`.
+    #[doc(hidden)]
+    pub fn values<'b>(params: &'b [Param<'_>]) -> Vec<&'b (dyn ToSql + Sync)> {
+        params.iter().map(Param::value).collect()
+    }
+}
+
+impl<'a> From<&'a (dyn ToSql + Sync)> for Param<'a> {
+    fn from(value: &'a (dyn ToSql + Sync)) -> Param<'a> {
+        Param {
+            value: Value::Borrowed(value),
+            sensitive: false,
+        }
+    }
+}
+
+impl<'a, T: ToSql + Sync> From<&'a T> for Param<'a> {
+    fn from(value: &'a T) -> Param<'a> {
+        Param {
+            value: Value::Borrowed(value),
+            sensitive: false,
+        }
+    }
+}
+
+/// The types a parameter is encoded for when it is copied, see
+/// [`Param::into_static`], i.e. those `ToSql` is implemented for
+/// by `tokio_postgres` and pg-worm's features, and arrays of them.
+const COPYABLE_TYPES: &[Type] = &[
+    Type::BOOL,
+    Type::CHAR,
+    Type::INT2,
+    Type::INT4,
+    Type::INT8,
+    Type::OID,
+    Type::FLOAT4,
+    Type::FLOAT8,
+    Type::NUMERIC,
+    Type::MONEY,
+    Type::TEXT,
+    Type::VARCHAR,
+    Type::BPCHAR,
+    Type::NAME,
+    Type::UNKNOWN,
+    Type::BYTEA,
+    Type::JSON,
+    Type::JSONB,
+    Type::UUID,
+    Type::DATE,
+    Type::TIME,
+    Type::TIMESTAMP,
+    Type::TIMESTAMPTZ,
+    Type::INTERVAL,
+    Type::INET,
+    Type::CIDR,
+    Type::MACADDR,
+    Type::BOOL_ARRAY,
+    Type::CHAR_ARRAY,
+    Type::INT2_ARRAY,
+    Type::INT4_ARRAY,
+    Type::INT8_ARRAY,
+    Type::OID_ARRAY,
+    Type::FLOAT4_ARRAY,
+    Type::FLOAT8_ARRAY,
+    Type::NUMERIC_ARRAY,
+    Type::MONEY_ARRAY,
+    Type::TEXT_ARRAY,
+    Type::VARCHAR_ARRAY,
+    Type::BPCHAR_ARRAY,
+    Type::NAME_ARRAY,
+    Type::BYTEA_ARRAY,
+    Type::JSON_ARRAY,
+    Type::JSONB_ARRAY,
+    Type::UUID_ARRAY,
+    Type::DATE_ARRAY,
+    Type::TIME_ARRAY,
+    Type::TIMESTAMP_ARRAY,
+    Type::TIMESTAMPTZ_ARRAY,
+    Type::INTERVAL_ARRAY,
+    Type::INET_ARRAY,
+    Type::CIDR_ARRAY,
+    Type::MACADDR_ARRAY,
+];
+
+/// A value copied by [`Param::into_static`], encoded for each type it accepts.
+struct Copied {
+    encodings: Vec<(&'static Type, Format, Option<Bytes>)>,
+    debug: String,
+}
+
+impl Copied {
+    fn encoding(&self, ty: &Type) -> Option<&(&'static Type, Format, Option<Bytes>)> {
+        self.encodings.iter().find(|(i, ..)| *i == ty)
+    }
+}
+
+impl ToSql for Copied {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        match self.encoding(ty) {
+            Some((_, _, Some(value))) => {
+                out.extend_from_slice(value);
+                Ok(IsNull::No)
+            }
+            Some((_, _, None)) => Ok(IsNull::Yes),
+            None => Err(format!("`{}` can't be sent as `{ty}`", self.debug).into()),
+        }
+    }
+
+    // The type is checked by `to_sql`
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    fn encode_format(&self, ty: &Type) -> Format {
+        self.encoding(ty)
+            .map_or(Format::Binary, |(_, format, _)| *format)
+    }
+
+    to_sql_checked!();
+}
+
+impl fmt::Debug for Copied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.debug)
+    }
+}
+
+/// A parameter which can't be sent, see [`Param::invalid`].
+struct Invalid(String);
+
+impl ToSql for Invalid {
+    fn to_sql(&self, _: &Type, _: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        Err(self.0.clone().into())
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+impl fmt::Debug for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("?")
+    }
+}
+
+impl fmt::Debug for Param<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sensitive {
+            return f.write_str(MASK);
+        }
+
+        self.value().fmt(f)
+    }
+}
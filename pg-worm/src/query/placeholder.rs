@@ -0,0 +1,220 @@
+use std::fmt::Write;
+
+/// A placeholder of a statement, see [`scan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    /// A `?`, which refers to the next parameter.
+    Next,
+    /// A `$n`, which refers to the `n`-th parameter.
+    Numbered(usize),
+}
+
+/// Number the placeholders of a chunk which is placed after
+/// `offset` parameters, see [`push_numbered`].
+pub(super) fn number_placeholders(stmt: &str, offset: usize) -> String {
+    // Since we change '?' to e.g. '$1' we need to
+    // reserve some more space to avoid reallocating.
+    const RESERVED: usize = 9;
+    let mut buf = String::with_capacity(stmt.len() + RESERVED);
+    push_numbered(&mut buf, stmt, offset);
+
+    buf
+}
+
+/// Push a chunk to a statement, numbering its placeholders
+/// as if `offset` parameters came before it.
+///
+/// Raw chunks use `?`, which are numbered starting at `$offset + 1`.
+/// Chunks built from a whole query are numbered already,
+/// so their placeholders are shifted by `offset`.
+/// If a chunk uses both, the `?`s take the numbers
+/// which aren't referenced by a `$n`.
+///
+/// Placeholders inside string literals, quoted identifiers
+/// and dollar-quoted blocks are left alone.
+pub(super) fn push_numbered(buf: &mut String, stmt: &str, offset: usize) {
+    let mut taken = Vec::new();
+    if stmt.contains('$') {
+        scan(stmt, |_, placeholder| {
+            if let Some(Placeholder::Numbered(n)) = placeholder {
+                taken.push(n);
+            }
+        });
+    }
+
+    let mut count = 0;
+    scan(stmt, |text, placeholder| {
+        buf.push_str(text);
+
+        let n = match placeholder {
+            Some(Placeholder::Next) => {
+                count += 1;
+                while taken.contains(&count) {
+                    count += 1;
+                }
+                count
+            }
+            Some(Placeholder::Numbered(n)) => n,
+            None => return,
+        };
+        // Writing to a `String` can't fail
+        let _ = write!(buf, "${}", n + offset);
+    });
+}
+
+/// Split a statement into the text in front of each placeholder
+/// and the placeholder itself. The text after the last one
+/// is passed without a placeholder.
+fn scan<'s>(stmt: &'s str, mut f: impl FnMut(&'s str, Option<Placeholder>)) {
+    let bytes = stmt.as_bytes();
+    // The start of the text which hasn't been passed yet
+    let mut start = 0;
+    let mut i = 0;
+
+    while let Some(&byte) = bytes.get(i) {
+        match byte {
+            b'?' => {
+                f(&stmt[start..i], Some(Placeholder::Next));
+                i += 1;
+                start = i;
+            }
+            b'\'' => {
+                // Backslashes only escape in `E'...'` strings
+                let escapes = i > 0
+                    && bytes[i - 1].eq_ignore_ascii_case(&b'e')
+                    && (i < 2 || !is_ident_byte(bytes[i - 2]));
+                i += quoted_len(&bytes[i..], b'\'', escapes);
+            }
+            b'"' => i += quoted_len(&bytes[i..], b'"', false),
+            b'$' => {
+                let digits = bytes[i + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                // Only `$` followed by a number is a placeholder
+                if let Ok(n) = stmt[i + 1..i + 1 + digits].parse() {
+                    f(&stmt[start..i], Some(Placeholder::Numbered(n)));
+                    i += 1 + digits;
+                    start = i;
+                } else if i > 0 && is_ident_byte(bytes[i - 1]) {
+                    // Part of an identifier like `a$b`
+                    i += 1;
+                } else {
+                    i += dollar_quoted_len(&stmt[i..]);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    f(&stmt[start..], None);
+}
+
+/// Whether a byte may be part of an identifier.
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$'
+}
+
+/// The length of the quoted text at the start of `bytes`, including
+/// the quotes. Doubled quotes are part of the text. If the text
+/// isn't closed, it reaches until the end of the statement.
+fn quoted_len(bytes: &[u8], quote: u8, escapes: bool) -> usize {
+    let mut i = 1;
+
+    while let Some(&byte) = bytes.get(i) {
+        match byte {
+            b'\\' if escapes => i += 2,
+            b if b == quote && bytes.get(i + 1) == Some(&quote) => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// The length of the dollar-quoted block, like `$tag$...$tag$`,
+/// at the start of `stmt`. It is `1` if `stmt` doesn't start with
+/// such a block, i.e. only the `$` is skipped.
+fn dollar_quoted_len(stmt: &str) -> usize {
+    let bytes = stmt.as_bytes();
+    let tag_len = bytes[1..]
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+        .count();
+
+    if bytes.get(tag_len + 1) != Some(&b'$') {
+        return 1;
+    }
+
+    let tag = &stmt[..tag_len + 2];
+    match stmt[tag.len()..].find(tag) {
+        Some(end) => 2 * tag.len() + end,
+        None => stmt.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbering() {
+        assert_eq!(
+            number_placeholders("a = ? AND b = ?", 0),
+            "a = $1 AND b = $2"
+        );
+        assert_eq!(
+            number_placeholders("a = ? AND b = $1", 2),
+            "a = $4 AND b = $3"
+        );
+        assert_eq!(number_placeholders("a$b = ?", 0), "a$b = $1");
+        assert_eq!(number_placeholders("a = '$1'", 3), "a = '$1'");
+    }
+
+    #[test]
+    fn mixed_placeholders() {
+        assert_eq!(
+            number_placeholders("a = $1 AND b = ? AND c = ?", 0),
+            "a = $1 AND b = $2 AND c = $3"
+        );
+        assert_eq!(
+            number_placeholders("a = ? AND b = $1 AND c = ?", 0),
+            "a = $2 AND b = $1 AND c = $3"
+        );
+        assert_eq!(
+            number_placeholders("a = $2 OR b = ?", 1),
+            "a = $3 OR b = $2"
+        );
+    }
+
+    #[test]
+    fn skip_quoted() {
+        assert_eq!(
+            number_placeholders("note = 'why?' AND id = ?", 0),
+            "note = 'why?' AND id = $1"
+        );
+        assert_eq!(
+            number_placeholders("note = 'it''s ?' AND id = ?", 0),
+            "note = 'it''s ?' AND id = $1"
+        );
+        assert_eq!(
+            number_placeholders(r"note = E'\'?' AND id = ?", 0),
+            r"note = E'\'?' AND id = $1"
+        );
+        assert_eq!(number_placeholders(r#""what?" = ?"#, 0), r#""what?" = $1"#);
+        assert_eq!(
+            number_placeholders("body = $$ ? $1 $$ AND id = ?", 0),
+            "body = $$ ? $1 $$ AND id = $1"
+        );
+        assert_eq!(
+            number_placeholders("body = $fn$ 'x' ? $fn$ AND id = ?", 0),
+            "body = $fn$ 'x' ? $fn$ AND id = $1"
+        );
+        // Unterminated literals reach until the end
+        assert_eq!(
+            number_placeholders("a = ? AND b = 'x?", 0),
+            "a = $1 AND b = 'x?"
+        );
+    }
+}
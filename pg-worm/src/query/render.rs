@@ -0,0 +1,108 @@
+use bytes::BytesMut;
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+use super::Param;
+
+/// How the values of sensitive columns are rendered.
+pub(super) const MASK: &str = "***";
+
+/// Marks values which are sent to the database as is,
+/// but never shown when the query is rendered or debugged.
+///
+/// Parameters of columns marked with `#[column(sensitive)]`
+/// are marked automatically. Use [`Sensitive::wrap`] for
+/// the parameters of raw queries.
+///
+/// ```ignore
+/// let query = Query::<u64>::with_params(
+///     "UPDATE account SET token = ? WHERE id = ?".to_string(),
+///     vec![Sensitive::wrap(&token), Param::from(&id)],
+/// );
+/// assert!(query.describe().contains("***"));
+/// ```
+pub struct Sensitive;
+
+impl Sensitive {
+    /// Use a value as a parameter which is rendered as `***`.
+    pub fn wrap<T: ToSql + Sync>(value: &T) -> Param<'_> {
+        Param::from(value).sensitive()
+    }
+}
+
+/// Render a parameter like it would be written in SQL, e.g. for logging.
+///
+/// `NULL`, booleans, integers, floats and strings are rendered as
+/// literals, other values using their `Debug` implementation.
+/// It doesn't know whether a value belongs to a sensitive column,
+/// use [`Query::describe`](super::Query::describe) to hide those.
+///
+/// ```
+/// use pg_worm::query::format_sql_value;
+///
+/// assert_eq!(format_sql_value(&"it's"), "'it''s'");
+/// assert_eq!(format_sql_value(&Some(5_i64)), "5");
+/// assert_eq!(format_sql_value(&None::<bool>), "NULL");
+/// ```
+pub fn format_sql_value(value: &(dyn ToSql + Sync)) -> String {
+    render_as(value, Type::BOOL, |b: bool| b.to_string().to_uppercase())
+        .or_else(|| render_as(value, Type::INT2, |n: i16| n.to_string()))
+        .or_else(|| render_as(value, Type::INT4, |n: i32| n.to_string()))
+        .or_else(|| render_as(value, Type::INT8, |n: i64| n.to_string()))
+        .or_else(|| render_as(value, Type::FLOAT4, |n: f32| n.to_string()))
+        .or_else(|| render_as(value, Type::FLOAT8, |n: f64| n.to_string()))
+        .or_else(|| {
+            render_as(value, Type::TEXT, |s: String| {
+                format!("'{}'", s.replace('\'', "''"))
+            })
+        })
+        .unwrap_or_else(|| format!("{value:?}"))
+}
+
+/// Render a value if it can be sent as `ty`, by
+/// reading it back as `T`.
+fn render_as<T: for<'b> FromSql<'b>>(
+    value: &(dyn ToSql + Sync),
+    ty: Type,
+    render: impl FnOnce(T) -> String,
+) -> Option<String> {
+    let mut buf = BytesMut::new();
+
+    match value.to_sql_checked(&ty, &mut buf).ok()? {
+        IsNull::Yes => Some("NULL".to_string()),
+        IsNull::No => T::from_sql(&ty, &buf).ok().map(render),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_values() {
+        assert_eq!(format_sql_value(&true), "TRUE");
+        assert_eq!(format_sql_value(&3_i16), "3");
+        assert_eq!(format_sql_value(&-7_i32), "-7");
+        assert_eq!(format_sql_value(&1.5_f64), "1.5");
+        assert_eq!(format_sql_value(&"Foo".to_string()), "'Foo'");
+        assert_eq!(format_sql_value(&None::<String>), "NULL");
+        assert_eq!(format_sql_value(&vec![1_i64, 2]), "[1, 2]");
+    }
+
+    #[test]
+    fn mask_sensitive() {
+        let password = "hunter2".to_string();
+        let param = Sensitive::wrap(&password);
+
+        assert!(param.is_sensitive());
+        assert_eq!(format!("{param:?}"), "***");
+
+        // Only the flag hides a value, not the way it looks
+        let stars = MASK.to_string();
+        assert!(!Param::from(&stars).is_sensitive());
+        assert_eq!(format_sql_value(&stars), "'***'");
+
+        let mut buf = BytesMut::new();
+        param.value().to_sql_checked(&Type::TEXT, &mut buf).unwrap();
+        assert_eq!(&buf[..], b"hunter2");
+    }
+}
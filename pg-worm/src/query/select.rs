@@ -3,37 +3,181 @@ use std::{
     marker::PhantomData,
     ops::Deref,
     pin::Pin,
+    sync::Arc,
 };
 
-use tokio_postgres::{types::ToSql, Row};
+use tokio_postgres::types::ToSql;
 
-use super::{replace_question_marks, PushChunk, Query, QueryOutcome, Where};
-use crate::Column;
+use super::{
+    push_all_with_sep, push_numbered, ExecResult, Keyset, KeysetPage, OpenTransaction, Order,
+    Param, PushChunk, Query, QueryOutcome, QueryTiming, SqlChunk, Union, Where, With,
+};
+use crate::{Column, Error, Row};
 
 /// A struct which holds the information needed to build
 /// a `SELECT` query.
 pub struct Select<'a, T = Vec<Row>> {
+    with: With<'a>,
     cols: Vec<Column>,
+    aliases: Vec<(Column, String)>,
+    qualify_aliases: bool,
     from: &'static str,
+    /// The statement up to the `FROM` clause, if it was built beforehand.
+    prefix: Option<&'static str>,
+    /// The alias of the table the columns are selected from.
+    from_alias: Option<&'static str>,
+    joins: Vec<(String, Where<'a>)>,
     where_: Where<'a>,
+    order_by: Vec<SqlChunk<'a>>,
+    /// The model's default ordering, which is used
+    /// unless the rows are ordered explicitly.
+    default_order: Vec<(Column, Order)>,
     marker: PhantomData<T>,
     limit: Option<u64>,
     offset: Option<u64>,
+    /// Whether to fail if more than one row matches.
+    strict: bool,
+    /// Whether to fail if no row matches.
+    required: bool,
+    /// The transaction the query is executed in,
+    /// see [`Transaction::select`](super::Transaction::select).
+    transaction: Option<Arc<OpenTransaction>>,
+}
+
+impl<'a, T> Clone for Select<'a, T> {
+    fn clone(&self) -> Self {
+        Select {
+            with: self.with.clone(),
+            cols: self.cols.clone(),
+            aliases: self.aliases.clone(),
+            qualify_aliases: self.qualify_aliases,
+            from: self.from,
+            prefix: self.prefix,
+            from_alias: self.from_alias,
+            joins: self.joins.clone(),
+            where_: self.where_.clone(),
+            order_by: self.order_by.clone(),
+            default_order: self.default_order.clone(),
+            marker: PhantomData::<T>,
+            limit: self.limit,
+            offset: self.offset,
+            strict: self.strict,
+            required: self.required,
+            transaction: self.transaction.clone(),
+        }
+    }
 }
 
 impl<'a, T> Select<'a, T> {
     #[doc(hidden)]
-    pub fn new(cols: &[&dyn Deref<Target = Column>], from: &'static str) -> Select<'a, T> {
+    pub fn new(cols: &[Column], from: &'static str) -> Select<'a, T> {
         Select {
-            cols: cols.iter().map(|i| (***i)).collect(),
+            with: With::default(),
+            cols: cols.to_vec(),
+            aliases: Vec::new(),
+            qualify_aliases: false,
             from,
+            prefix: None,
+            from_alias: None,
+            joins: Vec::new(),
             where_: Where::Empty,
+            order_by: Vec::new(),
+            default_order: Vec::new(),
             marker: PhantomData::<T>,
             limit: None,
             offset: None,
+            strict: false,
+            required: false,
+            transaction: None,
         }
     }
 
+    /// Use a prebuilt `SELECT {columns} FROM {table}`, which has
+    /// to match the columns and the table this query was created with.
+    ///
+    /// It is ignored if the columns are aliased.
+    #[doc(hidden)]
+    pub fn with_prefix(mut self, prefix: &'static str) -> Select<'a, T> {
+        self.prefix = Some(prefix);
+
+        self
+    }
+
+    /// Order the rows by the model's default ordering,
+    /// e.g. `book.id DESC`, unless another ordering is set.
+    #[doc(hidden)]
+    pub fn default_order_by(mut self, order: &[(Column, Order)]) -> Select<'a, T> {
+        self.default_order = order.to_vec();
+
+        self
+    }
+
+    /// Remove the model's default ordering set
+    /// using `#[table(order_by)]`, if it is used.
+    pub(super) fn without_default_order(mut self) -> Select<'a, T> {
+        self.default_order.clear();
+
+        self
+    }
+
+    /// Whether this query only reads data, i.e. it doesn't
+    /// have a common table expression with a raw body.
+    pub(super) fn is_plain(&self) -> bool {
+        self.with.is_plain()
+    }
+
+    /// Don't order the rows, removing the model's default
+    /// ordering and any ordering added before.
+    pub fn unordered(mut self) -> Select<'a, T> {
+        self.order_by.clear();
+        self.default_order.clear();
+
+        self
+    }
+
+    /// Give the table the columns are selected from an alias,
+    /// i.e. `FROM book AS b`.
+    ///
+    /// The selected columns are qualified using the alias.
+    /// Refer to them in conditions using [`TypedColumn::aliased`](crate::query::TypedColumn::aliased).
+    /// This is needed for joining a table to itself, see [`Select::join`].
+    pub fn from_alias(mut self, alias: &'static str) -> Select<'a, T> {
+        self.from_alias = Some(alias);
+
+        self
+    }
+
+    /// Join another table under an alias, i.e. `JOIN {table} AS {alias} ON {on}`.
+    ///
+    /// Its columns aren't selected, but they can be used in conditions
+    /// using [`TypedColumn::aliased`](crate::query::TypedColumn::aliased).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Books whose sequel is called "Dune Messiah"
+    /// let books = Book::select()
+    ///     .from_alias("b")
+    ///     .join(
+    ///         Book::QUALIFIED_TABLE,
+    ///         "s",
+    ///         Book::id.aliased("s").eq_column(Book::sequel_id.aliased("b")),
+    ///     )
+    ///     .where_(Book::title.aliased("s").eq(&"Dune Messiah".into()))
+    ///     .await?;
+    /// ```
+    pub fn join(
+        mut self,
+        table: &'static str,
+        alias: &'static str,
+        on: Where<'a>,
+    ) -> Select<'a, T> {
+        self.joins
+            .push((format!(" JOIN {table} AS {alias} ON "), on));
+
+        self
+    }
+
     /// Add a `WHERE` clause to your query.
     ///
     /// If used multiple time, the conditions are joined
@@ -75,6 +219,258 @@ impl<'a, T> Select<'a, T> {
         self.where_(where_)
     }
 
+    /// Add a common table expression (`WITH name AS (...)`) to your query.
+    ///
+    /// If used multiple times, the CTEs are defined in the order
+    /// they were added, so later ones may reference earlier ones.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Book::select()
+    ///     .with("popular", Author::select().where_(Author::followers.gt(&1000)))
+    ///     .where_raw("book.author_id IN (SELECT id FROM popular)", vec![])
+    ///     .await?;
+    /// ```
+    pub fn with<U>(mut self, name: impl Into<String>, query: Select<'a, U>) -> Select<'a, T> {
+        if query.is_plain() {
+            self.with.push(name.into(), query.into(), false);
+        } else {
+            self.with.push_raw(name.into(), query.into(), false);
+        }
+
+        self
+    }
+
+    /// Add a common table expression with a raw body to your query.
+    ///
+    /// You can reference the `params` by using the `?` placeholder in your statement.
+    /// Since the body may modify data, the query isn't retried after
+    /// transient errors, unlike other `SELECT`s.
+    pub fn with_raw(
+        mut self,
+        name: impl Into<String>,
+        statement: impl Into<String>,
+        params: Vec<&'a (dyn ToSql + Sync)>,
+    ) -> Select<'a, T> {
+        self.with.push_raw(
+            name.into(),
+            SqlChunk(
+                statement.into(),
+                params.into_iter().map(Param::from).collect(),
+            ),
+            false,
+        );
+
+        self
+    }
+
+    /// Add a recursive common table expression to your query.
+    ///
+    /// This turns the query's `WITH` into `WITH RECURSIVE`.
+    /// You can reference the `params` by using the `?` placeholder in your statement.
+    /// Like [`Select::with_raw`], this keeps the query from being retried.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Book::select()
+    ///     .with_recursive(
+    ///         "subtree",
+    ///         "SELECT id FROM category WHERE id = ? \
+    ///         UNION ALL SELECT c.id FROM category c JOIN subtree s ON c.parent_id = s.id",
+    ///         vec![&root_id],
+    ///     )
+    ///     .where_raw("book.category_id IN (SELECT id FROM subtree)", vec![])
+    ///     .await?;
+    /// ```
+    pub fn with_recursive(
+        mut self,
+        name: impl Into<String>,
+        statement: impl Into<String>,
+        params: Vec<&'a (dyn ToSql + Sync)>,
+    ) -> Select<'a, T> {
+        self.with.push_raw(
+            name.into(),
+            SqlChunk(
+                statement.into(),
+                params.into_iter().map(Param::from).collect(),
+            ),
+            true,
+        );
+
+        self
+    }
+
+    /// Order the rows by a column.
+    ///
+    /// If called multiple times, the rows are ordered by
+    /// the columns in the order they were added.
+    /// This replaces the model's default ordering.
+    pub fn order_by(self, col: impl Deref<Target = Column>, order: Order) -> Select<'a, T> {
+        let mut select = self.without_default_order();
+        let col = select.requalify(&col);
+        select.order_by.push(SqlChunk(
+            format!("{} {}", col.full_name(), direction(order)),
+            vec![],
+        ));
+
+        select
+    }
+
+    /// Order the rows by a column given by its name,
+    /// e.g. from a query string.
+    ///
+    /// Only the columns selected by this query are accepted,
+    /// others are rejected with [`Error::UnknownColumn`].
+    ///
+    /// ```ignore
+    /// // ?sort=title:desc
+    /// let books = Book::select().order_by_name("title", Order::Desc)?.await?;
+    /// ```
+    pub fn order_by_name(self, name: &str, order: Order) -> Result<Select<'a, T>, Error> {
+        let col = self.column_by_name(name)?;
+
+        Ok(self.order_by(&col, order))
+    }
+
+    /// Filter the rows by whether a column, given by its name,
+    /// is equal to a value given as a string, e.g. from a query string.
+    ///
+    /// The column has to be selected by this query, otherwise
+    /// [`Error::UnknownColumn`] is returned. The value is checked
+    /// using the column's [`ColumnValueParser`](crate::query::ColumnValueParser)
+    /// and cast to the column's type by the database, without its
+    /// length or precision, so it is compared as given.
+    /// Columns using `encode_with` parse the value as their
+    /// field's type and compare it encoded.
+    /// If it is invalid, or the column's type has no parser,
+    /// an [`Error::Validation`] is returned.
+    ///
+    /// ```ignore
+    /// // ?author_id=3
+    /// let books = Book::select().filter_eq_by_name("author_id", &value)?.await?;
+    /// ```
+    pub fn filter_eq_by_name(self, name: &str, value: &str) -> Result<Select<'a, T>, Error> {
+        let col = self.column_by_name(name)?;
+        if let Some(encoded) = col.encode(value) {
+            let mut param = encoded.map_err(|err| Error::Validation {
+                column: col.column_name,
+                reason: format!("couldn't parse {value:?}: {err}"),
+            })?;
+            if col.sensitive() {
+                param = param.sensitive();
+            }
+
+            return Ok(self.where_(Where::with_params(
+                format!("{} = ?", col.full_name()),
+                vec![param],
+            )));
+        }
+
+        let reason = match col.parse(value) {
+            Some(Ok(())) => None,
+            Some(Err(err)) => Some(format!("couldn't parse {value:?}: {err}")),
+            None => Some("its type can't be parsed from a string".to_string()),
+        };
+        if let Some(reason) = reason {
+            return Err(Error::Validation {
+                column: col.column_name,
+                reason,
+            });
+        }
+
+        let mut param = Param::owned(value.to_string());
+        if col.sensitive() {
+            param = param.sensitive();
+        }
+
+        Ok(self.where_(Where::with_params(
+            format!(
+                "{} = CAST(?::text AS {})",
+                col.full_name(),
+                base_type(col.pg_type())
+            ),
+            vec![param],
+        )))
+    }
+
+    /// Find a selected column by its name.
+    fn column_by_name(&self, name: &str) -> Result<Column, Error> {
+        self.cols
+            .iter()
+            .find(|col| col.column_name.trim_matches('"') == name)
+            .map(|col| self.requalify(col))
+            .ok_or_else(|| Error::UnknownColumn(name.to_string()))
+    }
+
+    /// Qualify a column of the selected table using its alias, if it has one.
+    fn requalify(&self, col: &Column) -> Column {
+        let mut col = *col;
+        if let Some(alias) = self.from_alias.filter(|_| col.table_name == self.from) {
+            col.table_name = alias;
+        }
+
+        col
+    }
+
+    /// Order the rows by how similar a column's value is to `value`,
+    /// most similar first.
+    ///
+    /// Requires the `pg_trgm` extension, see [`crate::create_trgm_extension`].
+    #[cfg(feature = "trgm")]
+    pub fn order_by_similarity(
+        self,
+        col: impl Deref<Target = Column>,
+        value: &'a String,
+    ) -> Select<'a, T> {
+        let mut select = self.without_default_order();
+        let col = select.requalify(&col);
+        select.order_by.push(SqlChunk(
+            format!("similarity({}, ?) DESC", col.full_name()),
+            vec![Param::from(value)],
+        ));
+
+        select
+    }
+
+    /// Select a column under another name using `AS`.
+    ///
+    /// This takes precedence over [`Select::qualify_aliases`].
+    pub fn column_alias(
+        mut self,
+        col: impl Deref<Target = Column>,
+        alias: impl Into<String>,
+    ) -> Select<'a, T> {
+        self.aliases.push((*col, alias.into()));
+
+        self
+    }
+
+    /// Select every column as `{table}__{column}`, e.g. `book.id AS "book__id"`,
+    /// so that columns of different tables don't clash.
+    ///
+    /// Models try parsing these qualified names before the bare
+    /// column names, so several models can be parsed from one row.
+    pub fn qualify_aliases(mut self) -> Select<'a, T> {
+        self.qualify_aliases = true;
+
+        self
+    }
+
+    /// Get the name a column is selected as, if it is aliased.
+    fn alias(&self, col: &Column) -> Option<String> {
+        let alias = self.aliases.iter().rev().find(|(i, _)| {
+            i.table_name() == col.table_name() && i.column_name() == col.column_name()
+        });
+
+        match alias {
+            Some((_, alias)) => Some(alias.clone()),
+            None if self.qualify_aliases => Some(col.qualified_alias()),
+            None => None,
+        }
+    }
+
     /// Add a `LIMIT` to your query.
     pub fn limit(mut self, limit: u64) -> Select<'a, T> {
         self.limit = Some(limit);
@@ -88,49 +484,388 @@ impl<'a, T> Select<'a, T> {
 
         self
     }
-}
 
-impl<'a, T> From<Select<'a, T>> for Query<'a, T> {
-    fn from(mut from: Select<'a, T>) -> Self {
-        let mut buffer = Query::default();
+    /// Combine this query with another one using `UNION`,
+    /// removing duplicate rows.
+    ///
+    /// Both queries must select the same columns.
+    /// The rows are parsed into the output type of this query.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Book::select()
+    ///     .where_(Book::id.lt(&10))
+    ///     .union(BookArchive::select())
+    ///     .limit(10)
+    ///     .await?;
+    /// ```
+    pub fn union<U>(self, other: Select<'a, U>) -> Union<'a, T> {
+        Union::new(self).union(other)
+    }
+
+    /// Combine this query with another one using `UNION ALL`,
+    /// keeping duplicate rows.
+    ///
+    /// Both queries must select the same columns.
+    pub fn union_all<U>(self, other: Select<'a, U>) -> Union<'a, T> {
+        Union::new(self).union_all(other)
+    }
+
+    /// Also return the number of rows, see [`ExecResult`].
+    pub fn with_count(self) -> Select<'a, ExecResult<T>> {
+        self.cast()
+    }
 
-        buffer.0.push_str("SELECT ");
+    /// Also return how long executing the query took, see [`QueryTiming`].
+    pub fn with_timing(self) -> Select<'a, (T, QueryTiming)> {
+        self.cast()
+    }
+
+    /// Get the columns selected by this query.
+    pub(super) fn columns(&self) -> &[Column] {
+        &self.cols
+    }
+
+    /// Push `SELECT {columns} FROM {table}` to a buffer.
+    fn push_prefix<U>(&self, buffer: &mut Query<'a, U>) {
+        buffer.statement.push_str("SELECT ");
 
         // Push the selected columns
-        let cols = from
-            .cols
-            .iter()
-            .map(|i| i.full_name())
-            .collect::<Vec<_>>()
-            .join(", ");
-        buffer.0.push_str(&cols);
+        for (i, col) in self.cols.iter().enumerate() {
+            if i > 0 {
+                buffer.statement.push_str(", ");
+            }
+
+            let table = self.requalify(col).table_name;
+            buffer.statement.push_str(table);
+            buffer.statement.push('.');
+            buffer.statement.push_str(col.column_name);
+
+            // Aliases are always quoted, so they keep their case
+            if let Some(alias) = self.alias(col) {
+                buffer.statement.push_str(" AS \"");
+                buffer.statement.push_str(&alias.replace('"', "\"\""));
+                buffer.statement.push('"');
+            }
+        }
 
         // Push the table from which the columns
         // are selected
-        buffer.0.push_str(" FROM ");
-        buffer.0.push_str(from.from);
+        buffer.statement.push_str(" FROM ");
+        buffer.statement.push_str(self.from);
+        if let Some(alias) = self.from_alias {
+            buffer.statement.push_str(" AS ");
+            buffer.statement.push_str(alias);
+        }
+    }
+
+    /// Estimate the length of the statement, so the
+    /// buffer rarely needs to grow while it is built.
+    fn estimated_len(&self) -> usize {
+        // Room for the keywords, conditions and placeholders
+        const SLACK: usize = 64;
+        if let Some(prefix) = self.prefix {
+            return prefix.len() + SLACK;
+        }
+
+        let cols: usize = self
+            .cols
+            .iter()
+            .map(|col| col.table_name.len() + col.column_name.len() + 3)
+            .sum();
+
+        "SELECT  FROM ".len() + cols + self.from.len() + SLACK
+    }
+
+    /// Change the output type of this query.
+    pub(super) fn cast<U>(self) -> Select<'a, U> {
+        Select {
+            with: self.with,
+            cols: self.cols,
+            aliases: self.aliases,
+            qualify_aliases: self.qualify_aliases,
+            from: self.from,
+            prefix: self.prefix,
+            from_alias: self.from_alias,
+            joins: self.joins,
+            where_: self.where_,
+            order_by: self.order_by,
+            default_order: self.default_order,
+            marker: PhantomData::<U>,
+            limit: self.limit,
+            offset: self.offset,
+            strict: self.strict,
+            required: self.required,
+            transaction: self.transaction,
+        }
+    }
+
+    /// Execute this query as part of a transaction.
+    pub(super) fn bound_to(mut self, transaction: Arc<OpenTransaction>) -> Select<'a, T> {
+        self.transaction = Some(transaction);
+
+        self
+    }
+
+    /// Take the transaction this query is executed in, if any.
+    pub(super) fn take_transaction(&mut self) -> Option<Arc<OpenTransaction>> {
+        self.transaction.take()
+    }
+}
+
+impl<'a, T> Select<'a, Option<T>> {
+    /// Fail with [`Error::MoreThanOneRow`] if more than one row
+    /// matches, instead of returning the first one.
+    ///
+    /// This way a filter which is supposed to be unique is checked
+    /// to be so. Up to two rows are fetched, regardless of `limit`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let book = Book::select_one()
+    ///     .where_(Book::isbn.eq(&isbn))
+    ///     .strict()
+    ///     .await?;
+    /// ```
+    pub fn strict(mut self) -> Select<'a, Option<T>> {
+        self.strict = true;
+
+        self
+    }
+
+    /// Fail with [`Error::NotFound`] instead of
+    /// returning `None` if no row matches.
+    pub fn required(mut self) -> Select<'a, Option<T>> {
+        self.required = true;
+
+        self
+    }
+}
+
+impl<'a, T> Select<'a, Vec<T>> {
+    /// Only select the rows after the last one seen, for keyset pagination.
+    ///
+    /// Adds `WHERE {key} > ?` and orders the rows by the key,
+    /// before any other ordering. Unlike `OFFSET`, this stays fast
+    /// on large tables if the key is indexed. The key has to be unique,
+    /// otherwise rows sharing a value are skipped; several columns
+    /// are compared as a row, i.e. `(a, b) > (?, ?)`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut page = Book::select()
+    ///     .order_by(Book::id, Order::Asc)
+    ///     .limit(100)
+    ///     .page(|book| book.id)
+    ///     .await?;
+    ///
+    /// while let Some(last) = page.next_cursor {
+    ///     page = Book::select()
+    ///         .after(Book::id, &last)
+    ///         .limit(100)
+    ///         .page(|book| book.id)
+    ///         .await?;
+    /// }
+    ///
+    /// // Ordered by publication date, ties broken by id
+    /// Book::select().after((Book::published_at, Book::id), (&date, &id));
+    /// ```
+    pub fn after<K: Keyset<'a>>(self, key: K, last: K::Values) -> Select<'a, Vec<T>> {
+        let mut select = self.without_default_order();
+        let cols: Vec<_> = key.columns().iter().map(|col| col.full_name()).collect();
+        let params = key.params(last);
+
+        let condition = match cols.as_slice() {
+            [col] => format!("{col} > ?"),
+            _ => format!(
+                "({}) > ({})",
+                cols.join(", "),
+                vec!["?"; cols.len()].join(", ")
+            ),
+        };
+
+        let order = cols
+            .into_iter()
+            .map(|col| SqlChunk(format!("{col} ASC"), vec![]));
+        select.order_by.splice(0..0, order);
+
+        select.where_(Where::with_params(condition, params))
+    }
+
+    /// Execute the query and return its rows as a [`KeysetPage`],
+    /// taking the cursor for the next page from the last row.
+    ///
+    /// See [`Select::after`].
+    pub async fn page<C>(self, cursor: impl FnOnce(&T) -> C) -> Result<KeysetPage<T, C>, Error>
+    where
+        T: Sync + Send + 'a,
+        Vec<T>: QueryOutcome,
+    {
+        let limit = self.limit;
+        let items = self.await?;
+
+        Ok(KeysetPage::new(items, limit, cursor))
+    }
+}
+
+/// Check the number of rows returned by a strict `select_one`.
+fn at_most_one(rows: &[Row]) -> Result<(), Error> {
+    match rows.len() {
+        0 | 1 => Ok(()),
+        _ => Err(Error::MoreThanOneRow),
+    }
+}
+
+/// Check the number of rows returned by a strict and required `select_one`.
+fn exactly_one(rows: &[Row]) -> Result<(), Error> {
+    match rows.len() {
+        0 => Err(Error::NotFound),
+        1 => Ok(()),
+        _ => Err(Error::MoreThanOneRow),
+    }
+}
+
+/// Get the SQL keyword for the direction of an ordering.
+fn direction(order: Order) -> &'static str {
+    match order {
+        Order::Asc => "ASC",
+        Order::Desc => "DESC",
+    }
+}
+
+/// Check the number of rows returned by a required `select_one`.
+fn at_least_one(rows: &[Row]) -> Result<(), Error> {
+    match rows.len() {
+        0 => Err(Error::NotFound),
+        _ => Ok(()),
+    }
+}
+
+impl<'a, T> PushChunk<'a> for Select<'a, T> {
+    fn push_to_buffer<U>(&mut self, buffer: &mut Query<'a, U>) {
+        self.with.push_to_buffer(buffer);
+
+        let plain = self.aliases.is_empty() && !self.qualify_aliases && self.from_alias.is_none();
+        match self.prefix.filter(|_| plain) {
+            Some(prefix) => buffer.statement.push_str(prefix),
+            None => self.push_prefix(buffer),
+        }
+
+        for (join, on) in &mut self.joins {
+            buffer.statement.push_str(join);
+            if on.is_empty() {
+                buffer.statement.push_str("TRUE");
+            } else {
+                on.push_to_buffer(buffer);
+            }
+        }
 
         // If it exists, push the WHERE clause
-        if !from.where_.is_empty() {
-            buffer.0.push_str(" WHERE ");
-            from.where_.push_to_buffer(&mut buffer);
+        if !self.where_.is_empty() {
+            buffer.statement.push_str(" WHERE ");
+            self.where_.push_to_buffer(buffer);
+        }
+
+        // Fall back to the model's default ordering,
+        // using the table's alias if it has one
+        if self.order_by.is_empty() {
+            self.order_by = self
+                .default_order
+                .iter()
+                .map(|(col, order)| {
+                    let col = self.requalify(col);
+                    SqlChunk(format!("{} {}", col.full_name(), direction(*order)), vec![])
+                })
+                .collect();
         }
 
-        // If set, add a LIMIT
-        if let Some(limit) = from.limit {
-            buffer.0.push_str(" LIMIT ");
-            buffer.0.push_str(&limit.to_string());
+        // If set, add an ORDER BY
+        if !self.order_by.is_empty() {
+            buffer.statement.push_str(" ORDER BY ");
+            push_all_with_sep(&mut self.order_by, buffer, ", ");
         }
 
-        // If set, add an OFFSET
-        if let Some(offset) = from.offset {
-            buffer.0.push_str(" OFFSET ");
-            buffer.0.push_str(&offset.to_string())
+        push_limit_offset(buffer, self.limit, self.offset);
+    }
+}
+
+/// Add a `LIMIT` and an `OFFSET`, if set, sending their values as parameters.
+///
+/// This way queries which only differ in the page
+/// they fetch share one prepared statement.
+pub(super) fn push_limit_offset<T>(
+    buffer: &mut Query<'_, T>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+) {
+    for (clause, value) in [(" LIMIT ?", limit), (" OFFSET ?", offset)] {
+        if let Some(value) = value {
+            push_numbered(&mut buffer.statement, clause, buffer.params.len());
+            // Postgres' limits are BIGINTs
+            let value = i64::try_from(value).unwrap_or(i64::MAX);
+            buffer.params.push(Param::owned(value));
         }
+    }
+}
+
+/// Strip the length or precision from a type, e.g. `VARCHAR(3)`
+/// to `VARCHAR`, so that casting to it never truncates a value.
+fn base_type(pg_type: &str) -> String {
+    let mut base = String::with_capacity(pg_type.len());
+    let mut depth = 0;
+    for c in pg_type.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => base.push(c),
+            _ => {}
+        }
+    }
+
+    base
+}
+
+impl<'a, T> From<Select<'a, T>> for Query<'a, T> {
+    fn from(mut from: Select<'a, T>) -> Self {
+        let mut buffer = Query::with_capacity(from.estimated_len());
+
+        // The CTEs are prepended afterwards so that
+        // further ones can be added to the query.
+        let with = std::mem::take(&mut from.with);
+
+        // A second row is needed to tell whether there are several
+        if from.strict {
+            from.limit = Some(2);
+        }
+
+        let plain = with.is_plain();
+        from.push_to_buffer(&mut buffer);
+        with.prepend_to(&mut buffer);
+
+        buffer.inspect = match (from.strict, from.required) {
+            (true, true) => Some(exactly_one),
+            (true, false) => Some(at_most_one),
+            (false, true) => Some(at_least_one),
+            (false, false) => None,
+        };
+        buffer.transaction = from.transaction;
 
-        buffer.0 = replace_question_marks(buffer.0);
+        // Only plain selects are safe to retry
+        if plain {
+            buffer.idempotent()
+        } else {
+            buffer
+        }
+    }
+}
 
-        buffer
+/// Build the query from a copy of the builder, so it can be reused.
+impl<'a, T> From<&Select<'a, T>> for Query<'a, T> {
+    fn from(from: &Select<'a, T>) -> Self {
+        Query::from(from.clone())
     }
 }
 
@@ -139,12 +874,12 @@ where
     T: QueryOutcome,
     Query<'a, T>: From<Select<'a, T>>,
 {
-    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'a>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
     type Output = Result<T, crate::Error>;
 
     fn into_future(self) -> Self::IntoFuture {
         let query: Query<'_, T> = self.into();
-        Box::pin(async move { T::exec(&query.0, query.1.as_slice()).await })
+        Box::pin(query.exec())
     }
 }
 
@@ -152,7 +887,8 @@ where
 mod test {
     #![allow(dead_code)]
     use crate::prelude::*;
-    use crate::query::Query;
+    use crate::query::{Order, Query};
+    use crate::Error;
 
     #[derive(Model)]
     struct Book {
@@ -161,15 +897,395 @@ mod test {
         title: String,
     }
 
+    #[derive(Model)]
+    #[table(table_name = "essays", rename_all = "camelCase")]
+    struct Essay {
+        #[column(primary_key, auto)]
+        id: i64,
+        word_count: i64,
+        #[column(column_name = "heading")]
+        title: String,
+        #[column(skip)]
+        cached: bool,
+    }
+
+    #[derive(Model)]
+    struct Novel {
+        #[column(primary_key, auto)]
+        id: i64,
+        title: String,
+        sequel_id: i64,
+    }
+
+    #[test]
+    fn self_join() {
+        let title = "Dune Messiah".to_string();
+        let query: Query<'_, Vec<Novel>> = Novel::select()
+            .from_alias("b")
+            .join(
+                Novel::QUALIFIED_TABLE,
+                "s",
+                Novel::id
+                    .aliased("s")
+                    .eq_column(Novel::sequel_id.aliased("b")),
+            )
+            .where_(Novel::title.aliased("s").eq(&title))
+            .order_by(Novel::title.aliased("b"), Order::Asc)
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT b.id, b.title, b.sequel_id FROM novel AS b \
+            JOIN novel AS s ON s.id = b.sequel_id \
+            WHERE s.title = $1 ORDER BY b.title ASC"
+        );
+        assert_eq!(format!("{:?}", query.params()), "[\"Dune Messiah\"]");
+    }
+
+    #[test]
+    fn from_alias() {
+        let title = "Dune".to_string();
+        let query: Query<'_, Vec<Novel>> = Novel::select()
+            .from_alias("n")
+            .join("author", "a", Where::default())
+            .filter_eq_by_name("title", &title)
+            .unwrap()
+            .order_by_name("id", Order::Desc)
+            .unwrap()
+            .qualify_aliases()
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT n.id AS \"novel__id\", n.title AS \"novel__title\", n.sequel_id AS \"novel__sequel_id\" \
+            FROM novel AS n JOIN author AS a ON TRUE \
+            WHERE n.title = CAST($1::text AS text) ORDER BY n.id DESC"
+        );
+    }
+
+    #[test]
+    fn prebuilt_prefix() {
+        fn assert_same<T>(fast: Select<'_, T>, slow: Select<'_, T>) {
+            let fast: Query<'_, T> = fast.into();
+            let slow: Query<'_, T> = slow.into();
+            assert_eq!(fast.statement(), slow.statement());
+        }
+        fn slow<'a, T>() -> Select<'a, T> {
+            Select::new(Essay::columns(), Essay::table_name())
+        }
+
+        assert_same(Essay::select(), slow());
+        assert_same(Essay::select_one(), slow().limit(1));
+        assert_same(
+            Book::select(),
+            Select::new(Book::columns(), Book::table_name()),
+        );
+
+        let words = 100;
+        assert_same(
+            Essay::select()
+                .where_(Essay::word_count.gt(&words))
+                .order_by(Essay::title, Order::Asc)
+                .offset(3),
+            slow()
+                .where_(Essay::word_count.gt(&words))
+                .order_by(Essay::title, Order::Asc)
+                .offset(3),
+        );
+        assert_same(
+            Essay::select().with_raw("x", "SELECT 1", vec![]),
+            slow().with_raw("x", "SELECT 1", vec![]),
+        );
+
+        // Changing the selected columns falls back to building them
+        assert_same(
+            Essay::select().column_alias(Essay::title, "t"),
+            slow().column_alias(Essay::title, "t"),
+        );
+        assert_same(Essay::select().qualify_aliases(), slow().qualify_aliases());
+        assert_same(Essay::select().from_alias("e"), slow().from_alias("e"));
+
+        let query: Query<'_, _> = Essay::select().from_alias("e").into();
+        assert_eq!(
+            query.statement(),
+            "SELECT e.id, e.\"wordCount\", e.heading FROM essays AS e"
+        );
+    }
+
+    #[test]
+    fn column_list() {
+        assert_eq!(Book::COLUMN_LIST, Book::columns_sql());
+        assert_eq!(Book::QUALIFIED_TABLE, Book::table_name());
+
+        assert_eq!(
+            Essay::COLUMN_LIST,
+            "essays.id, essays.\"wordCount\", essays.heading"
+        );
+        assert_eq!(Essay::COLUMN_LIST, Essay::columns_sql());
+        assert_eq!(Essay::QUALIFIED_TABLE, Essay::table_name());
+
+        // The raw query selects the same columns as the builder
+        let raw: Query<'_, Vec<Essay>> = Essay::query(
+            format!(
+                "SELECT {} FROM {}",
+                Essay::COLUMN_LIST,
+                Essay::QUALIFIED_TABLE
+            ),
+            vec![],
+        );
+        let built: Query<'_, Vec<Essay>> = Essay::select().into();
+        assert_eq!(raw.statement(), built.statement());
+    }
+
+    #[test]
+    fn by_name() {
+        let query: Query<'_, Vec<Essay>> = Essay::select()
+            .order_by_name("wordCount", Order::Desc)
+            .unwrap()
+            .order_by_name("heading", Order::Asc)
+            .unwrap()
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT essays.id, essays.\"wordCount\", essays.heading FROM essays \
+            ORDER BY essays.\"wordCount\" DESC, essays.heading ASC"
+        );
+
+        let count = "500".to_string();
+        let query: Query<'_, Vec<Essay>> = Essay::select()
+            .filter_eq_by_name("wordCount", &count)
+            .unwrap()
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT essays.id, essays.\"wordCount\", essays.heading FROM essays \
+            WHERE essays.\"wordCount\" = CAST($1::text AS int8)"
+        );
+        assert_eq!(query.params().len(), 1);
+    }
+
+    #[test]
+    fn base_type() {
+        assert_eq!(super::base_type("VARCHAR(3)"), "VARCHAR");
+        assert_eq!(super::base_type("NUMERIC(10, 2)[]"), "NUMERIC[]");
+        assert_eq!(
+            super::base_type("TIMESTAMP(3) WITH TIME ZONE"),
+            "TIMESTAMP WITH TIME ZONE"
+        );
+        assert_eq!(super::base_type("int8"), "int8");
+    }
+
+    #[test]
+    fn by_name_rejected() {
+        // Only the selected columns' names are accepted
+        for name in ["word_count", "title", "cached", "id; DROP TABLE essays", ""] {
+            assert!(matches!(
+                Essay::select().order_by_name(name, Order::Asc),
+                Err(Error::UnknownColumn(n)) if n == name
+            ));
+        }
+
+        let value = "many".to_string();
+        let Err(Error::Validation { column, reason }) =
+            Essay::select().filter_eq_by_name("wordCount", &value)
+        else {
+            panic!("invalid value was accepted");
+        };
+        assert_eq!(column, "\"wordCount\"");
+        assert!(reason.contains("\"many\""), "{reason}");
+    }
+
+    #[derive(Model)]
+    #[table(order_by = "title desc, id")]
+    struct Memo {
+        id: i64,
+        title: String,
+    }
+
+    #[test]
+    fn default_order() {
+        let query: Query<'_, Vec<Memo>> = Memo::select().into();
+        assert_eq!(
+            query.statement(),
+            "SELECT memo.id, memo.title FROM memo ORDER BY memo.title DESC, memo.id ASC"
+        );
+
+        let query: Query<'_, Option<Memo>> = Memo::select_one().into();
+        assert_eq!(
+            query.statement(),
+            "SELECT memo.id, memo.title FROM memo ORDER BY memo.title DESC, memo.id ASC LIMIT $1"
+        );
+
+        // Explicit ordering replaces it
+        let query: Query<'_, Vec<Memo>> = Memo::select()
+            .order_by(Memo::id, Order::Desc)
+            .order_by(Memo::title, Order::Asc)
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT memo.id, memo.title FROM memo ORDER BY memo.id DESC, memo.title ASC"
+        );
+
+        let query: Query<'_, Vec<Memo>> = Memo::select().unordered().into();
+        assert_eq!(query.statement(), "SELECT memo.id, memo.title FROM memo");
+
+        // The branches of a union can't be ordered
+        let query: Query<'_, Vec<Memo>> = Memo::select().union(Memo::select()).into();
+        assert!(!query.statement().contains("ORDER BY"));
+    }
+
     #[test]
     fn select_limit() {
         let query: Query<'_, Vec<Book>> = Book::select().limit(3).into();
-        assert_eq!(query.0, "SELECT book.id, book.title FROM book LIMIT 3");
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id, book.title FROM book LIMIT $1"
+        );
+        assert_eq!(format!("{:?}", query.params()), "[3]");
     }
 
     #[test]
     fn select_offset() {
         let query: Query<'_, Vec<Book>> = Book::select().offset(4).into();
-        assert_eq!(query.0, "SELECT book.id, book.title FROM book OFFSET 4");
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id, book.title FROM book OFFSET $1"
+        );
+        assert_eq!(format!("{:?}", query.params()), "[4]");
+    }
+
+    #[test]
+    fn select_order_by() {
+        let query: Query<'_, Vec<Book>> = Book::select()
+            .where_(Book::id.gt(&3))
+            .order_by(Book::title, Order::Asc)
+            .order_by(Book::id, Order::Desc)
+            .limit(2)
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id, book.title FROM book WHERE book.id > $1 \
+            ORDER BY book.title ASC, book.id DESC LIMIT $2"
+        );
+    }
+
+    #[test]
+    fn select_aliases() {
+        let query: Query<'_, Vec<Book>> = Book::select().qualify_aliases().into();
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id AS \"book__id\", book.title AS \"book__title\" FROM book"
+        );
+
+        let query: Query<'_, Vec<Book>> = Book::select()
+            .qualify_aliases()
+            .column_alias(Book::title, "bookTitle")
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id AS \"book__id\", book.title AS \"bookTitle\" FROM book"
+        );
+    }
+
+    #[test]
+    fn map_statement() {
+        let query: Query<'_, Vec<Book>> = Book::select().where_(Book::id.eq(&1)).into();
+        assert!(query.idempotent);
+        let query = query.map_statement(|stmt| stmt + " FOR UPDATE");
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id, book.title FROM book WHERE book.id = $1 FOR UPDATE"
+        );
+        assert_eq!(query.params().len(), 1);
+        // Locking rows isn't safe to retry
+        assert!(!query.idempotent);
+    }
+
+    #[test]
+    fn idempotent() {
+        let plain: Query<'_, Vec<Book>> = Book::select()
+            .with("short", Book::select().limit(1))
+            .union(Book::select())
+            .into();
+        assert!(plain.idempotent);
+
+        // A raw CTE may modify data
+        let archive = || {
+            Book::select().with_raw(
+                "archived",
+                "DELETE FROM book WHERE id = 1 RETURNING *",
+                vec![],
+            )
+        };
+        let query: Query<'_, Vec<Book>> = archive().into();
+        assert!(!query.idempotent);
+        let query: Query<'_, Vec<Book>> = Book::select().union(archive()).into();
+        assert!(!query.idempotent);
+        let query: Query<'_, Vec<Book>> = Book::select().with("nested", archive()).into();
+        assert!(!query.idempotent);
+
+        let query: Query<'_, Vec<Book>> = Book::select().into();
+        assert!(query.idempotent);
+        let query = query.with_raw("moved", "DELETE FROM book RETURNING *", vec![]);
+        assert!(!query.idempotent);
+    }
+
+    #[test]
+    fn placeholders() {
+        let id = 1_i64;
+        let query: Query<'_, Vec<Book>> = Book::select()
+            .where_raw("book.title <> 'why?'", vec![])
+            .where_(Book::id.eq(&id))
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id, book.title FROM book WHERE (book.title <> 'why?') AND (book.id = $1)"
+        );
+
+        let ids = (1..=12_i64).collect::<Vec<_>>();
+        let query: Query<'_, Vec<Book>> = ids
+            .iter()
+            .fold(Book::select(), |q, id| q.where_(Book::id.gt(id)))
+            .into();
+        assert!(query
+            .statement()
+            .ends_with("(book.id > $11) AND (book.id > $12)"));
+        assert_eq!(query.params().len(), 12);
+
+        let title = "Dune".to_string();
+        let query = Book::query(
+            "SELECT * FROM book WHERE id = $1 AND title = ? AND id > $1",
+            vec![&id, &title],
+        );
+        assert_eq!(
+            query.statement(),
+            "SELECT * FROM book WHERE id = $1 AND title = $2 AND id > $1"
+        );
+    }
+
+    #[cfg(feature = "trgm")]
+    #[test]
+    fn select_order_by_similarity() {
+        let title = String::from("Dune");
+        let query: Query<'_, Vec<Book>> = Book::select()
+            .where_(Book::title.similar_to(&title, 0.3))
+            .order_by_similarity(Book::title, &title)
+            .order_by(Book::id, Order::Asc)
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT book.id, book.title FROM book \
+            WHERE book.title % $1 AND similarity(book.title, $2) > $3 \
+            ORDER BY similarity(book.title, $4) DESC, book.id ASC"
+        );
+        assert_eq!(query.params().len(), 4);
+
+        // The column is qualified using the table's alias
+        let query: Query<'_, Vec<Book>> = Book::select()
+            .from_alias("b")
+            .order_by_similarity(Book::title, &title)
+            .into();
+        assert_eq!(
+            query.statement(),
+            "SELECT b.id, b.title FROM book AS b ORDER BY similarity(b.title, $1) DESC"
+        );
     }
 }
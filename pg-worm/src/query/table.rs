@@ -1,11 +1,22 @@
+#[cfg(feature = "net")]
+use std::net::IpAddr;
 use std::{
+    any::Any,
+    fmt::{self, Display},
     marker::PhantomData,
     ops::{Deref, Not},
+    str::FromStr,
 };
 
-use tokio_postgres::types::ToSql;
+use bytes::BytesMut;
+#[cfg(feature = "time")]
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime};
+use tokio_postgres::types::{IsNull, ToSql, Type};
 
-use crate::query::Where;
+use crate::{
+    query::{Param, Sensitive, Where},
+    Error, Model,
+};
 
 /// A wrapper around the [`Column`] struct which includes
 /// the rust type of the field.
@@ -30,10 +41,88 @@ use crate::query::Where;
 ///
 /// ```
 ///
-#[derive(Clone, Copy, Debug)]
-pub struct TypedColumn<T: ToSql + Sync> {
+/// The columns generated for a model are tied to it by the second type
+/// parameter, so the builders of another model's queries don't accept them:
+///
+/// ```compile_fail
+/// use pg_worm::prelude::*;
+///
+/// #[derive(Model)]
+/// struct Book {
+///     title: String,
+/// }
+///
+/// #[derive(Model)]
+/// struct Author {
+///     title: String,
+/// }
+///
+/// let title = "Foo".to_string();
+/// let _ = Book::update().set(Author::title, &title);
+/// ```
+///
+/// Columns created using [`TypedColumn::new`] aren't tied to
+/// a model (i.e. it is `()`) and are accepted by all builders.
+/// Conditions aren't checked, since they may refer to joined tables.
+pub struct TypedColumn<T: ToSql + Sync, M = ()> {
     column: Column,
     rs_type: PhantomData<T>,
+    /// The model the column belongs to.
+    model: PhantomData<fn() -> M>,
+    /// Turns a value into the parameter sent to the database,
+    /// if the field uses `encode_with`.
+    encode: Option<fn(&T) -> Param<'_>>,
+    /// Like `encode`, but the parameter owns the value.
+    encode_owned: Option<fn(T) -> Param<'static>>,
+    /// Builds an array from its elements, so they can be encoded
+    /// like values of this column, see [`clone_elements`].
+    from_elements: Option<ElementsBuilder<T>>,
+}
+
+/// Marks the columns which may be used in queries on the table of
+/// model `M`: its own ones and columns which aren't tied to a model.
+///
+/// All columns may be used in queries which aren't tied to a model.
+#[doc(hidden)]
+pub trait ColumnOf<M> {}
+
+impl<M: Model<M>> ColumnOf<M> for M {}
+impl<M: Model<M>> ColumnOf<M> for () {}
+impl<M: Model<M>> ColumnOf<()> for M {}
+impl ColumnOf<()> for () {}
+
+/// Clone the elements of an array column using `encode_with`, so they
+/// can be encoded as an array, e.g. by [`TypedColumn::contains`].
+///
+/// The `Model` derive passes it to the column, which is why
+/// encoded `Vec` fields need their elements to be `Clone`.
+#[doc(hidden)]
+pub fn clone_elements<T: Clone + 'static>(values: &[&dyn Any]) -> Vec<T> {
+    values
+        .iter()
+        .filter_map(|i| i.downcast_ref::<T>())
+        .cloned()
+        .collect()
+}
+
+impl<T: ToSql + Sync, M> Clone for TypedColumn<T, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ToSql + Sync, M> Copy for TypedColumn<T, M> {}
+
+impl<T: ToSql + Sync, M> fmt::Debug for TypedColumn<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedColumn")
+            .field("column", &self.column)
+            .field("rs_type", &self.rs_type)
+            .field("encode", &self.encode)
+            .field("encode_owned", &self.encode_owned)
+            .field("from_elements", &self.from_elements)
+            .finish()
+    }
 }
 
 /// This type represents a column.
@@ -47,18 +136,52 @@ pub struct Column {
     pub column_name: &'static str,
     /// The name of the table this columnn belongs to.
     pub table_name: &'static str,
+    pg_type: &'static str,
     nullable: bool,
     unique: bool,
     primary_key: bool,
     generated: bool,
+    sensitive: bool,
+    parser: Option<ColumnValueParser>,
+    encoder: Option<ColumnValueEncoder>,
+    max_length: Option<usize>,
+    min_length: Option<usize>,
+    max_bytes: Option<usize>,
+    min_bytes: Option<usize>,
+    insert_expr: Option<&'static str>,
 }
 
+/// Checks whether a string is a valid value of a column,
+/// returning why it isn't otherwise.
 ///
+/// The `Model` derive sets it for columns of common types,
+/// see [`Select::filter_eq_by_name`](crate::query::Select::filter_eq_by_name).
+pub type ColumnValueParser = fn(&str) -> Result<(), String>;
+
+/// Parses a string value of a column using `encode_with` and encodes it.
+#[doc(hidden)]
+pub type ColumnValueEncoder = fn(&str) -> Result<Param<'static>, String>;
+
+/// Builds the value of an array column using `encode_with`
+/// from its elements, see [`clone_elements`].
+#[doc(hidden)]
+pub type ElementsBuilder<T> = fn(&[&dyn Any]) -> T;
+
+/// The [`ColumnValueParser`] of a type which implements `FromStr`.
+#[doc(hidden)]
+pub fn parse_value<T: FromStr>(value: &str) -> Result<(), String>
+where
+    T::Err: Display,
+{
+    value.parse::<T>().map(drop).map_err(|err| err.to_string())
+}
+
+/// Static information about a column.
 #[doc(hidden)]
 pub trait ColumnInfo {
-    ///
+    /// The name of the table this column belongs to.
     const TABLE_NAME: &'static str;
-    ///
+    /// The name of the column.
     const COLUMN_NAME: &'static str;
 }
 
@@ -66,7 +189,7 @@ macro_rules! impl_prop_typed_col {
     ($($prop:ident),+) => {
         $(
             /// Set this property so `true`.
-            pub const fn $prop(mut self) -> TypedColumn<T> {
+            pub const fn $prop(mut self) -> TypedColumn<T, M> {
                 self.column.$prop = true;
                 self
             }
@@ -85,87 +208,390 @@ macro_rules! impl_prop_col {
     };
 }
 
+macro_rules! impl_array_cmp {
+    ($($name:ident: $op:literal $quantifier:literal, $doc:literal;)+) => {
+        $(
+            #[doc = $doc]
+            ///
+            /// The values are bound as a single array parameter.
+            /// Like in Postgres, `ALL` is `true` for an empty array
+            /// and `ANY` is `false`.
+            ///
+            /// Values of columns using `encode_with` are encoded one by
+            /// one, and comparing them by order fails like [`gt`](Self::gt).
+            pub fn $name<'a>(&self, values: &'a Vec<T>) -> Where<'a> {
+                let ordered = !matches!($op, "=" | "<>");
+                self.array_cmp(::core::concat!($op, " ", $quantifier), values, ordered)
+            }
+        )*
+    };
+}
+
+macro_rules! impl_column_cmp {
+    ($($name:ident: $op:literal, $doc:literal;)+) => {
+        $(
+            #[doc = $doc]
+            pub fn $name<'a, M2>(&self, other: TypedColumn<T, M2>) -> Where<'a> {
+                Where::new(
+                    format!("{} {} {}", self.full_name(), $op, other.full_name()),
+                    vec![],
+                )
+            }
+        )*
+    };
+}
+
+impl<T: ToSql + Sync, M> TypedColumn<T, M> {
+    /// Get the untyped column, e.g. to list it along with
+    /// columns of other types.
+    ///
+    /// Unlike dereferencing, this can be used in constants.
+    pub const fn column(&self) -> Column {
+        self.column
+    }
+
+    /// Get a value of this column as a parameter,
+    /// encoded if the field uses `encode_with`.
+    pub fn bind<'a>(&self, value: &'a T) -> Param<'a> {
+        match self.encode {
+            Some(encode) => encode(value),
+            None => self.mask(value),
+        }
+    }
+
+    /// Get a value of this column as a parameter to compare it by order.
+    ///
+    /// Encoded values can't be compared by order, so the
+    /// query fails with [`Error::InvalidParameter`](crate::Error::InvalidParameter) instead.
+    fn bind_ordered<'a>(&self, value: &'a T) -> Param<'a> {
+        match self.encode {
+            Some(_) => self.unordered(),
+            None => self.mask(value),
+        }
+    }
+
+    /// Get a parameter failing the query, since the encoded
+    /// values of this column can't be compared by order.
+    fn unordered(&self) -> Param<'static> {
+        Param::invalid(format!(
+            "the encoded values of `{}` can't be compared by order",
+            self.full_name()
+        ))
+    }
+
+    /// Compare this column's value to an array of values using `op`,
+    /// e.g. `= ANY`, which compares them by order if `ordered`.
+    ///
+    /// Encoded values are bound one by one, since only
+    /// the type of an encoded value is known.
+    fn array_cmp<'a>(&self, op: &str, values: &'a Vec<T>, ordered: bool) -> Where<'a> {
+        let column = format!("{}.{}", self.table_name, self.column_name);
+        if self.encode.is_none() {
+            return Where::new(format!("{column} {op}(?)"), vec![values]);
+        }
+        if ordered {
+            return Where::with_params(format!("{column} {op}(?)"), vec![self.unordered()]);
+        }
+        if values.is_empty() {
+            return Where::new(format!("{column} {op}('{{}}')"), vec![]);
+        }
+
+        let placeholders = vec!["?"; values.len()].join(", ");
+        Where::with_params(
+            format!("{column} {op}(ARRAY[{placeholders}])"),
+            values.iter().map(|i| self.bind(i)).collect(),
+        )
+    }
+
+    /// Get a value of this column as a parameter without encoding it,
+    /// which is rendered as `***` if the column is `sensitive`.
+    fn mask<'a>(&self, value: &'a T) -> Param<'a> {
+        if self.column.sensitive {
+            return Sensitive::wrap(value);
+        }
+
+        Param::from(value)
+    }
+}
+
 impl<T: ToSql + Sync + Send + 'static> TypedColumn<T> {
     /// Creates anew `TypedColumn<T>`.
     pub const fn new(table_name: &'static str, column_name: &'static str) -> TypedColumn<T> {
         TypedColumn {
             column: Column::new(table_name, column_name),
             rs_type: PhantomData::<T>,
+            model: PhantomData,
+            encode: None,
+            encode_owned: None,
+            from_elements: None,
+        }
+    }
+
+    /// Tie this column to the model it belongs to.
+    #[doc(hidden)]
+    pub const fn of_model<M>(self) -> TypedColumn<T, M> {
+        TypedColumn {
+            column: self.column,
+            rs_type: PhantomData::<T>,
+            model: PhantomData,
+            encode: self.encode,
+            encode_owned: self.encode_owned,
+            from_elements: self.from_elements,
         }
     }
+}
+
+impl<T: ToSql + Sync + Send + 'static, M> TypedColumn<T, M> {
+    /// Encode values of this column when setting them,
+    /// see `#[column(encode_with = "..")]`.
+    #[doc(hidden)]
+    pub const fn encoded(
+        mut self,
+        encode: fn(&T) -> Param<'_>,
+        encode_owned: fn(T) -> Param<'static>,
+    ) -> TypedColumn<T, M> {
+        self.encode = Some(encode);
+        self.encode_owned = Some(encode_owned);
+        self
+    }
+
+    /// Set the function building an array from its elements, so
+    /// filters like `contains` can encode them, see [`clone_elements`].
+    #[doc(hidden)]
+    pub const fn with_elements(mut self, from_elements: ElementsBuilder<T>) -> TypedColumn<T, M> {
+        self.from_elements = Some(from_elements);
+        self
+    }
 
-    impl_prop_typed_col!(nullable, unique, primary_key, generated);
+    impl_prop_typed_col!(nullable, unique, primary_key, generated, sensitive);
+
+    /// Set the function checking string values of this column.
+    #[doc(hidden)]
+    pub const fn with_parser(mut self, parser: ColumnValueParser) -> TypedColumn<T, M> {
+        self.column.parser = Some(parser);
+        self
+    }
+
+    /// Set the function parsing and encoding string values of this
+    /// column, if it uses `encode_with`.
+    #[doc(hidden)]
+    pub const fn with_encoder(mut self, encoder: ColumnValueEncoder) -> TypedColumn<T, M> {
+        self.column.encoder = Some(encoder);
+        self
+    }
+
+    /// Refer to this column of the table with this alias,
+    /// e.g. `b.title` instead of `book.title`.
+    ///
+    /// See [`Select::from_alias`](crate::query::Select::from_alias)
+    /// and [`Select::join`](crate::query::Select::join).
+    pub const fn aliased(mut self, alias: &'static str) -> TypedColumn<T, M> {
+        self.column.table_name = alias;
+        self
+    }
+
+    /// Set the Postgres type of this column, e.g. `"int8"`.
+    pub const fn with_pg_type(mut self, pg_type: &'static str) -> TypedColumn<T, M> {
+        self.column.pg_type = pg_type;
+        self
+    }
+
+    /// Set the maximum number of characters this column's values may have.
+    pub const fn max_length(mut self, n: usize) -> TypedColumn<T, M> {
+        self.column.max_length = Some(n);
+        self
+    }
+
+    /// Set the minimum number of characters this column's values must have.
+    pub const fn min_length(mut self, n: usize) -> TypedColumn<T, M> {
+        self.column.min_length = Some(n);
+        self
+    }
+
+    /// Set the maximum number of bytes this column's values may have.
+    pub const fn max_bytes(mut self, n: usize) -> TypedColumn<T, M> {
+        self.column.max_bytes = Some(n);
+        self
+    }
+
+    /// Set the minimum number of bytes this column's values must have.
+    pub const fn min_bytes(mut self, n: usize) -> TypedColumn<T, M> {
+        self.column.min_bytes = Some(n);
+        self
+    }
+
+    /// Set the SQL expression values of this column are written
+    /// with, see `#[column(insert_expr = "..")]`.
+    #[doc(hidden)]
+    pub const fn with_insert_expr(mut self, expr: &'static str) -> TypedColumn<T, M> {
+        self.column.insert_expr = Some(expr);
+        self
+    }
 
     /// Returns a [`Where`] clause which checks whether
     /// this column is equal to some value.
     pub fn eq<'a>(&self, other: &'a T) -> Where<'a> {
-        Where::new(
+        Where::with_params(
             format!("{}.{} = ?", self.table_name, self.column_name),
-            vec![other],
+            vec![self.bind(other)],
         )
     }
+
+    /// Check whether this column's value is `NULL`.
+    ///
+    /// This works for all columns since even a `NOT NULL`
+    /// column is `NULL` on the missing side of an outer join.
+    pub fn is_null<'a>(&self) -> Where<'a> {
+        Where::new(format!("{} IS NULL", self.full_name()), vec![])
+    }
+
+    /// Check whether this column's value is `NOT NULL`.
+    pub fn is_not_null<'a>(&self) -> Where<'a> {
+        Where::new(format!("{} IS NOT NULL", self.full_name()), vec![])
+    }
+
+    /// Refer to this column of the row which couldn't be inserted,
+    /// i.e. `excluded.{column}`.
+    ///
+    /// This is only valid in the condition of
+    /// [`Insert::do_update_where`](crate::query::Insert::do_update_where).
+    pub const fn excluded(&self) -> TypedColumn<T, M> {
+        let mut column = self.column;
+        column.table_name = "excluded";
+
+        TypedColumn {
+            column,
+            rs_type: PhantomData::<T>,
+            model: PhantomData,
+            encode: self.encode,
+            encode_owned: self.encode_owned,
+            from_elements: self.from_elements,
+        }
+    }
+
+    impl_column_cmp! {
+        eq_column: "=", "Check whether this column's value is equal to another column's.";
+    }
+
+    impl_array_cmp! {
+        eq_any: "=" "ANY", "Check whether this column's value is equal to any of some values.";
+        eq_all: "=" "ALL", "Check whether this column's value is equal to all of some values.";
+        neq_any: "<>" "ANY", "Check whether this column's value differs from any of some values.";
+        neq_all: "<>" "ALL", "Check whether this column's value differs from all of some values.";
+    }
 }
 
-impl<T: ToSql + Sync + Send + 'static + PartialOrd> TypedColumn<T> {
+impl<T: ToSql + Sync + Send + 'static + PartialOrd, M> TypedColumn<T, M> {
     /// Check whether this column's value is **g**reater **t**han some
     /// other value.
+    ///
+    /// The order of values encoded with `encode_with` is meaningless,
+    /// so the query fails with [`Error::InvalidParameter`](crate::Error::InvalidParameter)
+    /// for such columns. This goes for `gte`, `lt` and `lte`, too.
     pub fn gt<'a>(&self, other: &'a T) -> Where<'a> {
-        Where::new(
+        Where::with_params(
             format!("{}.{} > ?", self.table_name, self.column_name),
-            vec![other],
+            vec![self.bind_ordered(other)],
         )
     }
 
     /// Check whether this colum's value is **g**reater **t**han or **e**qual
     /// to another value.
     pub fn gte<'a>(&self, other: &'a T) -> Where<'a> {
-        Where::new(
+        Where::with_params(
             format!("{}.{} >= ?", self.table_name, self.column_name),
-            vec![other],
+            vec![self.bind_ordered(other)],
         )
     }
 
     /// Check whether this column's value is **l**ess **t**han some
     /// other value.
     pub fn lt<'a>(&self, other: &'a T) -> Where<'a> {
-        Where::new(
+        Where::with_params(
             format!("{}.{} < ?", self.table_name, self.column_name),
-            vec![other],
+            vec![self.bind_ordered(other)],
         )
     }
 
     /// Check whether this colum's value is **l**ess **t**han or **e**qual
     /// to another value.
     pub fn lte<'a>(&self, other: &'a T) -> Where<'a> {
-        Where::new(
+        Where::with_params(
             format!("{}.{} <= ?", self.table_name, self.column_name),
-            vec![other],
+            vec![self.bind_ordered(other)],
         )
     }
+
+    impl_column_cmp! {
+        gt_column: ">", "Check whether this column's value is greater than another column's.";
+        gte_column: ">=", "Check whether this column's value is greater than or equal to another column's.";
+        lt_column: "<", "Check whether this column's value is less than another column's.";
+        lte_column: "<=", "Check whether this column's value is less than or equal to another column's.";
+    }
+
+    impl_array_cmp! {
+        gt_any: ">" "ANY", "Check whether this column's value is greater than any of some values.";
+        gt_all: ">" "ALL", "Check whether this column's value is greater than all of some values.";
+        gte_any: ">=" "ANY", "Check whether this column's value is greater than or equal to any of some values.";
+        gte_all: ">=" "ALL", "Check whether this column's value is greater than or equal to all of some values.";
+        lt_any: "<" "ANY", "Check whether this column's value is less than any of some values.";
+        lt_all: "<" "ALL", "Check whether this column's value is less than all of some values.";
+        lte_any: "<=" "ANY", "Check whether this column's value is less than or equal to any of some values.";
+        lte_all: "<=" "ALL", "Check whether this column's value is less than or equal to all of some values.";
+    }
 }
 
-impl<'a, T: ToSql + Sync + 'a> TypedColumn<Option<T>> {
+impl<'a, T: ToSql + Sync + 'a, M> TypedColumn<Option<T>, M> {
     /// Check whether this column's value is `NULL`.
+    #[deprecated(since = "0.7.0", note = "use `is_null` instead")]
     pub fn null(&self) -> Where<'a> {
-        Where::new(
-            format!("{}.{} IS NULL", self.table_name, self.column_name),
-            vec![],
-        )
+        Where::new(format!("{} IS NULL", self.full_name()), vec![])
     }
 
     /// Check whether this column's value is `NOT NULL`
+    #[deprecated(since = "0.7.0", note = "use `is_not_null` instead")]
     pub fn not_noll(&self) -> Where<'a> {
-        self.null().not()
+        Where::new(format!("{} IS NOT NULL", self.full_name()), vec![])
+    }
+
+    /// Check whether this column's value is equal to some value,
+    /// treating `NULL` like any other value.
+    ///
+    /// Unlike `eq`, this matches if both are `NULL`
+    /// (`IS NOT DISTINCT FROM`).
+    pub fn eq_null_safe(&self, value: &'a Option<T>) -> Where<'a> {
+        Where::with_params(
+            format!(
+                "{}.{} IS NOT DISTINCT FROM ?",
+                self.table_name, self.column_name
+            ),
+            vec![self.bind(value)],
+        )
+    }
+
+    /// Check whether this column's value differs from some value,
+    /// treating `NULL` like any other value.
+    ///
+    /// This matches if exactly one of them is `NULL` (`IS DISTINCT FROM`).
+    pub fn neq_null_safe(&self, value: &'a Option<T>) -> Where<'a> {
+        Where::with_params(
+            format!(
+                "{}.{} IS DISTINCT FROM ?",
+                self.table_name, self.column_name
+            ),
+            vec![self.bind(value)],
+        )
     }
 }
 
-impl<'a, T: ToSql + Sync + 'a> TypedColumn<Vec<T>> {
+impl<'a, T: ToSql + Sync + 'static, M> TypedColumn<Vec<T>, M> {
     /// Check whether this column's array contains some value.
     pub fn contains(&self, value: &'a T) -> Where<'a> {
-        Where::new(
-            format!("? = ANY({}.{})", self.table_name, self.column_name),
-            vec![value],
-        )
+        let column = format!("{}.{}", self.table_name, self.column_name);
+        match self.encode_elements([value]) {
+            Some(param) => Where::with_params(format!("{column} @> ?"), vec![param]),
+            None => Where::new(format!("? = ANY({column})"), vec![value]),
+        }
     }
 
     /// Check whether this column's array does `NOT` contain some value.
@@ -176,19 +602,21 @@ impl<'a, T: ToSql + Sync + 'a> TypedColumn<Vec<T>> {
     /// Check whether this column's array contains any value of
     /// another array.
     pub fn contains_any(&self, values: &'a Vec<&'a T>) -> Where<'a> {
-        Where::new(
-            format!("{}.{} && ?", self.table_name, self.column_name),
-            vec![values],
-        )
+        let column = format!("{}.{}", self.table_name, self.column_name);
+        match self.encode_elements(values.iter().copied()) {
+            Some(param) => Where::with_params(format!("{column} && ?"), vec![param]),
+            None => Where::new(format!("{column} && ?"), vec![values]),
+        }
     }
 
     /// Check whether this column's array contains all values of
     /// another array.
     pub fn contains_all(&self, values: &'a Vec<&'a T>) -> Where<'a> {
-        Where::new(
-            format!("{}.{} @> ?", self.table_name, self.column_name),
-            vec![values],
-        )
+        let column = format!("{}.{}", self.table_name, self.column_name);
+        match self.encode_elements(values.iter().copied()) {
+            Some(param) => Where::with_params(format!("{column} @> ?"), vec![param]),
+            None => Where::new(format!("{column} @> ?"), vec![values]),
+        }
     }
 
     /// Check whether this column's array does not overlap
@@ -196,16 +624,276 @@ impl<'a, T: ToSql + Sync + 'a> TypedColumn<Vec<T>> {
     pub fn contains_none(&self, values: &'a Vec<&'a T>) -> Where<'a> {
         self.contains_any(values).not()
     }
+
+    /// Encode some values as an array like the values of
+    /// this column, if it uses `encode_with`.
+    fn encode_elements(&self, values: impl IntoIterator<Item = &'a T>) -> Option<Param<'static>> {
+        let encode = self.encode_owned?;
+        let Some(from_elements) = self.from_elements else {
+            return Some(Param::invalid(format!(
+                "the elements of `{}` can't be encoded on their own",
+                self.full_name()
+            )));
+        };
+
+        let values: Vec<&dyn Any> = values.into_iter().map(|i| i as &dyn Any).collect();
+        Some(encode(from_elements(&values)))
+    }
 }
 
-impl<'a> TypedColumn<String> {
+impl<'a, M> TypedColumn<String, M> {
     /// Check whethre a string contains a substring. Case sensitive.
     pub fn contains(&self, other: &'a String) -> Where<'a> {
-        Where::new(format!("POSITION(? in {})", self.full_name()), vec![other])
+        Where::with_params(
+            format!("POSITION(? in {})", self.full_name()),
+            vec![self.mask(other)],
+        )
+    }
+
+    /// Check whether a string's trigram similarity to `other`
+    /// is greater than `threshold`, which ranges from `0` to `1`.
+    ///
+    /// This uses the `%` operator, so a trigram index on the column can be
+    /// used. It only matches strings whose similarity reaches the
+    /// `pg_trgm.similarity_threshold` setting (`0.3` by default), so lower
+    /// the setting to search with a lower `threshold`.
+    ///
+    /// Requires the `pg_trgm` extension, see [`crate::create_trgm_extension`].
+    #[cfg(feature = "trgm")]
+    pub fn similar_to(&self, other: &'a String, threshold: f32) -> Where<'a> {
+        let col = self.full_name();
+        let other = self.mask(other);
+
+        Where::with_params(
+            format!("{col} % ? AND similarity({col}, ?) > ?"),
+            vec![
+                other.clone(),
+                other,
+                Param::owned(clamp_threshold(threshold)),
+            ],
+        )
+    }
+
+    /// Check whether `other` is similar to some part of the string,
+    /// i.e. its word similarity is greater than `threshold`.
+    ///
+    /// Like [`similar_to`](Self::similar_to), this uses the `<%` operator,
+    /// which only matches strings reaching the
+    /// `pg_trgm.word_similarity_threshold` setting (`0.6` by default).
+    ///
+    /// Requires the `pg_trgm` extension, see [`crate::create_trgm_extension`].
+    #[cfg(feature = "trgm")]
+    pub fn word_similar_to(&self, other: &'a String, threshold: f32) -> Where<'a> {
+        let col = self.full_name();
+        let other = self.mask(other);
+
+        Where::with_params(
+            format!("? <% {col} AND word_similarity(?, {col}) > ?"),
+            vec![
+                other.clone(),
+                other,
+                Param::owned(clamp_threshold(threshold)),
+            ],
+        )
+    }
+}
+
+/// Implement the network filters for IP address columns.
+#[cfg(feature = "net")]
+macro_rules! impl_net_filters {
+    ($($ty:ty),+) => {
+        $(
+            impl<M> TypedColumn<$ty, M> {
+                /// Check whether this address is in a network,
+                /// e.g. `"10.0.0.0/8"` or `"2001:db8::/32"`.
+                ///
+                /// The network is sent as text, so Postgres reports
+                /// if it isn't valid.
+                pub fn contained_in_cidr<'a>(&self, cidr: &'a String) -> Where<'a> {
+                    let cidr = if self.column.sensitive {
+                        Sensitive::wrap(cidr)
+                    } else {
+                        Param::from(cidr)
+                    };
+
+                    Where::with_params(format!("{} << ?::text::cidr", self.full_name()), vec![cidr])
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "net")]
+impl_net_filters!(IpAddr, Option<IpAddr>);
+
+/// Implement the calendar filters for timestamp columns.
+#[cfg(feature = "time")]
+macro_rules! impl_calendar_filters {
+    ($($ty:ty),+) => {
+        $(
+            impl<M> TypedColumn<$ty, M> {
+                /// Check whether this timestamp is on the given date,
+                /// i.e. in the session's time zone. Use `at_time_zone`
+                /// for another one.
+                ///
+                /// The column is compared to a range, so indexes on it can be used.
+                pub fn on_date<'a>(&self, date: &'a Date) -> Where<'a> {
+                    date_range(&self.column, None, DateRange::Day(date))
+                }
+
+                /// Check whether this timestamp is in the given month,
+                /// i.e. in the session's time zone.
+                pub fn in_month<'a>(&self, year: i32, month: Month) -> Where<'a> {
+                    date_range(&self.column, None, DateRange::Month(year, month))
+                }
+
+                /// Check whether this timestamp is in the given year,
+                /// i.e. in the session's time zone.
+                pub fn in_year<'a>(&self, year: i32) -> Where<'a> {
+                    date_range(&self.column, None, DateRange::Year(year))
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "time")]
+impl_calendar_filters!(
+    OffsetDateTime,
+    Option<OffsetDateTime>,
+    PrimitiveDateTime,
+    Option<PrimitiveDateTime>
+);
+
+#[cfg(feature = "time")]
+macro_rules! impl_at_time_zone {
+    ($($ty:ty),+) => {
+        $(
+            impl<M> TypedColumn<$ty, M> {
+                /// Use the calendar of a time zone, e.g. `"Europe/Berlin"`,
+                /// for the following `on_date`, `in_month` or `in_year`
+                /// instead of the session's time zone.
+                ///
+                /// # Example
+                ///
+                /// ```ignore
+                /// Order::select()
+                ///     .where_(Order::placed_at.at_time_zone("Europe/Berlin").on_date(&date))
+                ///     .await?;
+                /// ```
+                pub fn at_time_zone(&self, time_zone: impl Into<String>) -> ZonedColumn {
+                    ZonedColumn {
+                        column: self.column,
+                        time_zone: time_zone.into(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "time")]
+impl_at_time_zone!(OffsetDateTime, Option<OffsetDateTime>);
+
+/// A `TIMESTAMPTZ` column whose values are filtered by the
+/// calendar of a specific time zone.
+///
+/// Created by calling `at_time_zone` on the column.
+#[cfg(feature = "time")]
+pub struct ZonedColumn {
+    column: Column,
+    time_zone: String,
+}
+
+#[cfg(feature = "time")]
+impl ZonedColumn {
+    /// Check whether this timestamp is on the given date in the time zone.
+    pub fn on_date<'a>(&self, date: &'a Date) -> Where<'a> {
+        date_range(&self.column, Some(&self.time_zone), DateRange::Day(date))
+    }
+
+    /// Check whether this timestamp is in the given month in the time zone.
+    pub fn in_month<'a>(&self, year: i32, month: Month) -> Where<'a> {
+        date_range(
+            &self.column,
+            Some(&self.time_zone),
+            DateRange::Month(year, month),
+        )
+    }
+
+    /// Check whether this timestamp is in the given year in the time zone.
+    pub fn in_year<'a>(&self, year: i32) -> Where<'a> {
+        date_range(&self.column, Some(&self.time_zone), DateRange::Year(year))
+    }
+}
+
+/// The calendar periods which can be filtered for.
+#[cfg(feature = "time")]
+enum DateRange<'a> {
+    Day(&'a Date),
+    Month(i32, Month),
+    Year(i32),
+}
+
+/// Build a condition checking whether a timestamp is in a
+/// calendar period, starting at midnight of its first day.
+///
+/// The column is compared to the period's bounds instead of e.g.
+/// truncating it, so indexes on it can be used.
+#[cfg(feature = "time")]
+fn date_range<'a>(col: &Column, time_zone: Option<&str>, range: DateRange<'a>) -> Where<'a> {
+    let (start, end, date) = match range {
+        DateRange::Day(date) => (
+            "?::date".to_string(),
+            "?::date + INTERVAL '1 day'".to_string(),
+            Some(date),
+        ),
+        DateRange::Month(year, month) => {
+            let start = format!("make_date({year}, {}, 1)", month as u8);
+            let end = format!("{start} + INTERVAL '1 month'");
+            (start, end, None)
+        }
+        DateRange::Year(year) => {
+            let start = format!("make_date({year}, 1, 1)");
+            let end = format!("{start} + INTERVAL '1 year'");
+            (start, end, None)
+        }
+    };
+
+    // Midnight of a local date is a different instant in each time zone
+    let bound = |bound: String| match time_zone {
+        Some(_) => format!("({bound})::timestamp AT TIME ZONE ?::text"),
+        None => format!("({bound})"),
+    };
+
+    // Both bounds use the date and the time zone, if any
+    let mut bound_params: Vec<Param<'a>> = Vec::new();
+    bound_params.extend(date.map(Param::from));
+    bound_params.extend(time_zone.map(|tz| Param::owned(tz.to_string())));
+    let params = [bound_params.clone(), bound_params].concat();
+
+    Where::with_params(
+        format!(
+            "{col} >= {} AND {col} < {}",
+            bound(start),
+            bound(end),
+            col = col.full_name()
+        ),
+        params,
+    )
+}
+
+/// Keep a similarity threshold between `0` and `1`.
+#[cfg(feature = "trgm")]
+fn clamp_threshold(threshold: f32) -> f32 {
+    if threshold.is_nan() {
+        return 0.0;
     }
+
+    threshold.clamp(0.0, 1.0)
 }
 
-impl<T: ToSql + Sync> Deref for TypedColumn<T> {
+impl<T: ToSql + Sync, M> Deref for TypedColumn<T, M> {
     type Target = Column;
 
     fn deref(&self) -> &Self::Target {
@@ -219,14 +907,142 @@ impl Column {
         Column {
             column_name,
             table_name,
+            pg_type: "",
             nullable: false,
             unique: false,
             primary_key: false,
             generated: false,
+            sensitive: false,
+            parser: None,
+            encoder: None,
+            max_length: None,
+            min_length: None,
+            max_bytes: None,
+            min_bytes: None,
+            insert_expr: None,
         }
     }
 
-    impl_prop_col!(unique, nullable, primary_key, generated);
+    impl_prop_col!(unique, nullable, primary_key, generated, sensitive);
+
+    /// Check whether a string is a valid value of this column.
+    ///
+    /// Returns `None` if the column's type has no [`ColumnValueParser`].
+    pub fn parse(&self, value: &str) -> Option<Result<(), String>> {
+        self.parser.map(|parse| parse(value))
+    }
+
+    /// Parse a string value of this column and encode it,
+    /// if the column uses `encode_with`.
+    pub(crate) fn encode(&self, value: &str) -> Option<Result<Param<'static>, String>> {
+        self.encoder.map(|encode| encode(value))
+    }
+
+    /// Get the Postgres type of this column, e.g. `"int8"` or `"VARCHAR(20)"`.
+    ///
+    /// This is the type used when creating the column.
+    /// It is empty for columns which weren't created by the derive macro.
+    pub const fn pg_type(&self) -> &'static str {
+        self.pg_type
+    }
+
+    /// Get the maximum number of characters this column's values may have.
+    pub const fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    /// Get the minimum number of characters this column's values must have.
+    pub const fn min_length(&self) -> Option<usize> {
+        self.min_length
+    }
+
+    /// Get the maximum number of bytes this column's values may have.
+    pub const fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Get the minimum number of bytes this column's values must have.
+    pub const fn min_bytes(&self) -> Option<usize> {
+        self.min_bytes
+    }
+
+    /// Get the SQL expression values of this column are written with
+    /// when inserting or updating, e.g. `ST_GeomFromText(?, 4326)`.
+    ///
+    /// The value is passed in place of its `?`. It is `"?"` for
+    /// columns without `#[column(insert_expr = "..")]`.
+    pub const fn insert_expr(&self) -> &'static str {
+        match self.insert_expr {
+            Some(expr) => expr,
+            None => "?",
+        }
+    }
+
+    /// Check whether a value satisfies this column's length constraints.
+    ///
+    /// The value is measured the way it is sent to Postgres, so this
+    /// works for `String`s, `Vec<u8>`s and their `Option`s alike.
+    /// `NULL`s and other types always pass.
+    pub fn validate(&self, value: &(dyn ToSql + Sync)) -> Result<(), Error> {
+        let limits = [
+            self.max_length,
+            self.min_length,
+            self.max_bytes,
+            self.min_bytes,
+        ];
+        if limits.iter().all(Option::is_none) {
+            return Ok(());
+        }
+
+        // Strings are sent as UTF-8, byte arrays as they are.
+        let mut buf = BytesMut::new();
+        let mut text = None;
+        for ty in [Type::TEXT, Type::BYTEA] {
+            buf.clear();
+            match value.to_sql_checked(&ty, &mut buf) {
+                Ok(IsNull::No) => {
+                    text = Some(ty == Type::TEXT);
+                    break;
+                }
+                Ok(IsNull::Yes) => return Ok(()),
+                Err(_) => continue,
+            }
+        }
+        let Some(text) = text else {
+            return Ok(());
+        };
+
+        let invalid = |reason: String| {
+            Err(Error::Validation {
+                column: self.column_name,
+                reason,
+            })
+        };
+
+        let bytes = buf.len();
+        if let Some(max) = self.max_bytes.filter(|max| bytes > *max) {
+            return invalid(format!("{bytes} bytes exceed the maximum of {max}"));
+        }
+        if let Some(min) = self.min_bytes.filter(|min| bytes < *min) {
+            return invalid(format!("{bytes} bytes fall short of the minimum of {min}"));
+        }
+
+        if !text {
+            return Ok(());
+        }
+
+        let chars = String::from_utf8_lossy(&buf).chars().count();
+        if let Some(max) = self.max_length.filter(|max| chars > *max) {
+            return invalid(format!("{chars} characters exceed the maximum of {max}"));
+        }
+        if let Some(min) = self.min_length.filter(|min| chars < *min) {
+            return invalid(format!(
+                "{chars} characters fall short of the minimum of {min}"
+            ));
+        }
+
+        Ok(())
+    }
 
     /// Get the column name.
     pub const fn column_name(&self) -> &'static str {
@@ -256,24 +1072,39 @@ impl Column {
     pub fn full_name(&self) -> String {
         format!("{}.{}", self.table_name, self.column_name)
     }
+
+    /// Get the name this column is selected as when using
+    /// [`Select::qualify_aliases`](crate::query::Select::qualify_aliases),
+    /// i.e. `{table}__{column}` without any quotes.
+    pub fn qualified_alias(&self) -> String {
+        format!(
+            "{}__{}",
+            self.table_name.trim_matches('"'),
+            self.column_name.trim_matches('"')
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     #![allow(dead_code)]
 
+    use bytes::BytesMut;
+    use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type};
+
     use crate::{
         prelude::*,
-        query::{PushChunk, Query, Where},
+        query::{PushChunk, Query, TypedColumn, Where},
     };
 
     impl<'a> Where<'a> {
         /// This is a convieniance function for testing purposes.
+        #[allow(clippy::wrong_self_convention)]
         fn to_stmt(&mut self) -> String {
             let mut q = Query::<u64>::default();
             self.push_to_buffer(&mut q);
 
-            q.0
+            q.statement().to_string()
         }
     }
 
@@ -284,37 +1115,614 @@ mod tests {
         pages: Vec<String>,
     }
 
+    #[derive(Model)]
+    struct Review {
+        #[column(min_length = 2, max_length = 5)]
+        title: String,
+        #[column(max_length = 3)]
+        comment: Option<String>,
+        #[column(max_bytes = 4)]
+        data: Vec<u8>,
+    }
+
+    /// An element type which isn't `Clone`.
+    #[derive(Debug)]
+    struct Label(String);
+
+    impl ToSql for Label {
+        fn to_sql(
+            &self,
+            ty: &Type,
+            out: &mut BytesMut,
+        ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+            self.0.to_sql(ty, out)
+        }
+
+        fn accepts(ty: &Type) -> bool {
+            <String as ToSql>::accepts(ty)
+        }
+
+        to_sql_checked!();
+    }
+
+    #[test]
+    fn contains_without_clone() {
+        let labels = TypedColumn::<Vec<Label>>::new("shelf", "labels");
+        let label = Label("new".into());
+        let others = vec![&label];
+
+        assert_eq!(labels.contains(&label).to_stmt(), "$1 = ANY(shelf.labels)");
+        assert_eq!(labels.contains_any(&others).to_stmt(), "shelf.labels && $1");
+    }
+
     #[test]
     fn equals() {
-        assert_eq!(Book::title.eq(&"ABC".into()).to_stmt(), "book.title = ?")
+        assert_eq!(Book::title.eq(&"ABC".into()).to_stmt(), "book.title = $1")
     }
 
     #[test]
     fn greater_than() {
-        assert_eq!(Book::id.gt(&1).to_stmt(), "book.id > ?");
+        assert_eq!(Book::id.gt(&1).to_stmt(), "book.id > $1");
     }
 
     #[test]
     fn greater_than_equals() {
-        assert_eq!(Book::id.gte(&1).to_stmt(), "book.id >= ?");
+        assert_eq!(Book::id.gte(&1).to_stmt(), "book.id >= $1");
     }
 
     #[test]
     fn less_than() {
-        assert_eq!(Book::id.lt(&1).to_stmt(), "book.id < ?")
+        assert_eq!(Book::id.lt(&1).to_stmt(), "book.id < $1")
     }
 
     #[test]
     fn less_than_equals() {
-        assert_eq!(Book::id.lte(&1).to_stmt(), "book.id <= ?")
+        assert_eq!(Book::id.lte(&1).to_stmt(), "book.id <= $1")
+    }
+
+    #[test]
+    fn any_all() {
+        let v = vec![1, 2];
+        assert_eq!(Book::id.eq_any(&v).to_stmt(), "book.id = ANY($1)");
+        assert_eq!(Book::id.eq_all(&v).to_stmt(), "book.id = ALL($1)");
+        assert_eq!(Book::id.neq_any(&v).to_stmt(), "book.id <> ANY($1)");
+        assert_eq!(Book::id.neq_all(&v).to_stmt(), "book.id <> ALL($1)");
+        assert_eq!(Book::id.gt_any(&v).to_stmt(), "book.id > ANY($1)");
+        assert_eq!(Book::id.gt_all(&v).to_stmt(), "book.id > ALL($1)");
+        assert_eq!(Book::id.gte_any(&v).to_stmt(), "book.id >= ANY($1)");
+        assert_eq!(Book::id.gte_all(&v).to_stmt(), "book.id >= ALL($1)");
+        assert_eq!(Book::id.lt_any(&v).to_stmt(), "book.id < ANY($1)");
+        assert_eq!(Book::id.lt_all(&v).to_stmt(), "book.id < ALL($1)");
+        assert_eq!(Book::id.lte_any(&v).to_stmt(), "book.id <= ANY($1)");
+        assert_eq!(Book::id.lte_all(&v).to_stmt(), "book.id <= ALL($1)");
+    }
+
+    #[test]
+    fn any_all_single_param() {
+        let v = vec![1, 2, 3];
+        let q: Query<'_, Vec<Book>> = Book::select()
+            .where_(Book::id.gt_all(&v) & Book::id.lt(&10))
+            .into();
+
+        assert_eq!(
+            q.statement(),
+            "SELECT book.id, book.title, book.pages FROM book \
+            WHERE (book.id > ALL($1)) AND (book.id < $2)"
+        );
+        assert_eq!(q.params().len(), 2);
+    }
+
+    #[test]
+    fn any_all_empty() {
+        // Empty arrays are passed on, so Postgres decides:
+        // `ALL` over nothing is true, `ANY` is false.
+        let v: Vec<i64> = vec![];
+        let q: Query<'_, Vec<Book>> = Book::select().where_(Book::id.neq_all(&v)).into();
+
+        assert_eq!(
+            q.statement(),
+            "SELECT book.id, book.title, book.pages FROM book WHERE book.id <> ALL($1)"
+        );
+        assert_eq!(format!("{:?}", q.params()), "[[]]");
+    }
+
+    /// Render a condition, returning the statement and its parameters.
+    fn render(mut where_: Where<'_>) -> (String, Vec<String>) {
+        let mut q = Query::<u64>::default();
+        where_.push_to_buffer(&mut q);
+
+        (
+            q.statement().to_string(),
+            q.params().iter().map(|i| format!("{i:?}")).collect(),
+        )
+    }
+
+    /// A tiny xorshift generator, so the random
+    /// filter trees are the same on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+
+            self.0 % n
+        }
+    }
+
+    /// Build a random filter tree, taking the values of its
+    /// parameters from `values` in the order they are written.
+    fn random_where<'a>(rng: &mut Rng, values: &mut &'a [i64], depth: u32) -> Where<'a> {
+        let mut next = || {
+            let (value, rest) = values.split_first().unwrap();
+            *values = rest;
+            value
+        };
+
+        let kind = if depth == 0 {
+            rng.below(5)
+        } else {
+            rng.below(13)
+        };
+        match kind {
+            0 => Book::id.eq(next()),
+            1 => Book::id.gt(next()),
+            2 => Where::default(),
+            3 | 4 => {
+                let n = rng.below(4) as usize;
+                let params = (0..n).map(|_| next() as _).collect();
+                Where::new(format!("f({})", vec!["?"; n].join(", ")), params)
+            }
+            5 => {
+                let a = random_where(rng, values, depth - 1);
+                a & random_where(rng, values, depth - 1)
+            }
+            6 => {
+                let a = random_where(rng, values, depth - 1);
+                a | random_where(rng, values, depth - 1)
+            }
+            7 => !random_where(rng, values, depth - 1),
+            8 => Where::group(random_where(rng, values, depth - 1)),
+            9 => {
+                let a = random_where(rng, values, depth - 1);
+                a.and(random_where(rng, values, depth - 1))
+            }
+            10 => {
+                let a = random_where(rng, values, depth - 1);
+                a.or(random_where(rng, values, depth - 1))
+            }
+            11 => {
+                let n = rng.below(4);
+                Where::all(
+                    (0..n)
+                        .map(|_| random_where(rng, values, depth - 1))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            _ => {
+                let n = rng.below(4);
+                Where::any(
+                    (0..n)
+                        .map(|_| random_where(rng, values, depth - 1))
+                        .collect::<Vec<_>>(),
+                )
+            }
+        }
+    }
+
+    /// Check that the i-th placeholder of a statement is `$i`
+    /// and that the parameters are the first values, in order.
+    fn assert_numbered(stmt: &str, params: &[String]) {
+        assert!(!stmt.contains('?'), "{stmt}");
+
+        let placeholders: Vec<usize> = stmt
+            .split('$')
+            .skip(1)
+            .map(|s| {
+                let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+                s[..digits].parse().unwrap()
+            })
+            .collect();
+        let expected: Vec<_> = (1..=params.len()).collect();
+        assert_eq!(placeholders, expected, "{stmt}");
+
+        let values: Vec<_> = (0..params.len()).map(|i| i.to_string()).collect();
+        assert_eq!(params, values, "{stmt}");
+    }
+
+    #[test]
+    fn random_trees_numbering() {
+        let values: Vec<i64> = (0..10_000).collect();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..500 {
+            let mut rest = values.as_slice();
+            let (stmt, params) = render(random_where(&mut rng, &mut rest, 4));
+
+            assert_numbered(&stmt, &params);
+        }
+    }
+
+    #[test]
+    fn random_where_raw_numbering() {
+        let values: Vec<i64> = (0..10_000).collect();
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..500 {
+            let mut rest = values.as_slice();
+            let mut select = Book::select();
+            for _ in 0..rng.below(4) {
+                select = if rng.below(2) == 0 {
+                    select.where_(random_where(&mut rng, &mut rest, 3))
+                } else {
+                    let (value, tail) = rest.split_first().unwrap();
+                    rest = tail;
+                    select.where_raw("f(book.id, ?)", vec![value])
+                };
+            }
+
+            let q: Query<'_, Vec<Book>> = select.into();
+            let params: Vec<_> = q.params().iter().map(|i| format!("{i:?}")).collect();
+            assert_numbered(q.statement(), &params);
+        }
+    }
+
+    #[test]
+    fn tuple_in() {
+        let none: Vec<(i64, String)> = vec![];
+        let (stmt, params) = render(Where::tuple_in((Book::id, Book::title), &none));
+        assert_eq!(stmt, "FALSE");
+        assert!(params.is_empty());
+
+        let rows = [(1, "a".to_string())];
+        let (stmt, params) = render(Where::tuple_in((Book::id, Book::title), &rows));
+        assert_eq!(stmt, "(book.id, book.title) IN (($1, $2))");
+        assert_eq!(params, ["1", "\"a\""]);
+
+        let rows = [(1, "a".into()), (2, "b".into()), (3, "c".into())];
+        let q: Query<'_, Vec<Book>> = Book::select()
+            .where_(Book::id.gt(&0) & Where::tuple_in((Book::id, Book::title), &rows))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "SELECT book.id, book.title, book.pages FROM book WHERE (book.id > $1) \
+            AND ((book.id, book.title) IN (($2, $3), ($4, $5), ($6, $7)))"
+        );
+        let params: Vec<_> = q.params().iter().map(|i| format!("{i:?}")).collect();
+        assert_eq!(params, ["0", "1", "\"a\"", "2", "\"b\"", "3", "\"c\""]);
+
+        // Values are bound by their columns
+        let rows = [(1, "secret".to_string())];
+        let (_, params) = render(Where::tuple_in((Login::id, Login::token), &rows));
+        assert_eq!(params, ["1", "***"]);
+    }
+
+    #[derive(Model)]
+    struct Login {
+        id: i64,
+        #[column(sensitive)]
+        token: String,
+    }
+
+    #[test]
+    fn and_inside_or() {
+        let (stmt, params) = render((Book::id.eq(&1) & Book::id.gt(&2)) | Book::id.lt(&3));
+        assert_eq!(
+            stmt,
+            "((book.id = $1) AND (book.id > $2)) OR (book.id < $3)"
+        );
+        assert_eq!(params, ["1", "2", "3"]);
+
+        let (stmt, params) = render(Book::id.eq(&1) | (Book::id.gt(&2) & Book::id.lt(&3)));
+        assert_eq!(
+            stmt,
+            "(book.id = $1) OR ((book.id > $2) AND (book.id < $3))"
+        );
+        assert_eq!(params, ["1", "2", "3"]);
+
+        // An AND joined to an existing OR stays nested
+        let (stmt, _) =
+            render((Book::id.eq(&1) | Book::id.eq(&2)) | (Book::id.gt(&3) & Book::id.lt(&4)));
+        assert_eq!(
+            stmt,
+            "(book.id = $1) OR (book.id = $2) OR ((book.id > $3) AND (book.id < $4))"
+        );
+    }
+
+    #[test]
+    fn not_or_and() {
+        let (stmt, params) = render(!(Book::id.eq(&1) | Book::id.eq(&2)) & Book::id.lt(&3));
+        assert_eq!(
+            stmt,
+            "(NOT ((book.id = $1) OR (book.id = $2))) AND (book.id < $3)"
+        );
+        assert_eq!(params, ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn nested_three_levels() {
+        let (stmt, params) = render(
+            ((Book::id.eq(&1) | Book::id.eq(&2)) & Book::id.gt(&3))
+                | (Book::id.lt(&4) & !(Book::id.eq(&5) | Book::id.eq(&6))),
+        );
+        assert_eq!(
+            stmt,
+            "(((book.id = $1) OR (book.id = $2)) AND (book.id > $3)) OR \
+            ((book.id < $4) AND (NOT ((book.id = $5) OR (book.id = $6))))"
+        );
+        assert_eq!(params, ["1", "2", "3", "4", "5", "6"]);
+
+        // Flattening keeps the order of the operands
+        let (stmt, params) = render(Book::id.eq(&1) | (Book::id.eq(&2) | Book::id.eq(&3)));
+        assert_eq!(stmt, "(book.id = $1) OR (book.id = $2) OR (book.id = $3)");
+        assert_eq!(params, ["1", "2", "3"]);
+
+        let (stmt, params) = render(Book::id.eq(&1) & (Book::id.eq(&2) & Book::id.eq(&3)));
+        assert_eq!(stmt, "(book.id = $1) AND (book.id = $2) AND (book.id = $3)");
+        assert_eq!(params, ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn is_null() {
+        let (stmt, params) = render(Review::comment.is_null());
+        assert_eq!(stmt, "review.comment IS NULL");
+        assert!(params.is_empty());
+
+        let (stmt, _) = render(Review::comment.is_not_null());
+        assert_eq!(stmt, "review.comment IS NOT NULL");
+
+        // Columns which aren't `Option`s, e.g. from an outer join
+        let (stmt, _) = render(Book::id.is_null() | Book::title.is_not_null());
+        assert_eq!(stmt, "(book.id IS NULL) OR (book.title IS NOT NULL)");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_null_aliases() {
+        assert_eq!(
+            render(Review::comment.null()),
+            render(Review::comment.is_null())
+        );
+        assert_eq!(
+            render(Review::comment.not_noll()),
+            render(Review::comment.is_not_null())
+        );
+    }
+
+    #[test]
+    fn null_safe() {
+        let (stmt, params) = render(Review::comment.eq_null_safe(&Some("abc".into())));
+        assert_eq!(stmt, "review.comment IS NOT DISTINCT FROM $1");
+        assert_eq!(params, ["Some(\"abc\")"]);
+
+        let (stmt, params) = render(Review::comment.eq_null_safe(&None));
+        assert_eq!(stmt, "review.comment IS NOT DISTINCT FROM $1");
+        assert_eq!(params, ["None"]);
+
+        let (stmt, params) = render(Review::comment.neq_null_safe(&Some("abc".into())));
+        assert_eq!(stmt, "review.comment IS DISTINCT FROM $1");
+        assert_eq!(params, ["Some(\"abc\")"]);
+
+        let (stmt, params) = render(Review::comment.neq_null_safe(&None));
+        assert_eq!(stmt, "review.comment IS DISTINCT FROM $1");
+        assert_eq!(params, ["None"]);
+    }
+
+    #[cfg(feature = "trgm")]
+    #[test]
+    fn similarity() {
+        let (stmt, params) = render(Book::title.similar_to(&"Dune".into(), 0.4));
+        assert_eq!(stmt, "book.title % $1 AND similarity(book.title, $2) > $3");
+        assert_eq!(params, ["\"Dune\"", "\"Dune\"", "0.4"]);
+
+        let (stmt, params) = render(Book::title.word_similar_to(&"Dune".into(), 0.6));
+        assert_eq!(
+            stmt,
+            "$1 <% book.title AND word_similarity($2, book.title) > $3"
+        );
+        assert_eq!(params, ["\"Dune\"", "\"Dune\"", "0.6"]);
+
+        // Thresholds are kept in range
+        let (_, params) = render(Book::title.similar_to(&"Dune".into(), 2.0));
+        assert_eq!(params[2], "1.0");
+        let (_, params) = render(Book::title.similar_to(&"Dune".into(), f32::NAN));
+        assert_eq!(params[2], "0.0");
+    }
+
+    #[cfg(feature = "net")]
+    #[derive(Model)]
+    struct Visit {
+        ip: std::net::IpAddr,
+        #[column(sensitive)]
+        forwarded_for: Option<std::net::IpAddr>,
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn net_filters() {
+        let (stmt, params) = render(Visit::ip.contained_in_cidr(&"10.0.0.0/8".into()));
+        assert_eq!(stmt, "visit.ip << $1::text::cidr");
+        assert_eq!(params, ["\"10.0.0.0/8\""]);
+
+        let (stmt, params) = render(Visit::forwarded_for.contained_in_cidr(&"::/120".into()));
+        assert_eq!(stmt, "visit.forwarded_for << $1::text::cidr");
+        assert_eq!(params, ["***"]);
+    }
+
+    #[cfg(feature = "time")]
+    #[derive(Model)]
+    struct Event {
+        starts_at: time::OffsetDateTime,
+        ends_at: Option<time::PrimitiveDateTime>,
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn calendar_filters() {
+        let date = time::Date::from_calendar_date(2024, time::Month::March, 31).unwrap();
+
+        let (stmt, params) = render(Event::starts_at.on_date(&date));
+        assert_eq!(
+            stmt,
+            "event.starts_at >= ($1::date) AND event.starts_at < ($2::date + INTERVAL '1 day')"
+        );
+        assert_eq!(params, ["2024-03-31", "2024-03-31"]);
+
+        let (stmt, params) = render(Event::ends_at.in_month(2024, time::Month::February));
+        assert_eq!(
+            stmt,
+            "event.ends_at >= (make_date(2024, 2, 1)) \
+            AND event.ends_at < (make_date(2024, 2, 1) + INTERVAL '1 month')"
+        );
+        assert!(params.is_empty());
+
+        let (stmt, _) = render(Event::starts_at.in_year(2023));
+        assert_eq!(
+            stmt,
+            "event.starts_at >= (make_date(2023, 1, 1)) \
+            AND event.starts_at < (make_date(2023, 1, 1) + INTERVAL '1 year')"
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn calendar_filters_time_zone() {
+        let date = time::Date::from_calendar_date(2024, time::Month::March, 31).unwrap();
+
+        let (stmt, params) = render(
+            Event::starts_at
+                .at_time_zone("Europe/Berlin")
+                .on_date(&date),
+        );
+        assert_eq!(
+            stmt,
+            "event.starts_at >= ($1::date)::timestamp AT TIME ZONE $2::text \
+            AND event.starts_at < ($3::date + INTERVAL '1 day')::timestamp AT TIME ZONE $4::text"
+        );
+        assert_eq!(params.len(), 4);
+
+        // The time zone is always a parameter
+        let (stmt, params) = render(Event::starts_at.at_time_zone("it's").in_year(2024));
+        assert_eq!(
+            stmt,
+            "event.starts_at >= (make_date(2024, 1, 1))::timestamp AT TIME ZONE $1::text \
+            AND event.starts_at < (make_date(2024, 1, 1) + INTERVAL '1 year')::timestamp AT TIME ZONE $2::text"
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn group() {
+        let (stmt, params) =
+            render(Book::id.eq(&1) & Where::group(Book::id.gt(&2) & Book::id.lt(&3)));
+        assert_eq!(
+            stmt,
+            "(book.id = $1) AND (((book.id > $2) AND (book.id < $3)))"
+        );
+        assert_eq!(params, ["1", "2", "3"]);
+
+        let (stmt, _) = render(Where::group(Where::group(Book::id.eq(&1))) | Book::id.eq(&2));
+        assert_eq!(stmt, "((book.id = $1)) OR (book.id = $2)");
+
+        assert!(matches!(Where::group(Where::Empty), Where::Empty));
+    }
+
+    #[test]
+    fn dynamic_lists() {
+        let ids = [1i64, 2, 3];
+
+        let (stmt, params) = render(Where::any(ids.iter().map(|id| Book::id.eq(id))));
+        assert_eq!(stmt, "(book.id = $1) OR (book.id = $2) OR (book.id = $3)");
+        assert_eq!(params, ["1", "2", "3"]);
+
+        let (stmt, params) = render(Where::all(ids.iter().map(|id| !Book::id.eq(id))));
+        assert_eq!(
+            stmt,
+            "(NOT (book.id = $1)) AND (NOT (book.id = $2)) AND (NOT (book.id = $3))"
+        );
+        assert_eq!(params, ["1", "2", "3"]);
+
+        // Lists of lists keep their structure
+        let (stmt, _) = render(Where::any([
+            Where::all(ids[..2].iter().map(|id| Book::id.gt(id))),
+            Where::all(ids[1..].iter().map(|id| Book::id.lt(id))),
+        ]));
+        assert_eq!(
+            stmt,
+            "((book.id > $1) AND (book.id > $2)) OR ((book.id < $3) AND (book.id < $4))"
+        );
+
+        // A single condition isn't wrapped
+        let (stmt, _) = render(Where::all(ids[..1].iter().map(|id| Book::id.eq(id))));
+        assert_eq!(stmt, "book.id = $1");
+
+        // Empty lists and empty conditions result in no condition at all
+        assert!(matches!(Where::any([]), Where::Empty));
+        assert!(matches!(Where::all([]), Where::Empty));
+        assert!(matches!(
+            Where::all([Where::default(), Where::Empty]),
+            Where::Empty
+        ));
+
+        let q: Query<'_, Vec<Book>> = Book::select().where_(Where::any([])).into();
+        assert_eq!(
+            q.statement(),
+            "SELECT book.id, book.title, book.pages FROM book"
+        );
     }
 
     #[test]
     fn complete_query() {
         let q: Query<'_, Vec<Book>> = Book::select().where_(Book::id.gt(&3)).into();
         assert_eq!(
-            q.0,
+            q.statement(),
             "SELECT book.id, book.title, book.pages FROM book WHERE book.id > $1"
         );
     }
+
+    #[test]
+    fn validate_length() {
+        assert!(Review::title.validate(&"ab".to_string()).is_ok());
+        assert!(Review::title.validate(&"abcde".to_string()).is_ok());
+        assert!(matches!(
+            Review::title.validate(&"abcdef".to_string()),
+            Err(pg_worm::Error::Validation {
+                column: "title",
+                ..
+            })
+        ));
+        assert!(Review::title.validate(&"a".to_string()).is_err());
+
+        // Characters are counted, not bytes
+        assert!(Review::title.validate(&"äöüß".to_string()).is_ok());
+    }
+
+    #[test]
+    fn validate_option() {
+        assert!(Review::comment.validate(&None::<String>).is_ok());
+        assert!(Review::comment.validate(&Some("abc".to_string())).is_ok());
+        assert!(Review::comment.validate(&Some("abcd".to_string())).is_err());
+    }
+
+    #[test]
+    fn validate_bytes() {
+        assert!(Review::data.validate(&vec![0u8; 4]).is_ok());
+        assert!(Review::data.validate(&vec![0u8; 5]).is_err());
+    }
+
+    #[test]
+    fn pg_type() {
+        assert_eq!(Book::id.pg_type(), "int8");
+        assert_eq!(Book::title.pg_type(), "text");
+        assert_eq!(Book::pages.pg_type(), "text[]");
+        assert_eq!(Review::title.pg_type(), "VARCHAR(5)");
+        assert_eq!(Review::data.pg_type(), "bytea");
+    }
+
+    #[test]
+    fn validate_unlimited() {
+        assert!(Book::title.validate(&"a".repeat(1000)).is_ok());
+        assert!(Book::id.validate(&1).is_ok());
+    }
 }
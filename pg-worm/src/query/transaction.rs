@@ -1,13 +1,29 @@
 use std::{
     alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    future::Future,
+    marker::PhantomData,
     ptr::drop_in_place,
+    sync::{Arc, Mutex},
 };
 
-use tokio_postgres::Transaction as PgTransaction;
+use async_trait::async_trait;
+use tokio_postgres::{types::ToSql, Row as PgRow, Transaction as PgTransaction};
 
-use crate::{fetch_client, pool::Client as PgClient, Error};
+use crate::{
+    fetch_client,
+    pool::{unnamed, Client as PgClient},
+    Error, Model, Row,
+};
 
-use super::{Query, QueryOutcome};
+use super::{
+    measured, Delete, Executor, Insert, NoneSet, Query, QueryOutcome, RowHook, Select, Update,
+};
+
+tokio::task_local! {
+    /// The transaction of the [`Transaction::scope`]
+    /// the current task runs in, if any.
+    static SCOPED_TRANSACTION: Arc<OpenTransaction>;
+}
 
 struct PinnedClient(pub *mut PgClient);
 
@@ -31,6 +47,11 @@ impl PinnedClient {
     }
 }
 
+// The `PinnedClient` owns the client behind the pointer, just like
+// a `Box` would, so it may be sent and shared the same way.
+unsafe impl Send for PinnedClient where PgClient: Send {}
+unsafe impl Sync for PinnedClient where PgClient: Sync {}
+
 impl Drop for PinnedClient {
     fn drop(&mut self) {
         unsafe {
@@ -46,17 +67,63 @@ impl Drop for PinnedClient {
     }
 }
 
+/// The constraints affected by [`Transaction::set_constraints`].
+#[derive(Clone, Copy, Debug)]
+pub enum Constraints<'a> {
+    /// All deferrable constraints.
+    All,
+    /// The deferrable constraints with these names, which are
+    /// quoted, so they have to match exactly.
+    Named(&'a [&'a str]),
+}
+
+/// When constraints are checked, see [`Transaction::set_constraints`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstraintMode {
+    /// At commit.
+    Deferred,
+    /// At the end of each statement.
+    Immediate,
+}
+
+/// A transaction along with the client it runs on.
+///
+/// The transaction is declared first, so it is
+/// dropped (i.e. rolled back) before the client.
+#[doc(hidden)]
+pub struct OpenTransaction {
+    transaction: PgTransaction<'static>,
+    _client: PinnedClient,
+    /// Hooks to run again once the transaction has been committed.
+    after_commit: Mutex<Vec<(RowHook, Vec<Row>)>>,
+    /// Held while savepoints are in use. Rolling back to one also undoes
+    /// the savepoints created after it, e.g. by another task of the scope.
+    savepoints: tokio::sync::Mutex<()>,
+}
+
+impl OpenTransaction {
+    /// Get the transaction.
+    pub(super) fn transaction(&self) -> &PgTransaction<'static> {
+        &self.transaction
+    }
+
+    /// Wait until no other task of the scope uses savepoints.
+    pub(super) async fn lock_savepoints(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.savepoints.lock().await
+    }
+}
+
 /// A struct providing transaction functionality.
 ///
 /// Use it to execute queries as part of this transaction.
 /// When you are done, commit using `.commit()`
 pub struct Transaction<'a> {
-    transaction: PgTransaction<'a>,
-    _client: PinnedClient,
+    inner: Arc<OpenTransaction>,
+    lifetime: PhantomData<&'a ()>,
 }
 
 impl<'a> Transaction<'a> {
-    async fn from_client<'this>(client: PgClient) -> Result<Transaction<'a>, Error> {
+    async fn from_client(client: PgClient) -> Result<Transaction<'a>, Error> {
         let client = unsafe { PinnedClient::from_client(client) };
         let transaction = unsafe {
             // Convert `*mut PgClient` to `&mut PgClient`
@@ -68,11 +135,24 @@ impl<'a> Transaction<'a> {
         .await?;
 
         Ok(Transaction {
-            _client: client,
-            transaction,
+            inner: Arc::new(OpenTransaction {
+                _client: client,
+                transaction,
+                after_commit: Mutex::default(),
+                savepoints: tokio::sync::Mutex::default(),
+            }),
+            lifetime: PhantomData,
         })
     }
 
+    /// Take the transaction out of its `Arc` to end it.
+    ///
+    /// Only queries of a [`scope`](Transaction::scope) which outlived
+    /// it, e.g. a future that was boxed and moved out, share it.
+    fn into_inner(self) -> Result<OpenTransaction, Error> {
+        Arc::try_unwrap(self.inner).map_err(|_| Error::TransactionInUse)
+    }
+
     /// Begin a new transaction.
     pub async fn begin() -> Result<Transaction<'a>, Error> {
         let client = fetch_client().await?;
@@ -82,22 +162,326 @@ impl<'a> Transaction<'a> {
 
     /// Rollback this transaction. TODO
     pub async fn rollback(self) -> Result<(), Error> {
-        self.transaction.rollback().await.map_err(Error::from)
+        // The client has to outlive the transaction
+        let OpenTransaction {
+            transaction,
+            _client,
+            ..
+        } = self.into_inner()?;
+        transaction.rollback().await?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_transaction(false);
+
+        Ok(())
     }
 
     /// Commit the transaction. TODO
     pub async fn commit(self) -> Result<(), Error> {
-        self.transaction.commit().await.map_err(Error::from)
+        let OpenTransaction {
+            transaction,
+            _client,
+            after_commit,
+            ..
+        } = self.into_inner()?;
+        transaction.commit().await?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_transaction(true);
+
+        let after_commit = after_commit.into_inner().unwrap_or_default();
+        for (hook, rows) in after_commit {
+            hook(&rows)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a future with every query it executes, e.g. using
+    /// `Model::insert` or query builders, being part of this transaction.
+    ///
+    /// Scopes can be nested, the innermost one wins. Once the future
+    /// has finished, panicked or been dropped, queries use the pool
+    /// (or the enclosing scope's transaction) again.
+    ///
+    /// The scope is bound to the current task, so queries
+    /// of tasks spawned inside it don't take part in it.
+    /// Prepared statements are executed like regular ones.
+    ///
+    /// ```ignore
+    /// let transaction = Transaction::begin().await?;
+    /// transaction
+    ///     .scope(async {
+    ///         Book::insert("Dune".to_string()).await?;
+    ///         Book::update().set(Book::pages, &412).await
+    ///     })
+    ///     .await?;
+    /// transaction.commit().await?;
+    /// ```
+    pub async fn scope<F: Future>(&self, fut: F) -> F::Output {
+        SCOPED_TRANSACTION.scope(self.inner.clone(), fut).await
+    }
+
+    /// Change when deferrable constraints are checked
+    /// for the rest of this transaction using `SET CONSTRAINTS`.
+    ///
+    /// This allows e.g. inserting rows which reference each
+    /// other, since foreign keys aren't checked until commit.
+    /// Constraints which aren't `DEFERRABLE` are not affected.
+    ///
+    /// Fails with [`Error::NoConstraintsNamed`] if
+    /// [`Constraints::Named`] is passed an empty list.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let transaction = Transaction::begin().await?;
+    /// transaction
+    ///     .set_constraints(Constraints::All, ConstraintMode::Deferred)
+    ///     .await?;
+    /// ```
+    pub async fn set_constraints(
+        &self,
+        constraints: Constraints<'_>,
+        mode: ConstraintMode,
+    ) -> Result<(), Error> {
+        let constraints = match constraints {
+            Constraints::All => "ALL".to_string(),
+            Constraints::Named([]) => return Err(Error::NoConstraintsNamed),
+            Constraints::Named(names) => names
+                .iter()
+                .map(|i| format!("\"{}\"", i.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        let mode = match mode {
+            ConstraintMode::Deferred => "DEFERRED",
+            ConstraintMode::Immediate => "IMMEDIATE",
+        };
+
+        self.inner
+            .transaction
+            .batch_execute(&format!("SET CONSTRAINTS {constraints} {mode}"))
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Change a run-time parameter, like `statement_timeout`,
+    /// for the rest of this transaction using `set_config(..., true)`,
+    /// which is equivalent to `SET LOCAL`.
+    ///
+    /// Unlike session settings, this also works with
+    /// PgBouncer's transaction pooling mode.
+    ///
+    /// ```ignore
+    /// let transaction = Transaction::begin().await?;
+    /// transaction.set_local("statement_timeout", "5s").await?;
+    /// ```
+    pub async fn set_local(&self, name: &str, value: &str) -> Result<(), Error> {
+        self.inner
+            .transaction
+            .execute("SELECT set_config($1, $2, true)", &[&name, &value])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Start building a `SELECT` query which is executed
+    /// as part of this transaction when awaited.
+    ///
+    /// The same goes for the other query builders created by this
+    /// transaction. They hold on to it until they are executed or dropped,
+    /// so committing it before fails with [`Error::TransactionInUse`].
+    ///
+    /// ```ignore
+    /// let transaction = Transaction::begin().await?;
+    /// let books = transaction
+    ///     .select::<Book>()
+    ///     .where_(Book::pages.gt(&300))
+    ///     .await?;
+    /// transaction.commit().await?;
+    /// ```
+    pub fn select<'b, M: Model<M>>(&self) -> Select<'b, Vec<M>> {
+        M::select().bound_to(self.inner.clone())
     }
 
-    /// Execute a query  as part of this transaction
+    /// Start building a `SELECT` query which returns either one entity
+    /// or `None`, executed as part of this transaction.
+    ///
+    /// See [`Transaction::select`].
+    pub fn select_one<'b, M: Model<M>>(&self) -> Select<'b, Option<M>> {
+        M::select_one().bound_to(self.inner.clone())
+    }
+
+    /// Start building an `UPDATE` query which is
+    /// executed as part of this transaction.
+    ///
+    /// See [`Transaction::select`].
+    pub fn update<'b, M: Model<M>>(&self) -> Update<'b, NoneSet, u64, M> {
+        M::update().bound_to(self.inner.clone())
+    }
+
+    /// Start building a `DELETE` query which is
+    /// executed as part of this transaction.
+    ///
+    /// See [`Transaction::select`].
+    pub fn delete<'b, M: Model<M>>(&self) -> Delete<'b> {
+        M::delete().bound_to(self.inner.clone())
+    }
+
+    /// Start building an `INSERT` query which is
+    /// executed as part of this transaction.
+    ///
+    /// See [`Transaction::select`].
+    pub fn insert_builder<'b, M: Model<M>>(&self) -> Insert<'b, NoneSet, u64, M> {
+        M::insert_builder().bound_to(self.inner.clone())
+    }
+
+    /// Execute a query as part of this transaction
     /// and return its return value.
+    ///
+    /// Any query builder or [`Query`] can be passed, i.e. everything
+    /// a `Query` can be created from using `From`. The builder is consumed,
+    /// but a [`Select`] can also be passed by reference, which clones it:
+    ///
+    /// ```ignore
+    /// let long_books = Book::select().where_(Book::pages.gt(&300));
+    /// let before = transaction.execute(&long_books).await?;
+    /// transaction.execute(Book::delete().where_(Book::pages.gt(&1000))).await?;
+    /// let after = transaction.execute(long_books).await?;
+    /// ```
     pub async fn execute<'b, Q, T>(&self, into_query: Q) -> Result<T, Error>
     where
         T: QueryOutcome,
         Query<'b, T>: From<Q>,
     {
         let query = Query::from(into_query);
-        T::exec_with(&query.0, query.1.as_slice(), &self.transaction).await
+        let exec = query.exec_with(&self.inner.transaction);
+
+        SCOPED_TRANSACTION.scope(self.inner.clone(), exec).await
+    }
+}
+
+/// Run a future, e.g. executing a query, as part of the
+/// transaction the query is bound to, if any.
+///
+/// See [`Transaction::select`].
+pub(super) async fn run_bound<F: Future>(
+    transaction: Option<Arc<OpenTransaction>>,
+    fut: F,
+) -> F::Output {
+    match transaction {
+        Some(inner) => SCOPED_TRANSACTION.scope(inner, fut).await,
+        None => fut.await,
+    }
+}
+
+/// Pass the rows to `hook` again once the transaction of the enclosing
+/// [`Transaction::scope`] has been committed, if there is one.
+///
+/// E.g. cached entities have to be invalidated after the commit, too,
+/// since a concurrent lookup may cache the old row until then.
+pub(crate) fn after_commit(hook: RowHook, rows: &[Row]) {
+    let _ = SCOPED_TRANSACTION.try_with(|inner| {
+        if let Ok(mut after_commit) = inner.after_commit.lock() {
+            after_commit.push((hook, rows.to_vec()));
+        }
+    });
+}
+
+/// Whether the current task runs inside a [`Transaction::scope`].
+pub(crate) fn scoped() -> bool {
+    SCOPED_TRANSACTION.try_with(|_| ()).is_ok()
+}
+
+/// The client queries are executed on: the transaction of the
+/// enclosing [`Transaction::scope`] or a client from the pool.
+#[doc(hidden)]
+// Boxing the client would cost an allocation for every query
+#[allow(clippy::large_enum_variant)]
+pub enum ScopedClient {
+    /// A client from the pool.
+    Pooled(PgClient),
+    /// The transaction of the enclosing scope.
+    Scoped(Arc<OpenTransaction>),
+}
+
+impl ScopedClient {
+    /// Maps to tokio_postgres::Client::execute.
+    pub async fn execute(
+        &self,
+        stmt: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, tokio_postgres::Error> {
+        let run = async {
+            match self {
+                ScopedClient::Pooled(client) => {
+                    unnamed(
+                        client,
+                        params,
+                        |typed| async move { client.execute_typed(stmt, &typed).await },
+                        || client.execute(stmt, params),
+                    )
+                    .await
+                }
+                ScopedClient::Scoped(inner) => inner.transaction.execute(stmt, params).await,
+            }
+        };
+
+        measured(stmt, run).await
+    }
+
+    /// Maps to tokio_postgres::Client::query_one.
+    pub async fn query_one(
+        &self,
+        stmt: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<PgRow, tokio_postgres::Error> {
+        let run = async {
+            match self {
+                ScopedClient::Pooled(client) => {
+                    unnamed(
+                        client,
+                        params,
+                        |typed| async move { client.query_typed_one(stmt, &typed).await },
+                        || client.query_one(stmt, params),
+                    )
+                    .await
+                }
+                ScopedClient::Scoped(inner) => inner.transaction.query_one(stmt, params).await,
+            }
+        };
+
+        measured(stmt, run).await
+    }
+}
+
+/// Fetch the client queries of the current task are executed on,
+/// see [`ScopedClient`].
+#[doc(hidden)]
+pub async fn fetch_scoped_client() -> Result<ScopedClient, Error> {
+    if let Ok(inner) = SCOPED_TRANSACTION.try_with(Arc::clone) {
+        return Ok(ScopedClient::Scoped(inner));
+    }
+
+    Ok(ScopedClient::Pooled(fetch_client().await?))
+}
+
+#[async_trait]
+impl Executor for &ScopedClient {
+    async fn query(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error> {
+        match self {
+            ScopedClient::Pooled(client) => Executor::query(&&***client, stmt, params).await,
+            ScopedClient::Scoped(inner) => Executor::query(&&inner.transaction, stmt, params).await,
+        }
+    }
+
+    async fn execute(&self, stmt: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
+        match self {
+            ScopedClient::Pooled(client) => Executor::execute(&&***client, stmt, params).await,
+            ScopedClient::Scoped(inner) => {
+                Executor::execute(&&inner.transaction, stmt, params).await
+            }
+        }
     }
 }
@@ -0,0 +1,114 @@
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+
+use super::Query;
+use crate::Error;
+
+/// A `TRUNCATE` statement, which removes all rows of
+/// one or more tables at once.
+///
+/// Truncating several tables in one statement lets tables
+/// referencing each other be cleared regardless of their order,
+/// see [`truncate!`](crate::truncate).
+///
+/// Like other queries it can be executed inside a transaction
+/// using [`Transaction::execute`](super::Transaction::execute).
+///
+/// # Example
+///
+/// ```ignore
+/// Book::truncate().restart_identity().await?;
+/// truncate!(Author, Book).cascade().await?;
+/// ```
+pub struct Truncate {
+    tables: Vec<&'static str>,
+    restart_identity: bool,
+    cascade: bool,
+}
+
+impl Truncate {
+    /// Start building a `TRUNCATE` statement for the given tables.
+    pub fn new(tables: Vec<&'static str>) -> Truncate {
+        Truncate {
+            tables,
+            restart_identity: false,
+            cascade: false,
+        }
+    }
+
+    /// Reset the sequences of the tables' columns,
+    /// so `auto` ids start at 1 again.
+    pub fn restart_identity(mut self) -> Truncate {
+        self.restart_identity = true;
+
+        self
+    }
+
+    /// Also truncate all tables with foreign keys
+    /// referencing one of the tables.
+    pub fn cascade(mut self) -> Truncate {
+        self.cascade = true;
+
+        self
+    }
+
+    /// Get the statement which is executed.
+    pub fn statement(&self) -> String {
+        let mut statement = format!("TRUNCATE TABLE {}", self.tables.join(", "));
+
+        if self.restart_identity {
+            statement.push_str(" RESTART IDENTITY");
+        }
+
+        if self.cascade {
+            statement.push_str(" CASCADE");
+        }
+
+        statement
+    }
+}
+
+impl<'a> From<Truncate> for Query<'a, u64> {
+    fn from(value: Truncate) -> Self {
+        Query::new(value.statement(), Vec::new())
+    }
+}
+
+impl IntoFuture for Truncate {
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+    type Output = Result<u64, Error>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(Query::from(self).exec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use crate::prelude::*;
+
+    #[derive(Model)]
+    struct Book {
+        id: i64,
+    }
+
+    #[derive(Model)]
+    struct Author {
+        id: i64,
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(Book::truncate().statement(), "TRUNCATE TABLE book");
+        assert_eq!(
+            Book::truncate().restart_identity().cascade().statement(),
+            "TRUNCATE TABLE book RESTART IDENTITY CASCADE"
+        );
+        assert_eq!(
+            crate::truncate!(Author, Book).cascade().statement(),
+            "TRUNCATE TABLE author, book CASCADE"
+        );
+    }
+}
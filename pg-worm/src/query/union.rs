@@ -0,0 +1,267 @@
+use std::{
+    future::{Future, IntoFuture},
+    ops::Deref,
+    pin::Pin,
+};
+
+use super::{
+    select::push_limit_offset, ExecResult, PushChunk, Query, QueryOutcome, QueryTiming, Select,
+};
+use crate::{Column, Row};
+
+/// The direction in which to order a query's rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Ascending order, i.e. smallest value first.
+    Asc,
+    /// Descending order, i.e. largest value first.
+    Desc,
+}
+
+/// A struct for building queries which combine multiple
+/// `SELECT`s using `UNION` or `UNION ALL`.
+///
+/// Created by calling [`Select::union`] or [`Select::union_all`].
+///
+/// `ORDER BY`, `LIMIT` and `OFFSET` apply to the combined result.
+pub struct Union<'a, T = Vec<Row>> {
+    first: Select<'a, T>,
+    rest: Vec<(bool, Select<'a, T>)>,
+    order_by: Vec<(Column, Order)>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl<'a, T> Union<'a, T> {
+    /// Start building a union with the given query as the first branch.
+    pub(super) fn new(first: Select<'a, T>) -> Union<'a, T> {
+        Union {
+            // Each branch can't be ordered on its own
+            first: first.without_default_order(),
+            rest: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    /// Add a branch joined by either `UNION ALL` or `UNION`.
+    fn push<U>(mut self, all: bool, other: Select<'a, U>) -> Union<'a, T> {
+        debug_assert!(
+            self.first
+                .columns()
+                .iter()
+                .map(|i| i.column_name())
+                .eq(other.columns().iter().map(|i| i.column_name())),
+            "all branches of a UNION need to select the same columns"
+        );
+
+        self.rest.push((all, other.without_default_order().cast()));
+
+        self
+    }
+
+    /// Add another query using `UNION`, removing duplicate rows.
+    pub fn union<U>(self, other: Select<'a, U>) -> Union<'a, T> {
+        self.push(false, other)
+    }
+
+    /// Add another query using `UNION ALL`, keeping duplicate rows.
+    pub fn union_all<U>(self, other: Select<'a, U>) -> Union<'a, T> {
+        self.push(true, other)
+    }
+
+    /// Order the combined rows by a column.
+    ///
+    /// If called multiple times, the rows are ordered by
+    /// the columns in the order they were added.
+    pub fn order_by(mut self, col: impl Deref<Target = Column>, order: Order) -> Union<'a, T> {
+        self.order_by.push((*col, order));
+
+        self
+    }
+
+    /// Add a `LIMIT` to the combined rows.
+    pub fn limit(mut self, limit: u64) -> Union<'a, T> {
+        self.limit = Some(limit);
+
+        self
+    }
+
+    /// Add an `OFFSET` to the combined rows.
+    pub fn offset(mut self, offset: u64) -> Union<'a, T> {
+        self.offset = Some(offset);
+
+        self
+    }
+
+    /// Also return the number of rows, see [`ExecResult`].
+    pub fn with_count(self) -> Union<'a, ExecResult<T>> {
+        self.cast()
+    }
+
+    /// Also return how long executing the query took, see [`QueryTiming`].
+    pub fn with_timing(self) -> Union<'a, (T, QueryTiming)> {
+        self.cast()
+    }
+
+    /// Change the output type of this query.
+    fn cast<U>(self) -> Union<'a, U> {
+        Union {
+            first: self.first.cast(),
+            rest: self
+                .rest
+                .into_iter()
+                .map(|(all, select)| (all, select.cast()))
+                .collect(),
+            order_by: self.order_by,
+            limit: self.limit,
+            offset: self.offset,
+        }
+    }
+}
+
+impl<'a, T> From<Union<'a, T>> for Query<'a, T> {
+    fn from(mut from: Union<'a, T>) -> Self {
+        let mut buffer = Query {
+            transaction: from.first.take_transaction(),
+            ..Query::default()
+        };
+        let plain = from.first.is_plain() && from.rest.iter().all(|(_, i)| i.is_plain());
+
+        // Push each branch wrapped in parentheses
+        buffer.statement.push('(');
+        from.first.push_to_buffer(&mut buffer);
+        buffer.statement.push(')');
+
+        for (all, select) in &mut from.rest {
+            buffer
+                .statement
+                .push_str(if *all { " UNION ALL (" } else { " UNION (" });
+            select.push_to_buffer(&mut buffer);
+            buffer.statement.push(')');
+        }
+
+        // The output columns of a union can't be
+        // qualified by a table name.
+        if !from.order_by.is_empty() {
+            let order_by = from
+                .order_by
+                .iter()
+                .map(|(col, order)| match order {
+                    Order::Asc => format!("{} ASC", col.column_name()),
+                    Order::Desc => format!("{} DESC", col.column_name()),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            buffer.statement.push_str(" ORDER BY ");
+            buffer.statement.push_str(&order_by);
+        }
+
+        push_limit_offset(&mut buffer, from.limit, from.offset);
+
+        // Only plain selects are safe to retry
+        if plain {
+            buffer.idempotent()
+        } else {
+            buffer
+        }
+    }
+}
+
+impl<'a, T: Sync + Send + 'a> IntoFuture for Union<'a, T>
+where
+    T: QueryOutcome,
+{
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+    type Output = Result<T, crate::Error>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let query: Query<'_, T> = self.into();
+        Box::pin(query.exec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use crate::prelude::*;
+    use crate::query::{Order, Query};
+
+    #[derive(Model)]
+    struct Book {
+        id: i64,
+        title: String,
+    }
+
+    #[derive(Model)]
+    struct BookArchive {
+        id: i64,
+        title: String,
+    }
+
+    #[derive(Model)]
+    struct Author {
+        id: i64,
+        name: String,
+    }
+
+    #[test]
+    fn union_all_limit() {
+        let q: Query<'_, Vec<Book>> = Book::select()
+            .union_all(BookArchive::select())
+            .limit(5)
+            .offset(10)
+            .into();
+
+        assert_eq!(
+            q.statement(),
+            "(SELECT book.id, book.title FROM book) UNION ALL \
+            (SELECT book_archive.id, book_archive.title FROM book_archive) LIMIT $1 OFFSET $2"
+        );
+
+        let params = q
+            .params()
+            .iter()
+            .map(|i| format!("{i:?}"))
+            .collect::<Vec<_>>();
+        assert_eq!(params, ["5", "10"]);
+    }
+
+    #[test]
+    fn union_params() {
+        let title = "Foo".to_string();
+        let q: Query<'_, Vec<Book>> = Book::select()
+            .where_(Book::id.gt(&1))
+            .union(BookArchive::select().where_(BookArchive::title.eq(&title)))
+            .union_all(Book::select().where_(Book::id.lt(&3)).limit(1))
+            .order_by(Book::title, Order::Desc)
+            .order_by(Book::id, Order::Asc)
+            .limit(2)
+            .into();
+
+        assert_eq!(
+            q.statement(),
+            "(SELECT book.id, book.title FROM book WHERE book.id > $1) UNION \
+            (SELECT book_archive.id, book_archive.title FROM book_archive WHERE book_archive.title = $2) UNION ALL \
+            (SELECT book.id, book.title FROM book WHERE book.id < $3 LIMIT $4) \
+            ORDER BY title DESC, id ASC LIMIT $5"
+        );
+
+        let params = q
+            .params()
+            .iter()
+            .map(|i| format!("{i:?}"))
+            .collect::<Vec<_>>();
+        assert_eq!(params, ["1", "\"Foo\"", "3", "1", "2"]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "same columns")]
+    fn union_mixed_columns() {
+        let _ = Book::select().union(Author::select());
+    }
+}
@@ -2,14 +2,18 @@ use std::{
     future::{Future, IntoFuture},
     marker::PhantomData,
     pin::Pin,
+    sync::Arc,
 };
 
 use tokio_postgres::types::ToSql;
 
-use crate::TypedColumn;
+use crate::{cache::Invalidate, Error, FromRow, TypedColumn};
+
+use super::ColumnOf;
 
 use super::{
-    push_all_with_sep, replace_question_marks, PushChunk, Query, QueryOutcome, SqlChunk, Where,
+    push_all_with_sep, ExecResult, OpenTransaction, Param, PushChunk, Query, QueryOutcome,
+    QueryTiming, SqlChunk, Where,
 };
 
 /// State representing that an UPDATE
@@ -27,29 +31,134 @@ pub struct SomeSet;
 ///
 /// The query can only be executed once at least one
 /// update has been made.
-pub struct Update<'a, State = NoneSet> {
+///
+/// By default it returns the number of updated rows,
+/// use [`Update::returning`] to get the rows themselves.
+///
+/// The updates of a model's table, e.g. `Book::update()`, only accept
+/// the model's own columns, see [`TypedColumn`].
+pub struct Update<'a, State = NoneSet, T = u64, M = ()> {
     table: &'static str,
     updates: Vec<SqlChunk<'a>>,
     where_: Where<'a>,
+    returning: bool,
+    idempotent: bool,
+    invalidate: Option<Invalidate>,
     state: PhantomData<State>,
+    output: PhantomData<T>,
+    /// The model whose table is updated.
+    model: PhantomData<fn() -> M>,
+    error: Option<Error>,
+    /// The transaction the query is executed in,
+    /// see [`Transaction::update`](super::Transaction::update).
+    transaction: Option<Arc<OpenTransaction>>,
 }
 
-impl<'a, T> Update<'a, T> {
+impl<'a> Update<'a> {
     /// Begin building a new `UPDATE` query.
     pub fn new(table: &'static str) -> Update<'a, NoneSet> {
         Update {
             table,
             updates: vec![],
             where_: Where::Empty,
+            returning: false,
+            idempotent: false,
+            invalidate: None,
             state: PhantomData::<NoneSet>,
+            output: PhantomData::<u64>,
+            model: PhantomData,
+            error: None,
+            transaction: None,
         }
     }
 
+    /// Only accept the columns of model `M`.
+    #[doc(hidden)]
+    pub fn of_model<M>(self) -> Update<'a, NoneSet, u64, M> {
+        self.cast()
+    }
+}
+
+impl<'a, S, M> Update<'a, S, u64, M> {
+    /// Return the updated rows instead of their number
+    /// by adding `RETURNING *`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let books: Vec<Book> = Book::update()
+    ///     .set(Book::title, &title)
+    ///     .returning()
+    ///     .await?;
+    /// ```
+    pub fn returning<R: FromRow>(mut self) -> Update<'a, S, Vec<R>, M> {
+        self.returning = true;
+
+        self.cast()
+    }
+}
+
+impl<'a, S, T, M> Update<'a, S, T, M> {
+    /// Change the state, output type or model of this query.
+    fn cast<S2, T2, M2>(self) -> Update<'a, S2, T2, M2> {
+        Update {
+            table: self.table,
+            updates: self.updates,
+            where_: self.where_,
+            returning: self.returning,
+            idempotent: self.idempotent,
+            invalidate: self.invalidate,
+            state: PhantomData::<S2>,
+            output: PhantomData::<T2>,
+            model: PhantomData,
+            error: self.error,
+            transaction: self.transaction,
+        }
+    }
+
+    /// Execute this query as part of a transaction.
+    pub(super) fn bound_to(mut self, transaction: Arc<OpenTransaction>) -> Update<'a, S, T, M> {
+        self.transaction = Some(transaction);
+
+        self
+    }
+
+    /// Mark this query as safe to run more than once,
+    /// which allows retrying it after a transient error.
+    ///
+    /// Only do this if running the update twice has the same effect
+    /// as running it once, e.g. when setting columns to fixed values.
+    ///
+    /// See [`ConnectionBuilder::retry_policy`](crate::pool::ConnectionBuilder::retry_policy).
+    pub fn idempotent(mut self) -> Update<'a, S, T, M> {
+        self.idempotent = true;
+
+        self
+    }
+
+    /// Invalidate the cached entities of the updated rows.
+    #[doc(hidden)]
+    pub fn invalidating(mut self, invalidate: Option<Invalidate>) -> Update<'a, S, T, M> {
+        self.invalidate = invalidate;
+
+        self
+    }
+
+    /// Also return the number of updated rows, see [`ExecResult`].
+    pub fn with_count(self) -> Update<'a, S, ExecResult<T>, M> {
+        self.cast()
+    }
+
+    /// Also return how long executing the query took, see [`QueryTiming`].
+    pub fn with_timing(self) -> Update<'a, S, (T, QueryTiming), M> {
+        self.cast()
+    }
+
     /// Add a `WHERE` to the query.
     ///
     /// If called multiple times, the conditions are
     /// joined using `AND`.
-    pub fn where_(mut self, where_: Where<'a>) -> Update<'a, T> {
+    pub fn where_(mut self, where_: Where<'a>) -> Update<'a, S, T, M> {
         self.where_ = self.where_.and(where_);
 
         self
@@ -76,7 +185,7 @@ impl<'a, T> Update<'a, T> {
         self,
         statement: impl Into<String>,
         params: Vec<&'a (dyn ToSql + Sync)>,
-    ) -> Update<'a, T> {
+    ) -> Update<'a, S, T, M> {
         let where_ = Where::new(statement.into(), params);
 
         self.where_(where_)
@@ -86,58 +195,330 @@ impl<'a, T> Update<'a, T> {
     ///
     /// This function has to be called at least once before
     /// you can execute the query.
-    pub fn set<U: ToSql + Sync>(
+    ///
+    /// If the value doesn't satisfy the column's constraints
+    /// (e.g. its `max_length`), executing the query returns
+    /// an [`Error::Validation`].
+    pub fn set<U: ToSql + Sync, C: ColumnOf<M>>(
         mut self,
-        col: TypedColumn<U>,
+        col: TypedColumn<U, C>,
         value: &'a U,
-    ) -> Update<'a, SomeSet> {
-        self.updates
-            .push(SqlChunk(format!("{} = ?", col.column_name), vec![value]));
-
-        Update {
-            state: PhantomData::<SomeSet>,
-            updates: self.updates,
-            where_: self.where_,
-            table: self.table,
+    ) -> Update<'a, SomeSet, T, M> {
+        if let Err(err) = col.validate(value) {
+            self.error.get_or_insert(err);
         }
+
+        self.updates.push(SqlChunk(
+            format!("{} = {}", col.column_name, col.insert_expr()),
+            vec![col.bind(value)],
+        ));
+
+        self.cast()
     }
+
+    /// Append a value to an array column, i.e.
+    /// `SET {col} = array_append({col}, ?)`.
+    ///
+    /// Like [`Update::set`], this can be combined with other
+    /// updates, as long as each column is only updated once.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Book::update()
+    ///     .array_append(Book::pages, &page)
+    ///     .where_(Book::id.eq(&id))
+    ///     .await?;
+    /// ```
+    pub fn array_append<U: ToSql + Sync, C: ColumnOf<M>>(
+        self,
+        col: TypedColumn<Vec<U>, C>,
+        value: &'a U,
+    ) -> Update<'a, SomeSet, T, M> {
+        self.array_update(col, "array_append", value)
+    }
+
+    /// Remove all elements equal to a value from an array column,
+    /// i.e. `SET {col} = array_remove({col}, ?)`.
+    pub fn array_remove<U: ToSql + Sync, C: ColumnOf<M>>(
+        self,
+        col: TypedColumn<Vec<U>, C>,
+        value: &'a U,
+    ) -> Update<'a, SomeSet, T, M> {
+        self.array_update(col, "array_remove", value)
+    }
+
+    /// Append all values of another array to an array column,
+    /// i.e. `SET {col} = array_cat({col}, ?)`.
+    pub fn array_cat<U: ToSql + Sync, C: ColumnOf<M>>(
+        self,
+        col: TypedColumn<Vec<U>, C>,
+        values: &'a Vec<U>,
+    ) -> Update<'a, SomeSet, T, M> {
+        self.array_update(col, "array_cat", values)
+    }
+
+    /// Set an array column to the result of calling
+    /// `function` with the column and a value.
+    fn array_update<U: ToSql + Sync, C>(
+        mut self,
+        col: TypedColumn<Vec<U>, C>,
+        function: &str,
+        value: &'a (dyn ToSql + Sync),
+    ) -> Update<'a, SomeSet, T, M> {
+        self.updates.push(SqlChunk(
+            format!("{0} = {function}({0}, ?)", col.column_name),
+            vec![Param::from(value)],
+        ));
+
+        self.cast()
+    }
+}
+
+#[cfg(feature = "serde-json")]
+impl<'a, S, T, M> Update<'a, S, T, M> {
+    /// Set the value at a path inside a `jsonb` column, i.e.
+    /// `SET {col} = jsonb_set({col}, ARRAY[?, ..]::text[], ?, {create_missing})`,
+    /// instead of replacing the whole document.
+    ///
+    /// Array elements are addressed by their index, e.g. `&["tags", "0"]`.
+    /// If `create_missing` is `false`, nothing is changed if the path
+    /// doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let theme = json!("dark");
+    /// User::update()
+    ///     .jsonb_set(User::settings, &["ui", "theme"], &theme, true)
+    ///     .where_(User::id.eq(&id))
+    ///     .await?;
+    /// ```
+    pub fn jsonb_set<C: ColumnOf<M>>(
+        mut self,
+        col: TypedColumn<serde_json::Value, C>,
+        path: &'a [&'a str],
+        value: &'a serde_json::Value,
+        create_missing: bool,
+    ) -> Update<'a, SomeSet, T, M> {
+        let mut params = json_path_params(path);
+        params.push(Param::from(value));
+
+        let create_missing = if create_missing { "TRUE" } else { "FALSE" };
+        self.updates.push(SqlChunk(
+            format!(
+                "{0} = jsonb_set({0}, {1}, ?, {create_missing})",
+                col.column_name,
+                json_path(path)
+            ),
+            params,
+        ));
+
+        self.cast()
+    }
+
+    /// Remove the value at a path inside a `jsonb` column, i.e.
+    /// `SET {col} = {col} #- ARRAY[?, ..]::text[]`.
+    pub fn jsonb_remove<C: ColumnOf<M>>(
+        mut self,
+        col: TypedColumn<serde_json::Value, C>,
+        path: &'a [&'a str],
+    ) -> Update<'a, SomeSet, T, M> {
+        self.updates.push(SqlChunk(
+            format!("{0} = {0} #- {1}", col.column_name, json_path(path)),
+            json_path_params(path),
+        ));
+
+        self.cast()
+    }
+}
+
+/// The SQL of a path into a `jsonb` document, with
+/// one placeholder per key, see [`json_path_params`].
+///
+/// The keys are bound one by one, since
+/// the slice itself can't be borrowed as a parameter.
+#[cfg(feature = "serde-json")]
+fn json_path(path: &[&str]) -> String {
+    format!("ARRAY[{}]::text[]", vec!["?"; path.len()].join(", "))
 }
 
-impl<'a> From<Update<'a, SomeSet>> for Query<'a, u64> {
-    fn from(mut from: Update<'a, SomeSet>) -> Self {
+/// The parameters of a path into a `jsonb` document.
+#[cfg(feature = "serde-json")]
+fn json_path_params<'a>(path: &'a [&'a str]) -> Vec<Param<'a>> {
+    path.iter().map(Param::from).collect()
+}
+
+impl<'a, T, M> From<Update<'a, SomeSet, T, M>> for Query<'a, T> {
+    fn from(mut from: Update<'a, SomeSet, T, M>) -> Self {
         let mut buffer = Query::default();
 
         // Which table to update
-        buffer.0.push_str("UPDATE ");
-        buffer.0.push_str(from.table);
+        buffer.statement.push_str("UPDATE ");
+        buffer.statement.push_str(from.table);
 
         // Which updates to make
-        buffer.0.push_str(" SET ");
+        buffer.statement.push_str(" SET ");
         push_all_with_sep(&mut from.updates, &mut buffer, ", ");
 
         // Which rows to update
         if !from.where_.is_empty() {
-            buffer.0.push_str(" WHERE ");
+            buffer.statement.push_str(" WHERE ");
             from.where_.push_to_buffer(&mut buffer);
         }
 
-        buffer.0 = replace_question_marks(buffer.0);
+        if from.returning {
+            buffer.statement.push_str(" RETURNING *");
+        } else if let Some(invalidate) = from.invalidate {
+            buffer.statement.push_str(" RETURNING ");
+            buffer.statement.push_str(&invalidate.column.full_name());
+        }
+        buffer.inspect = from.invalidate.map(|i| i.hook);
+
+        if from.idempotent {
+            buffer = buffer.idempotent();
+        }
+
+        if let Some(err) = from.error {
+            buffer.fail(err);
+        }
+        buffer.transaction = from.transaction;
 
         buffer
     }
 }
 
-impl<'a> IntoFuture for Update<'a, SomeSet>
+impl<'a, T, M> IntoFuture for Update<'a, SomeSet, T, M>
 where
-    Query<'a, u64>: From<Update<'a, SomeSet>>,
+    T: QueryOutcome + Send + Sync + 'a,
 {
-    type Output = Result<u64, crate::Error>;
+    type Output = Result<T, crate::Error>;
 
-    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'a>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
 
     fn into_future(self) -> Self::IntoFuture {
         let query = Query::from(self);
 
-        Box::pin(async move { u64::exec(&query.0, query.1.as_slice()).await })
+        Box::pin(query.exec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use crate::prelude::*;
+    use crate::query::{ExecResult, Query, TypedColumn, Update};
+
+    #[derive(Model)]
+    struct Book {
+        #[column(primary_key, auto)]
+        id: i64,
+        title: String,
+        pages: Vec<String>,
+    }
+
+    #[test]
+    fn update_returning() {
+        let title = "Foo".to_string();
+
+        let q: Query<'_, u64> = Book::update()
+            .set(Book::title, &title)
+            .where_(Book::id.eq(&1))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE book SET title = $1 WHERE book.id = $2"
+        );
+
+        let q: Query<'_, ExecResult<Vec<Book>>> = Book::update()
+            .returning()
+            .set(Book::title, &title)
+            .where_(Book::id.eq(&1))
+            .with_count()
+            .into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE book SET title = $1 WHERE book.id = $2 RETURNING *"
+        );
+    }
+
+    #[test]
+    fn untyped_columns() {
+        let title = "Foo".to_string();
+
+        // Columns which aren't tied to a model work everywhere
+        let untyped = TypedColumn::<String>::new("book", "title");
+        let q: Query<'_, u64> = Book::update().set(untyped, &title).into();
+        assert_eq!(q.statement(), "UPDATE book SET title = $1");
+
+        // And so do builders which aren't tied to one
+        let q: Query<'_, u64> = Update::new("book").set(Book::title, &title).into();
+        assert_eq!(q.statement(), "UPDATE book SET title = $1");
+    }
+
+    #[test]
+    fn array_updates() {
+        let title = "Foo".to_string();
+        let page = "Bar".to_string();
+        let pages = vec!["Baz".to_string()];
+
+        let q: Query<'_, u64> = Book::update()
+            .set(Book::title, &title)
+            .array_append(Book::pages, &page)
+            .where_(Book::id.eq(&1))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE book SET title = $1, pages = array_append(pages, $2) WHERE book.id = $3"
+        );
+
+        let q: Query<'_, u64> = Book::update().array_remove(Book::pages, &page).into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE book SET pages = array_remove(pages, $1)"
+        );
+
+        let q: Query<'_, Vec<Book>> = Book::update()
+            .array_cat(Book::pages, &pages)
+            .returning()
+            .into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE book SET pages = array_cat(pages, $1) RETURNING *"
+        );
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn jsonb_updates() {
+        #[derive(Model)]
+        struct Profile {
+            #[column(primary_key, auto)]
+            id: i64,
+            settings: serde_json::Value,
+            flags: serde_json::Value,
+        }
+
+        let theme = serde_json::json!("dark");
+        let q: Query<'_, u64> = Profile::update()
+            .jsonb_set(Profile::settings, &["ui", "theme"], &theme, true)
+            .where_(Profile::id.eq(&1))
+            .into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE profile SET settings = jsonb_set(settings, ARRAY[$1, $2]::text[], $3, TRUE) \
+            WHERE profile.id = $4"
+        );
+
+        let q: Query<'_, u64> = Profile::update()
+            .jsonb_set(Profile::settings, &["beta"], &theme, false)
+            .jsonb_remove(Profile::flags, &["ui", "font"])
+            .into();
+        assert_eq!(
+            q.statement(),
+            "UPDATE profile SET settings = jsonb_set(settings, ARRAY[$1]::text[], $2, FALSE), \
+            flags = flags #- ARRAY[$3, $4]::text[]"
+        );
     }
 }
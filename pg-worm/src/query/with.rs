@@ -0,0 +1,300 @@
+use tokio_postgres::types::ToSql;
+
+use super::{number_placeholders, Param, PushChunk, Query, Select, SqlChunk};
+
+/// A single common table expression, i.e. `name AS (...)`.
+#[derive(Clone)]
+struct Cte<'a> {
+    name: String,
+    body: SqlChunk<'a>,
+}
+
+/// The `WITH` clause of a query, containing any number
+/// of common table expressions.
+#[derive(Clone, Default)]
+pub(super) struct With<'a> {
+    ctes: Vec<Cte<'a>>,
+    recursive: bool,
+    /// Whether a CTE has a raw body, which may modify data.
+    raw: bool,
+}
+
+/// Keeps track of the common table expressions which have
+/// been prepended to an already built [`Query`].
+#[derive(Clone, Copy, Default)]
+pub(super) struct WithState {
+    /// The position in the statement right after the last CTE.
+    end: usize,
+    /// The number of parameters used by the CTEs.
+    params: usize,
+}
+
+impl<'a> With<'a> {
+    /// Add a common table expression.
+    pub(super) fn push(&mut self, name: String, body: SqlChunk<'a>, recursive: bool) {
+        self.recursive |= recursive;
+        self.ctes.push(Cte { name, body });
+    }
+
+    /// Add a common table expression with a raw body.
+    pub(super) fn push_raw(&mut self, name: String, body: SqlChunk<'a>, recursive: bool) {
+        self.raw = true;
+        self.push(name, body, recursive);
+    }
+
+    /// Whether all common table expressions are plain `SELECT`s,
+    /// so the query doesn't modify any data.
+    pub(super) fn is_plain(&self) -> bool {
+        !self.raw
+    }
+
+    /// Prepend all common table expressions to an already built query.
+    pub(super) fn prepend_to<T>(self, query: &mut Query<'a, T>) {
+        for cte in self.ctes {
+            query.push_cte(&cte.name, cte.body, self.recursive);
+        }
+    }
+}
+
+impl<'a> PushChunk<'a> for With<'a> {
+    fn push_to_buffer<T>(&mut self, buffer: &mut Query<'a, T>) {
+        if self.ctes.is_empty() {
+            return;
+        }
+
+        buffer.statement.push_str(if self.recursive {
+            "WITH RECURSIVE "
+        } else {
+            "WITH "
+        });
+
+        for (i, cte) in self.ctes.iter_mut().enumerate() {
+            if i > 0 {
+                buffer.statement.push_str(", ");
+            }
+
+            buffer.statement.push_str(&cte.name);
+            buffer.statement.push_str(" AS (");
+            cte.body.push_to_buffer(buffer);
+            buffer.statement.push(')');
+        }
+
+        buffer.statement.push(' ');
+    }
+}
+
+impl<'a, T> From<Select<'a, T>> for SqlChunk<'a> {
+    fn from(mut from: Select<'a, T>) -> Self {
+        let mut buffer = Query::<'a, ()>::default();
+        from.push_to_buffer(&mut buffer);
+
+        SqlChunk(buffer.statement, buffer.params)
+    }
+}
+
+impl<'a, T> Query<'a, T> {
+    /// Prepend a common table expression to this query.
+    ///
+    /// It is placed after any previously added CTEs, and
+    /// the placeholders of the main statement are renumbered
+    /// to make room for the CTE's parameters.
+    pub(super) fn push_cte(&mut self, name: &str, body: SqlChunk<'a>, recursive: bool) {
+        let SqlChunk(stmt, params) = body;
+        let WithState {
+            end,
+            params: offset,
+        } = self.with;
+
+        let cte = format!("{name} AS ({})", number_placeholders(&stmt, offset));
+        // The main statement is numbered already, so this only shifts it
+        let tail = number_placeholders(&self.statement[end..], params.len());
+
+        let mut buf = if end == 0 {
+            String::from("WITH ")
+        } else {
+            format!("{}, ", &self.statement[..end])
+        };
+        buf.push_str(&cte);
+        let mut end = buf.len();
+        if self.with.end == 0 {
+            buf.push(' ');
+        }
+        buf.push_str(&tail);
+
+        if recursive && !buf.starts_with("WITH RECURSIVE ") {
+            buf.replace_range(..5, "WITH RECURSIVE ");
+            end += "RECURSIVE ".len();
+        }
+
+        self.statement = buf;
+        self.with = WithState {
+            end,
+            params: offset + params.len(),
+        };
+        self.params.splice(offset..offset, params);
+    }
+
+    /// Prepend a common table expression (`WITH name AS (...)`)
+    /// to this query.
+    ///
+    /// If called multiple times, the CTEs are defined in the order
+    /// they were added, so later ones may reference earlier ones.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Query::<Vec<Book>>::new("SELECT * FROM book WHERE id IN (SELECT id FROM cheap)".into(), vec![])
+    ///     .with("cheap", Book::select().where_(Book::price.lt(&10)))
+    ///     .await?;
+    /// ```
+    pub fn with<U>(mut self, name: impl Into<String>, query: Select<'a, U>) -> Query<'a, T> {
+        self.idempotent &= query.is_plain();
+        self.push_cte(&name.into(), query.into(), false);
+
+        self
+    }
+
+    /// Prepend a common table expression with a raw body to this query.
+    ///
+    /// You can reference the `params` by using the `?` placeholder in your statement.
+    /// Since the body may modify data, the query isn't considered
+    /// [`idempotent`](Query::idempotent) anymore.
+    pub fn with_raw(
+        mut self,
+        name: impl Into<String>,
+        statement: impl Into<String>,
+        params: Vec<&'a (dyn ToSql + Sync)>,
+    ) -> Query<'a, T> {
+        self.idempotent = false;
+        self.push_cte(
+            &name.into(),
+            SqlChunk(
+                statement.into(),
+                params.into_iter().map(Param::from).collect(),
+            ),
+            false,
+        );
+
+        self
+    }
+
+    /// Prepend a recursive common table expression to this query.
+    ///
+    /// This turns the query's `WITH` into `WITH RECURSIVE`.
+    /// You can reference the `params` by using the `?` placeholder in your statement.
+    /// Like [`Query::with_raw`], this keeps the query from being retried.
+    pub fn with_recursive(
+        mut self,
+        name: impl Into<String>,
+        statement: impl Into<String>,
+        params: Vec<&'a (dyn ToSql + Sync)>,
+    ) -> Query<'a, T> {
+        self.idempotent = false;
+        self.push_cte(
+            &name.into(),
+            SqlChunk(
+                statement.into(),
+                params.into_iter().map(Param::from).collect(),
+            ),
+            true,
+        );
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use crate::prelude::*;
+    use crate::query::Query;
+
+    #[derive(Model)]
+    struct Book {
+        id: i64,
+        title: String,
+        category_id: i64,
+    }
+
+    #[derive(Model)]
+    struct Category {
+        id: i64,
+        parent_id: Option<i64>,
+    }
+
+    fn params<T>(q: &Query<'_, T>) -> Vec<String> {
+        q.params().iter().map(|i| format!("{i:?}")).collect()
+    }
+
+    #[test]
+    fn select_with_two_ctes() {
+        let title = "Dune".to_string();
+        let q: Query<'_, Vec<Book>> = Book::select()
+            .with("a", Category::select().where_(Category::id.gt(&1)))
+            .with_raw(
+                "b",
+                "SELECT id FROM a WHERE id < ? OR id = ?",
+                vec![&5i64, &7i64],
+            )
+            .where_(Book::title.eq(&title))
+            .where_raw("book.category_id IN (SELECT id FROM b)", vec![])
+            .into();
+
+        assert_eq!(
+            q.statement(),
+            "WITH a AS (SELECT category.id, category.parent_id FROM category WHERE category.id > $1), \
+            b AS (SELECT id FROM a WHERE id < $2 OR id = $3) \
+            SELECT book.id, book.title, book.category_id FROM book \
+            WHERE (book.title = $4) AND (book.category_id IN (SELECT id FROM b))"
+        );
+        assert_eq!(params(&q), ["1", "5", "7", "\"Dune\""]);
+    }
+
+    #[test]
+    fn query_with_two_ctes() {
+        let q: Query<'_, Vec<Book>> = Query::new(
+            "SELECT * FROM book WHERE id > ? AND category_id IN (SELECT id FROM b)".into(),
+            vec![&3i64],
+        )
+        .with("a", Category::select().where_(Category::id.gt(&1)))
+        .with_raw("b", "SELECT id FROM a WHERE id < ?", vec![&5i64]);
+
+        assert_eq!(
+            q.statement(),
+            "WITH a AS (SELECT category.id, category.parent_id FROM category WHERE category.id > $1), \
+            b AS (SELECT id FROM a WHERE id < $2) \
+            SELECT * FROM book WHERE id > $3 AND category_id IN (SELECT id FROM b)"
+        );
+        assert_eq!(params(&q), ["1", "5", "3"]);
+    }
+
+    #[test]
+    fn recursive() {
+        let q: Query<'_, Vec<Book>> = Book::select()
+            .with_recursive(
+                "subtree",
+                "SELECT id FROM category WHERE id = ? \
+                UNION ALL SELECT c.id FROM category c JOIN subtree s ON c.parent_id = s.id",
+                vec![&1i64],
+            )
+            .where_raw("book.category_id IN (SELECT id FROM subtree)", vec![])
+            .where_(Book::id.lt(&2))
+            .into();
+
+        assert_eq!(
+            q.statement(),
+            "WITH RECURSIVE subtree AS (SELECT id FROM category WHERE id = $1 \
+            UNION ALL SELECT c.id FROM category c JOIN subtree s ON c.parent_id = s.id) \
+            SELECT book.id, book.title, book.category_id FROM book \
+            WHERE (book.category_id IN (SELECT id FROM subtree)) AND (book.id < $2)"
+        );
+
+        // Adding a recursive CTE later turns the whole clause recursive
+        let q = q.with_recursive("x", "SELECT ?", vec![&3i64]);
+        assert!(q.statement().starts_with("WITH RECURSIVE subtree AS ("));
+        assert!(q.statement().contains("), x AS (SELECT $2) SELECT"));
+        assert!(q.statement().ends_with("(book.id < $3)"));
+        assert_eq!(params(&q), ["1", "3", "2"]);
+    }
+}
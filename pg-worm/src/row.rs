@@ -0,0 +1,75 @@
+//! This module contains the [`Row`] type which models are parsed from.
+
+use std::{fmt, ops::Deref};
+
+/// A single row returned by the database.
+///
+/// It is a thin wrapper around the row of the underlying driver,
+/// so implementing [`FromRow`](crate::FromRow) doesn't tie your code
+/// to a specific version of `tokio_postgres`. For anything not covered
+/// here, it dereferences to the inner row.
+///
+/// # Example
+///
+/// ```ignore
+/// struct Author {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// impl TryFrom<Row> for Author {
+///     type Error = pg_worm::Error;
+///
+///     fn try_from(row: Row) -> Result<Author, Self::Error> {
+///         Author::try_from(&row)
+///     }
+/// }
+///
+/// impl TryFrom<&Row> for Author {
+///     type Error = pg_worm::Error;
+///
+///     fn try_from(row: &Row) -> Result<Author, Self::Error> {
+///         Ok(Author {
+///             id: row.try_get("id")?,
+///             name: row.try_get("name")?,
+///         })
+///     }
+/// }
+///
+/// impl FromRow for Author {}
+/// ```
+#[derive(Clone)]
+pub struct Row(tokio_postgres::Row);
+
+impl Row {
+    /// Get the row of the underlying driver.
+    pub fn into_inner(self) -> tokio_postgres::Row {
+        self.0
+    }
+}
+
+impl Deref for Row {
+    type Target = tokio_postgres::Row;
+
+    fn deref(&self) -> &tokio_postgres::Row {
+        &self.0
+    }
+}
+
+impl From<tokio_postgres::Row> for Row {
+    fn from(row: tokio_postgres::Row) -> Row {
+        Row(row)
+    }
+}
+
+impl From<Row> for tokio_postgres::Row {
+    fn from(row: Row) -> tokio_postgres::Row {
+        row.0
+    }
+}
+
+impl fmt::Debug for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -0,0 +1,237 @@
+use std::{error::Error as StdError, fmt};
+
+use serde_json::Value;
+use tokio_postgres::types::{FromSql, Type};
+
+use crate::{
+    pool::fetch_client,
+    query::{Param, Query},
+    Error,
+};
+
+/// The plan the database chose for a query, see [`explain`].
+///
+/// Its `Display` implementation shows the plan as
+/// returned by the database.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    root: PlanNode,
+    text: String,
+}
+
+/// A single node of a [`Plan`], e.g. a `Seq Scan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanNode {
+    node_type: String,
+    relation_name: Option<String>,
+    index_name: Option<String>,
+    children: Vec<PlanNode>,
+}
+
+impl Plan {
+    /// Get the node at the top of the plan.
+    pub fn root(&self) -> &PlanNode {
+        &self.root
+    }
+
+    /// Iterate over all nodes of the plan, depth-first.
+    pub fn nodes(&self) -> impl Iterator<Item = &PlanNode> {
+        let mut stack = vec![&self.root];
+
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+
+            Some(node)
+        })
+    }
+
+    /// Whether an `Index Scan`, `Index Only Scan` or `Bitmap Index Scan`
+    /// node uses the index with this name.
+    pub fn uses_index(&self, index: &str) -> bool {
+        self.nodes().any(|node| {
+            matches!(
+                node.node_type(),
+                "Index Scan" | "Index Only Scan" | "Bitmap Index Scan"
+            ) && node.index_name() == Some(index)
+        })
+    }
+
+    /// Whether a `Seq Scan` node reads this table.
+    pub fn has_seq_scan(&self, table: &str) -> bool {
+        self.nodes()
+            .any(|node| node.node_type() == "Seq Scan" && node.relation_name() == Some(table))
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PlanNode {
+    /// Get the node's type, e.g. `Index Scan`.
+    pub fn node_type(&self) -> &str {
+        &self.node_type
+    }
+
+    /// Get the name of the table this node reads, if any.
+    pub fn relation_name(&self) -> Option<&str> {
+        self.relation_name.as_deref()
+    }
+
+    /// Get the name of the index this node uses, if any.
+    pub fn index_name(&self) -> Option<&str> {
+        self.index_name.as_deref()
+    }
+
+    /// Get the nodes whose output this node uses.
+    pub fn children(&self) -> &[PlanNode] {
+        &self.children
+    }
+
+    /// Get the root node from the output of `EXPLAIN (FORMAT JSON)`,
+    /// which is a list containing a single object.
+    fn from_output(json: &Value) -> Option<PlanNode> {
+        PlanNode::from_json(json.get(0)?.get("Plan")?)
+    }
+
+    /// Build a node from its JSON representation.
+    fn from_json(json: &Value) -> Option<PlanNode> {
+        let string = |key| json.get(key)?.as_str().map(str::to_string);
+        let children = match json.get("Plans") {
+            Some(Value::Array(plans)) => plans
+                .iter()
+                .map(PlanNode::from_json)
+                .collect::<Option<_>>()?,
+            _ => Vec::new(),
+        };
+
+        Some(PlanNode {
+            node_type: string("Node Type")?,
+            relation_name: string("Relation Name"),
+            index_name: string("Index Name"),
+            children,
+        })
+    }
+}
+
+/// Get the plan the database chooses for a query, without executing it.
+///
+/// ```ignore
+/// let plan = explain(Book::select().where_(Book::title.eq(&"Foo"))).await?;
+/// assert!(plan.uses_index("book_title_idx"), "{plan}");
+/// ```
+pub async fn explain<'a, T>(query: impl Into<Query<'a, T>>) -> Result<Plan, Error> {
+    let mut query = query.into();
+    if let Some(err) = query.take_error() {
+        return Err(err);
+    }
+
+    let stmt = format!("EXPLAIN (FORMAT JSON) {}", query.statement());
+    let row = fetch_client()
+        .await?
+        .query_one(&stmt, &Param::values(query.params()))
+        .await
+        .map_err(|err| Error::from_query(err, &stmt))?;
+    let RawJson(text) = row.try_get(0)?;
+
+    let invalid = || Error::ParseError("Plan", "QUERY PLAN");
+    let root = serde_json::from_str(&text)
+        .ok()
+        .as_ref()
+        .and_then(PlanNode::from_output)
+        .ok_or_else(invalid)?;
+
+    Ok(Plan { root, text })
+}
+
+/// Assert that the database uses the index with this name
+/// to execute a query.
+///
+/// # Panics
+///
+/// Panics, showing the full plan, if no `Index Scan`, `Index Only Scan`
+/// or `Bitmap Index Scan` uses the index.
+pub async fn assert_index_used<'a, T>(
+    query: impl Into<Query<'a, T>>,
+    index: &str,
+) -> Result<(), Error> {
+    let plan = explain(query).await?;
+    assert!(
+        plan.uses_index(index),
+        "the query doesn't use index {index}:\n{plan}"
+    );
+
+    Ok(())
+}
+
+/// Assert that the database doesn't read the whole table
+/// to execute a query.
+///
+/// # Panics
+///
+/// Panics, showing the full plan, if a `Seq Scan` reads the table.
+pub async fn assert_no_seq_scan<'a, T>(
+    query: impl Into<Query<'a, T>>,
+    table: &str,
+) -> Result<(), Error> {
+    let plan = explain(query).await?;
+    assert!(
+        !plan.has_seq_scan(table),
+        "the query scans table {table} sequentially:\n{plan}"
+    );
+
+    Ok(())
+}
+
+/// The text of a `json` value.
+struct RawJson(String);
+
+impl<'a> FromSql<'a> for RawJson {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<RawJson, Box<dyn StdError + Sync + Send>> {
+        Ok(RawJson(std::str::from_utf8(raw)?.to_string()))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::JSON
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_plan() {
+        let text = r#"[{"Plan": {
+            "Node Type": "Nested Loop",
+            "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "author"},
+                {"Node Type": "Bitmap Heap Scan", "Relation Name": "book", "Plans": [
+                    {"Node Type": "Bitmap Index Scan", "Index Name": "book_author_idx"}
+                ]}
+            ]
+        }}]"#;
+        let plan = Plan {
+            root: PlanNode::from_output(&serde_json::from_str(text).unwrap()).unwrap(),
+            text: text.to_string(),
+        };
+
+        let types: Vec<_> = plan.nodes().map(PlanNode::node_type).collect();
+        assert_eq!(
+            types,
+            [
+                "Nested Loop",
+                "Seq Scan",
+                "Bitmap Heap Scan",
+                "Bitmap Index Scan"
+            ]
+        );
+        assert!(plan.uses_index("book_author_idx"));
+        assert!(!plan.uses_index("book_pkey"));
+        assert!(plan.has_seq_scan("author"));
+        assert!(!plan.has_seq_scan("book"));
+    }
+}
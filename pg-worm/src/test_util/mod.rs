@@ -0,0 +1,141 @@
+//! This module contains utilities for testing code which uses `pg-worm`.
+
+#[cfg(feature = "serde-json")]
+mod explain;
+
+use std::{
+    future::Future,
+    panic::{resume_unwind, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use futures_util::FutureExt;
+
+#[cfg(feature = "serde-json")]
+pub use explain::{assert_index_used, assert_no_seq_scan, explain, Plan, PlanNode};
+
+use crate::{
+    pool::{checkout_error, in_sandbox, sandbox_pool, Connection, Pool},
+    Error,
+};
+
+/// This module contains the items needed in tests, including
+/// everything from the main [`prelude`](crate::prelude).
+pub mod prelude {
+    pub use super::SchemaSandbox;
+    #[cfg(feature = "serde-json")]
+    pub use super::{assert_index_used, assert_no_seq_scan};
+    pub use crate::prelude::*;
+    pub use crate::{create_table_if_missing, force_create_table, try_create_table};
+}
+
+/// A schema of its own for a test, so tests can run
+/// in parallel against the same database.
+///
+/// Queries run using [`SchemaSandbox::run`] use connections whose
+/// `search_path` starts with the sandbox's schema, so tables are
+/// created and queried there. Prepared statements are executed like
+/// regular queries. The migration functions only look at the
+/// `public` schema, though.
+///
+/// The sandbox's connections, including the ones used to create and
+/// drop its schema, are driven by the runtime it was created on.
+///
+/// Usually it's easier to use the [`#[pg_worm::test]`](crate::test) attribute.
+///
+/// # Example
+///
+/// ```ignore
+/// let sandbox = SchemaSandbox::new().await?;
+/// sandbox.run(async {
+///     force_create_table!(Book).await?;
+///     Book::insert("Foo").await
+/// }).await?;
+/// sandbox.tear_down().await?;
+/// ```
+pub struct SchemaSandbox {
+    schema: String,
+    pool: Pool,
+}
+
+impl SchemaSandbox {
+    /// Create a schema with a unique name, using the current connection's settings.
+    pub async fn new() -> Result<SchemaSandbox, Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let schema = format!(
+            "worm_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let sandbox = SchemaSandbox {
+            pool: sandbox_pool(&schema)?,
+            schema,
+        };
+        sandbox
+            .batch_execute(&format!(
+                "DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0}",
+                sandbox.schema
+            ))
+            .await?;
+
+        Ok(sandbox)
+    }
+
+    /// Get the name of the sandbox's schema.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Run a future whose queries use the sandbox's schema.
+    pub fn run<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+        in_sandbox(self.pool.clone(), fut)
+    }
+
+    /// Run a future whose queries use the sandbox's schema and
+    /// tear the sandbox down afterwards, even if the future panics.
+    ///
+    /// The panic is resumed once the schema has been dropped.
+    pub async fn run_and_tear_down<F: Future>(self, fut: F) -> F::Output {
+        let res = AssertUnwindSafe(self.run(fut)).catch_unwind().await;
+        self.tear_down()
+            .await
+            .expect("pg-worm: couldn't drop the sandbox schema");
+
+        match res {
+            Ok(res) => res,
+            Err(panic) => resume_unwind(panic),
+        }
+    }
+
+    /// Drop the schema including all of its tables.
+    pub async fn tear_down(self) -> Result<(), Error> {
+        let res = self
+            .batch_execute(&format!("DROP SCHEMA IF EXISTS {} CASCADE", self.schema))
+            .await;
+        self.pool.close();
+
+        res
+    }
+
+    /// Execute statements using one of the sandbox's own connections.
+    async fn batch_execute(&self, statements: &str) -> Result<(), Error> {
+        let client = self
+            .pool
+            .get()
+            .await
+            .map_err(|err| checkout_error(&self.pool, err))?;
+        client.batch_execute(statements).await?;
+
+        Ok(())
+    }
+}
+
+/// Connect to the database unless there already is a connection,
+/// e.g. from another test.
+pub async fn connect_once(connection_string: impl Into<String>) -> Result<(), Error> {
+    match Connection::build(connection_string).connect().await {
+        Ok(()) | Err(Error::AlreadyConnected) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
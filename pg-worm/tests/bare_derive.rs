@@ -0,0 +1,32 @@
+//! The derive only needs the macro itself to be imported,
+//! even if common names are shadowed.
+
+#![allow(dead_code)]
+
+use pg_worm::Model;
+
+type Result<T> = std::result::Result<T, ()>;
+
+#[derive(Model)]
+#[table(archive_to = "pebble_archive")]
+struct Pebble {
+    #[column(primary_key, auto)]
+    id: i64,
+    #[column(unique, max_length = 10)]
+    name: String,
+    #[column(sensitive)]
+    owner: Option<String>,
+    weight: Option<f64>,
+}
+
+#[test]
+fn derives_without_imports() -> Result<()> {
+    assert_eq!(Pebble::COLUMNS.len(), 4);
+    assert_eq!(Pebble::name.full_name(), "pebble.name");
+
+    let id = 1_i64;
+    let query = pg_worm::query!(Pebble, "SELECT * FROM pebble WHERE id = {id}");
+    assert_eq!(query.statement(), "SELECT * FROM pebble WHERE id = $1");
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+#![cfg(all(feature = "serde", feature = "serde-json"))]
+#![allow(dead_code)]
+
+use pg_worm::prelude::*;
+
+#[derive(Model)]
+#[table(serde)]
+struct Recipe {
+    #[column(primary_key, auto)]
+    id: i64,
+    title: String,
+    servings: Option<i32>,
+}
+
+#[test]
+fn insert_struct_roundtrip() {
+    let insert: RecipeInsert =
+        serde_json::from_str(r#"{"title": "Bread", "servings": 4}"#).unwrap();
+    assert_eq!(insert.title, "Bread");
+    assert_eq!(insert.servings, Some(4));
+
+    let value = serde_json::to_value(&insert).unwrap();
+    assert_eq!(value, serde_json::json!({"title": "Bread", "servings": 4}));
+}
@@ -0,0 +1,8 @@
+//! Compile errors of the derive macros, checked against
+//! the `.stderr` file next to each case in `tests/ui`.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,17 @@
+use std::time::SystemTime;
+
+use pg_worm::prelude::*;
+
+struct Fancy;
+
+#[derive(Model)]
+#[table(bogus = 1)]
+struct Book {
+    #[column(foo)]
+    id: i64,
+    cover: Fancy,
+    #[column(updated_at, auto)]
+    changed: SystemTime,
+}
+
+fn main() {}
@@ -0,0 +1,60 @@
+//! The workspace only publishes one API: `pg_worm`, along with its
+//! proc macros. There must be no other library users could add instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// Get the paths listed in the `members` of the workspace's manifest.
+fn members(root: &Path) -> Vec<PathBuf> {
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).unwrap();
+    let members = manifest
+        .split_once("members = [")
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(members, _)| members)
+        .expect("the workspace lists its members");
+
+    members
+        .split(',')
+        .map(|member| member.trim().trim_matches('"'))
+        .filter(|member| !member.is_empty())
+        .map(|member| root.join(member))
+        .collect()
+}
+
+#[test]
+fn one_api_surface() {
+    let root = workspace_root();
+
+    // No legacy crate at the root of the workspace
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).unwrap();
+    assert!(!manifest.contains("[package]"));
+    assert!(!root.join("src").exists());
+
+    // Everything but `pg-worm` is a proc macro, the identifier quoting
+    // shared with it, or not published
+    let mut published = Vec::new();
+    for member in members(&root) {
+        let manifest = fs::read_to_string(member.join("Cargo.toml")).unwrap();
+        if manifest.contains("publish = false")
+            || manifest.contains("proc-macro = true")
+            || member.ends_with("pg-worm-ident")
+        {
+            continue;
+        }
+        published.push(member.file_name().unwrap().to_owned());
+    }
+    assert_eq!(published, ["pg-worm"]);
+
+    // None of the legacy API is exported
+    let lib = fs::read_to_string(root.join("pg-worm/src/lib.rs")).unwrap();
+    for legacy in ["PgWormError", "get_client", "create_sql", "Entity"] {
+        assert!(!lib.contains(legacy), "`{legacy}` is exported");
+    }
+}